@@ -3,28 +3,33 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, OnceLock};
 
 #[derive(Debug, Eq, PartialEq, Hash)]
-enum MessageID {
+pub enum MessageID {
     Electrical,
     Hydraulic,
+    // SSPC status from the SPDA and the commands for it
+    SspcStatus,
+    SspcCommand,
 }
 
-struct CommunicationBus {
+#[derive(Default)]
+pub struct CommunicationBus {
     messages: Mutex<HashMap<MessageID, Vec<Arc<dyn Any + Send + Sync>>>>,
 }
 
 impl CommunicationBus {
-    fn new() -> Self {
+    // A bus of its own, for equipment that talks only to each other
+    pub fn new() -> Self {
         Self {
             messages: Mutex::new(HashMap::new()),
         }
     }
 
-    fn instance() -> &'static Arc<Self> {
+    pub fn instance() -> &'static Arc<Self> {
         static INSTANCE: OnceLock<Arc<CommunicationBus>> = OnceLock::new();
         INSTANCE.get_or_init(|| Arc::new(CommunicationBus::new()))
     }
 
-    fn send<T: 'static + Send + Sync>(&self, id: MessageID, message: T) {
+    pub fn send<T: 'static + Send + Sync>(&self, id: MessageID, message: T) {
         let mut messages = self.messages.lock().unwrap();
         messages
             .entry(id)
@@ -32,7 +37,7 @@ impl CommunicationBus {
             .push(Arc::new(message));
     }
 
-    fn receive<T: 'static + Send + Sync + Clone>(&self, id: MessageID) -> Vec<T> {
+    pub fn receive<T: 'static + Send + Sync + Clone>(&self, id: MessageID) -> Vec<T> {
         let messages = self.messages.lock().unwrap();
         if let Some(vec) = messages.get(&id) {
             vec.iter()
@@ -42,4 +47,19 @@ impl CommunicationBus {
             Vec::new()
         }
     }
+
+    // Receive and remove every message sent with `id`
+    pub fn take<T: 'static + Send + Sync + Clone>(&self, id: MessageID) -> Vec<T> {
+        let mut messages = self.messages.lock().unwrap();
+        messages
+            .remove(&id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|msg| msg.downcast::<T>().ok().map(|arc| (*arc).clone()))
+            .collect()
+    }
+
+    pub fn clear(&self, id: MessageID) {
+        self.messages.lock().unwrap().remove(&id);
+    }
 }
@@ -2,6 +2,8 @@ use eframe::egui;
 
 use crate::if_gui;
 use crate::systems::E170Systems;
+use crate::systems::electrical::load_analysis::{LoadAnalysis, load_phases, parse_phases};
+use crate::systems::electrical::network_export::NetworkSnapshot;
 use crate::utils::delta_time::DeltaTime;
 
 pub fn entry_point() {
@@ -35,13 +37,151 @@ pub fn entry_point() {
     });
 }
 
+const ELA_PHASES: &str = include_str!("../../assets/electrical/ela_phases.toml");
+const COORDINATION_SETTLE_TIME: f32 = 20.0; // Seconds
+const BOLTED_FAULT_RESISTANCE: f64 = 0.001; // Ohm
+const SNAPSHOT_TIME: f32 = 20.0; // Seconds
+
+// Headless electrical load analysis: `ela [phases.toml] [--output report.txt]`
+pub fn electrical_load_analysis(args: &[String]) {
+    let (phases_file, output) = match args {
+        [] => (None, None),
+        [flag, output] if flag == "--output" => (None, Some(output)),
+        [phases_file] if !phases_file.starts_with("--") => (Some(phases_file), None),
+        [phases_file, flag, output] if !phases_file.starts_with("--") && flag == "--output" => {
+            (Some(phases_file), Some(output))
+        }
+        _ => usage("ela [phases.toml] [--output report.txt]"),
+    };
+    let mut systems = aircraft();
+    let phases = match phases_file {
+        Some(phases_file) => load_phases(phases_file),
+        None => parse_phases(ELA_PHASES),
+    };
+    let analysis = phases.and_then(|phases| LoadAnalysis::run(&mut systems, &phases));
+    let analysis = match analysis {
+        Ok(analysis) => analysis,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("⚠️ {error}");
+            }
+            std::process::exit(1);
+        }
+    };
+
+    write_report(&analysis.to_string(), output);
+}
+
+// Short circuit coordination study of the breakers: `coordination [--output report.txt]`
+pub fn breaker_coordination_study(args: &[String]) {
+    let output = match args {
+        [] => None,
+        [flag, output] if flag == "--output" => Some(output),
+        _ => usage("coordination [--output report.txt]"),
+    };
+
+    // Let the generators come online first, the fault currents depend on the sources feeding the network
+    let mut systems = aircraft();
+    let mut elapsed = 0.0;
+    while elapsed < COORDINATION_SETTLE_TIME {
+        systems.update(0.1);
+        elapsed += 0.1;
+    }
+
+    let study = systems
+        .electrical_system()
+        .coordination_study(BOLTED_FAULT_RESISTANCE);
+    write_report(&study.to_string(), output);
+}
+
+// Network state after running for a while, saved to diff against another run:
+// `snapshot [--time seconds] [--dot] [--output snapshot.json]`
+pub fn network_snapshot(args: &[String]) {
+    let mut time = SNAPSHOT_TIME;
+    let mut dot = false;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--time" => match args.next().and_then(|time| time.parse::<f32>().ok()) {
+                Some(seconds) if seconds.is_finite() && seconds >= 0.0 => time = seconds,
+                _ => usage("snapshot [--time seconds] [--dot] [--output snapshot.json]"),
+            },
+            "--dot" => dot = true,
+            "--output" => match args.next() {
+                Some(path) => output = Some(path),
+                None => usage("snapshot [--time seconds] [--dot] [--output snapshot.json]"),
+            },
+            _ => usage("snapshot [--time seconds] [--dot] [--output snapshot.json]"),
+        }
+    }
+
+    let mut systems = aircraft();
+    let mut elapsed = 0.0;
+    while elapsed < time {
+        systems.update(0.1);
+        elapsed += 0.1;
+    }
+
+    let system = systems.electrical_system();
+    let report = if dot {
+        system.to_dot()
+    } else {
+        system.to_json()
+    };
+    write_report(&report, output);
+}
+
+// What changed between two runs: `diff before.json after.json [--output report.txt]`
+pub fn network_diff(args: &[String]) {
+    let (before, after, output) = match args {
+        [before, after] => (before, after, None),
+        [before, after, flag, output] if flag == "--output" => (before, after, Some(output)),
+        _ => usage("diff before.json after.json [--output report.txt]"),
+    };
+    let read = |path: &String| {
+        std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|json| NetworkSnapshot::from_json(&json).map_err(|error| error.to_string()))
+            .unwrap_or_else(|error| {
+                eprintln!("⚠️ cannot read snapshot {path}: {error}");
+                std::process::exit(1);
+            })
+    };
+
+    let diff = read(before).diff(&read(after));
+    write_report(&diff.to_string(), output);
+}
+
+fn aircraft() -> E170Systems {
+    E170Systems::new().unwrap_or_else(|error| {
+        eprintln!("⚠️ {error}");
+        std::process::exit(1);
+    })
+}
+
+fn usage(usage: &str) -> ! {
+    eprintln!("⚠️ usage: {usage}");
+    std::process::exit(1);
+}
+
+fn write_report(report: &str, output: Option<&String>) {
+    println!("\n{report}");
+    if let Some(output) = output
+        && let Err(error) = std::fs::write(output, report)
+    {
+        eprintln!("⚠️ cannot write {output}: {error}");
+        std::process::exit(1);
+    }
+}
+
 fn external_entry_point() {
     // we will create a simulation loop here since we are running outside the Simulator
     use std::time::Duration;
 
     let mut delta_time = DeltaTime::new();
 
-    let mut systems = E170Systems::new();
+    let mut systems = aircraft();
 
     loop {
         // we will first fetch the simulation data and update our state
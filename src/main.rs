@@ -1,4 +1,7 @@
-use entry_point::entry_point;
+use entry_point::{
+    breaker_coordination_study, electrical_load_analysis, entry_point, network_diff,
+    network_snapshot,
+};
 
 pub mod communication_bus;
 pub mod entry_point;
@@ -8,5 +11,12 @@ pub mod traits;
 pub mod utils;
 
 fn main() {
-    entry_point();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("ela") => electrical_load_analysis(&args[1..]),
+        Some("coordination") => breaker_coordination_study(&args[1..]),
+        Some("snapshot") => network_snapshot(&args[1..]),
+        Some("diff") => network_diff(&args[1..]),
+        _ => entry_point(),
+    }
 }
@@ -0,0 +1,373 @@
+// Breaker coordination study.
+// Puts a bolted short on every component of the network in turn, solves the network with it and works out
+// from the trip curves which breaker or SSPC clears the fault first. A selective network clears every
+// fault with the protective device nearest to it, anything upstream tripping first (or at the same time)
+// takes more of the aircraft down than it has to and is flagged, as is a fault that nothing clears. A fault
+// with no device between it and the sources is left to the source protection, unless a battery feeds it:
+// nothing on the battery side ever clears it, so it counts as not cleared.
+// The study only reads the network, the breakers keep their state and no fault is left behind.
+
+use crate::systems::electrical::ElectricalSystem;
+use crate::systems::electrical::components::dc::battery::Battery;
+use crate::systems::electrical::components::shared::circuit_breaker::CircuitBreaker;
+use crate::systems::electrical::components::shared::solid_state_power_controller::SolidStatePowerController;
+use crate::systems::electrical::solver::{CircuitModel, NetworkSolution};
+
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use std::collections::VecDeque;
+use std::fmt;
+use uom::si::electric_current::ampere;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+// Below this the short is on a dead part of the network
+const MIN_FAULT_CURRENT: f64 = 0.1; // Ampere
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Coordination {
+    // The nearest device trips first
+    Selective,
+    // A device further upstream trips before (or together with) the nearest one
+    UpstreamTripsFirst,
+    // No device trips, or only a battery feeds it with no device in between: the fault stays on the network
+    NotCleared,
+    // No breaker between the short and the sources, it is down to them (GCU, current limit) to clear it
+    SourceProtected,
+    // Nothing feeds the faulted component
+    Unpowered,
+}
+
+pub struct ShortCircuitResult {
+    pub location: String,
+    pub fault_current: ElectricCurrent,
+    // Protective device nearest upstream of the short that carries fault current
+    pub nearest: Option<String>,
+    pub first_trip: Option<(String, Time)>,
+    pub coordination: Coordination,
+}
+
+pub struct CoordinationStudy {
+    pub results: Vec<ShortCircuitResult>,
+}
+
+impl CoordinationStudy {
+    pub fn violations(&self) -> impl Iterator<Item = &ShortCircuitResult> {
+        self.results.iter().filter(|result| {
+            matches!(
+                result.coordination,
+                Coordination::UpstreamTripsFirst | Coordination::NotCleared
+            )
+        })
+    }
+}
+
+impl fmt::Display for CoordinationStudy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BREAKER COORDINATION STUDY")?;
+        writeln!(
+            f,
+            "{:<20} {:>9} {:<20} {:<20} {:>9}",
+            "Short at", "Fault A", "Nearest", "First trip", "Time s"
+        )?;
+        for result in &self.results {
+            if result.coordination == Coordination::Unpowered {
+                continue;
+            }
+            let (first_trip, time) = match &result.first_trip {
+                Some((name, time)) => (name.as_str(), format!("{:.3}", time.get::<second>())),
+                None => ("-", "-".to_string()),
+            };
+            let flag = match result.coordination {
+                Coordination::UpstreamTripsFirst => " ⚠️ NOT SELECTIVE",
+                Coordination::NotCleared => " ⚠️ NOT CLEARED",
+                Coordination::SourceProtected => " (source protection)",
+                _ => "",
+            };
+            writeln!(
+                f,
+                "{:<20} {:>9.1} {:<20} {first_trip:<20} {time:>9}{flag}",
+                result.location,
+                result.fault_current.get::<ampere>(),
+                result.nearest.as_deref().unwrap_or("-"),
+            )?;
+        }
+        writeln!(f, "\n{} violation(s)", self.violations().count())?;
+        for violation in self.violations() {
+            let reason = match (violation.coordination, &violation.nearest) {
+                (Coordination::UpstreamTripsFirst, _) => "not selective",
+                (_, None) => "not cleared, fed by a battery",
+                _ => "not cleared",
+            };
+            writeln!(f, "  {}: {reason}", violation.location)?;
+        }
+        Ok(())
+    }
+}
+
+impl ElectricalSystem {
+    // Shorts every component to ground through `fault_resistance` Ohm, one at a time
+    pub fn coordination_study(&self, fault_resistance: f64) -> CoordinationStudy {
+        let mut nodes: Vec<_> = self.component_nodes().collect();
+        nodes.sort();
+        let unfaulted = self.solve_network();
+        let results = nodes
+            .into_iter()
+            .map(|node| self.short_circuit(node, fault_resistance, &unfaulted))
+            .collect();
+        CoordinationStudy { results }
+    }
+
+    fn short_circuit(
+        &self,
+        location: NodeIndex,
+        fault_resistance: f64,
+        unfaulted: &NetworkSolution,
+    ) -> ShortCircuitResult {
+        let mut network = self.nodal_network(None);
+        network.add_shunt(location, ElectricalResistance::new::<ohm>(fault_resistance));
+        let solution = network.solve();
+
+        let fault_current = solution
+            .shunt_current
+            .last()
+            .copied()
+            .unwrap_or_default()
+            .abs();
+        let name = |node| self.component_name(node).unwrap_or("?").to_string();
+        let mut result = ShortCircuitResult {
+            location: name(location),
+            fault_current,
+            nearest: None,
+            first_trip: None,
+            coordination: Coordination::Unpowered,
+        };
+        if fault_current.get::<ampere>() < MIN_FAULT_CURRENT {
+            return result;
+        }
+
+        // Every device that sees the fault with the time it takes to trip on it
+        let current_at = |node| {
+            solution
+                .component_current
+                .get(&node)
+                .copied()
+                .unwrap_or_default()
+        };
+        let trips: Vec<(NodeIndex, Time)> = self
+            .component_nodes()
+            .filter_map(|node| Some((node, self.trip_time(node, current_at(node))??)))
+            .collect();
+        let first_trip = trips
+            .iter()
+            .min_by(|(_, a), (_, b)| a.value.total_cmp(&b.value))
+            .copied();
+        let nearest = self.nearest_device_upstream(location, |node| {
+            current_at(node).get::<ampere>().abs() >= MIN_FAULT_CURRENT
+        });
+        // The short draws more out of a battery that feeds it, whichever way round the wires run
+        let battery_fed = self
+            .component_nodes()
+            .filter(|node| self.handle_at::<Battery>(*node).is_some())
+            .any(|node| {
+                let before = unfaulted.component_current.get(&node).copied();
+                (current_at(node) - before.unwrap_or_default()).get::<ampere>() >= MIN_FAULT_CURRENT
+            });
+
+        result.nearest = nearest.map(name);
+        result.first_trip = first_trip.map(|(node, time)| (name(node), time));
+        result.coordination = match first_trip {
+            _ if nearest.is_none() && battery_fed => Coordination::NotCleared,
+            _ if nearest.is_none() => Coordination::SourceProtected,
+            None => Coordination::NotCleared,
+            Some(_) => {
+                // Devices are only told apart by their trip times, equal times are a race
+                let nearest_time = trips
+                    .iter()
+                    .find(|(node, _)| Some(*node) == nearest)
+                    .map(|(_, time)| *time);
+                let selective = nearest_time.is_some_and(|nearest_time| {
+                    trips
+                        .iter()
+                        .all(|(node, time)| Some(*node) == nearest || *time > nearest_time)
+                });
+                if selective {
+                    Coordination::Selective
+                } else {
+                    Coordination::UpstreamTripsFirst
+                }
+            }
+        };
+        result
+    }
+
+    // Trip time of the breaker or SSPC at `node`, None if `node` is not one of them
+    fn trip_time(&self, node: NodeIndex, current: ElectricCurrent) -> Option<Option<Time>> {
+        if let Some(breaker) = self.handle_at::<CircuitBreaker>(node) {
+            let breaker = self.get(breaker)?;
+            return Some(
+                breaker
+                    .is_closed()
+                    .then(|| breaker.trip_time(current))
+                    .flatten(),
+            );
+        }
+        let sspc = self.get(self.handle_at::<SolidStatePowerController>(node)?)?;
+        Some(sspc.is_closed().then(|| sspc.trip_time(current)).flatten())
+    }
+
+    // Walks back from the short towards the sources, the short sits on the input of `location` so a
+    // device there does not see it. Sources and converters end the walk, past them the fault current is
+    // only what they let through.
+    fn nearest_device_upstream(
+        &self,
+        location: NodeIndex,
+        carries_fault: impl Fn(NodeIndex) -> bool,
+    ) -> Option<NodeIndex> {
+        let mut visited = vec![location];
+        let mut pending = VecDeque::from([location]);
+        while let Some(current) = pending.pop_front() {
+            for previous in self.graph.neighbors_directed(current, Direction::Incoming) {
+                if visited.contains(&previous) {
+                    continue;
+                }
+                visited.push(previous);
+                if self
+                    .trip_time(previous, ElectricCurrent::default())
+                    .is_some()
+                    && carries_fault(previous)
+                {
+                    return Some(previous);
+                }
+                let feeds_fault = self.components.get(&previous).is_some_and(|component| {
+                    matches!(
+                        component.circuit_model(),
+                        CircuitModel::Source { .. } | CircuitModel::Converter { .. }
+                    )
+                });
+                if !feeds_fault {
+                    pending.push_back(previous);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::components::ac::ground_power::GroundPower;
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::components::shared::circuit_breaker::TripBand;
+    use uom::si::electric_potential::volt;
+    use uom::si::power::watt;
+
+    const BOLTED: f64 = 0.001; // Ohm
+
+    fn bus() -> Bus {
+        Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        }
+    }
+
+    // Battery, main bus, main breaker, sub bus, branch breaker, load bus
+    fn network(main: (f64, TripBand), branch: (f64, TripBand)) -> ElectricalSystem {
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("BATT", 24.0, 44.0, 0.02, 0.9);
+        let battery = system.add_component("BATT", battery).unwrap();
+        let main_bus = system.add_component("MAIN BUS", bus()).unwrap();
+        let main = CircuitBreaker::new("MAIN CB", main.0, main.1, false, 0.0);
+        let main = system.add_component("MAIN CB", main).unwrap();
+        let sub_bus = system.add_component("SUB BUS", bus()).unwrap();
+        let branch = CircuitBreaker::new("BRANCH CB", branch.0, branch.1, false, 0.0);
+        let branch = system.add_component("BRANCH CB", branch).unwrap();
+        let load_bus = system.add_component("LOAD BUS", bus()).unwrap();
+        system.connect_no_resistance(battery, main_bus);
+        system.connect_no_resistance(main_bus, main);
+        system.connect_no_resistance(main, sub_bus);
+        system.connect_no_resistance(sub_bus, branch);
+        system.connect_no_resistance(branch, load_bus);
+        system
+    }
+
+    fn result<'a>(study: &'a CoordinationStudy, location: &str) -> &'a ShortCircuitResult {
+        study
+            .results
+            .iter()
+            .find(|result| result.location == location)
+            .unwrap()
+    }
+
+    #[test]
+    fn nearest_breaker_tripping_first_is_selective() {
+        let system = network((50.0, TripBand::Standard), (5.0, TripBand::Fast));
+        let study = system.coordination_study(BOLTED);
+
+        let load_bus = result(&study, "LOAD BUS");
+        assert_eq!(load_bus.coordination, Coordination::Selective);
+        assert_eq!(load_bus.nearest.as_deref(), Some("BRANCH CB"));
+        assert_eq!(load_bus.first_trip.as_ref().unwrap().0, "BRANCH CB");
+        assert!(load_bus.fault_current.get::<ampere>() > 500.0);
+        let sub_bus = result(&study, "SUB BUS");
+        assert_eq!(sub_bus.coordination, Coordination::Selective);
+        assert_eq!(sub_bus.nearest.as_deref(), Some("MAIN CB"));
+    }
+
+    #[test]
+    fn upstream_breaker_tripping_first_is_flagged() {
+        let system = network((5.0, TripBand::Fast), (50.0, TripBand::Slow));
+        let study = system.coordination_study(BOLTED);
+
+        let load_bus = result(&study, "LOAD BUS");
+        assert_eq!(load_bus.coordination, Coordination::UpstreamTripsFirst);
+        assert_eq!(load_bus.nearest.as_deref(), Some("BRANCH CB"));
+        assert_eq!(load_bus.first_trip.as_ref().unwrap().0, "MAIN CB");
+        assert!(
+            study
+                .violations()
+                .any(|violation| violation.location == "LOAD BUS")
+        );
+        assert!(study.to_string().contains("  LOAD BUS: not selective\n"));
+    }
+
+    #[test]
+    fn battery_fed_fault_with_no_breaker_is_not_cleared() {
+        let system = network((50.0, TripBand::Standard), (5.0, TripBand::Fast));
+        let study = system.coordination_study(BOLTED);
+
+        for location in ["BATT", "MAIN BUS"] {
+            let result = result(&study, location);
+            assert_eq!(result.coordination, Coordination::NotCleared, "{location}");
+            assert_eq!(result.nearest, None);
+        }
+        assert!(
+            study
+                .to_string()
+                .contains("  MAIN BUS: not cleared, fed by a battery\n")
+        );
+    }
+
+    #[test]
+    fn ground_power_fed_fault_with_no_breaker_is_left_to_the_source() {
+        let mut system = ElectricalSystem::new();
+        let mut gpu = GroundPower::new("GPU", 115.0, 400.0, 0.05);
+        gpu.connect();
+        gpu.set_selected(true);
+        let gpu = system.add_component("GPU", gpu).unwrap();
+        let ac_bus = system.add_component("AC BUS", bus()).unwrap();
+        system.connect_no_resistance(gpu, ac_bus);
+        // Qualifies the ground power
+        for _ in 0..40 {
+            system.update_system(0.1);
+        }
+
+        let study = system.coordination_study(BOLTED);
+        let ac_bus = result(&study, "AC BUS");
+        assert_eq!(ac_bus.coordination, Coordination::SourceProtected);
+        assert!(ac_bus.fault_current.get::<ampere>() > 1000.0);
+        assert_eq!(study.violations().count(), 0);
+    }
+}
@@ -0,0 +1,832 @@
+// Bus Power Control Unit.
+// Decides which source feeds the essential buses and which loads the remaining sources can carry.
+// Source availability comes from the GCUs (power ready) or whoever owns the source, the essential bus
+// transfers sense the voltage of the buses that can feed them and pick the highest priority one that is
+// powered. Loads are shed by schedule when sources are lost and restored once power has been back for a
+// while. Shedding holds a load off without touching its switch, a crew action on a shed load takes effect
+// once it is restored. Sources that can feed the same bus are ranked, only the highest priority available
+// one is permitted to connect. The bus ties let a single source carry both main buses: an AC bus tie
+// closes when the generator of its bus is off line, a DC bus tie when one of the TRUs it joins has failed.
+// The contactor coils are fed from the coil supply buses (the DC ESS bus, and the hot battery bus so the
+// batteries can bring the DC ESS bus up), with every supply dead the contactors drop out whatever the
+// BPCU commands.
+
+use crate::systems::electrical::components::ac::air_driven_generator::AirDrivenGenerator;
+use crate::systems::electrical::components::ac::generic_ac_component::GenericAcComponent;
+use crate::systems::electrical::components::dc::generic_dc_component::GenericDcComponent;
+use crate::systems::electrical::components::dc::transformer_rectifier_unit::TransformerRectifierUnit;
+use crate::systems::electrical::components::shared::bus::Bus;
+use crate::systems::electrical::components::shared::contactor::Contactor;
+use crate::systems::electrical::components::shared::motor::Motor;
+use crate::systems::electrical::{ComponentHandle, ElectricalSystem};
+
+use uom::si::electric_potential::volt;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+const TRANSFER_BACK_DELAY: f64 = 2.0; // seconds a higher priority feed must be healthy before we return to it
+const RESTORE_DELAY: f64 = 5.0; // seconds the improved configuration must hold before shed loads come back
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LoadShedLevel {
+    // Two or more main sources, or external power
+    Normal,
+    // A single main source left, galleys and non-essential loads go
+    SingleSource,
+    // No main source, only the essential buses stay powered (ADG or batteries)
+    Emergency,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SourceKind {
+    Main,
+    // Ground power, sized for the whole aircraft on the ground so it never sheds anything on its own
+    External,
+    Emergency,
+}
+
+// Something the BPCU can switch off to shed its load
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShedLoad {
+    Dc(ComponentHandle<GenericDcComponent>),
+    Ac(ComponentHandle<GenericAcComponent>),
+    Motor(ComponentHandle<Motor>),
+    // Bus contactor, sheds everything on the bus behind it
+    Contactor(ComponentHandle<Contactor>),
+}
+
+impl ShedLoad {
+    // The named component, if it is something the BPCU can shed
+    pub fn find(system: &ElectricalSystem, name: &str) -> Option<ShedLoad> {
+        system
+            .handle::<GenericDcComponent>(name)
+            .map(ShedLoad::Dc)
+            .or_else(|| system.handle::<GenericAcComponent>(name).map(ShedLoad::Ac))
+            .or_else(|| system.handle::<Motor>(name).map(ShedLoad::Motor))
+            .or_else(|| system.handle::<Contactor>(name).map(ShedLoad::Contactor))
+    }
+}
+
+impl From<ComponentHandle<GenericDcComponent>> for ShedLoad {
+    fn from(handle: ComponentHandle<GenericDcComponent>) -> Self {
+        ShedLoad::Dc(handle)
+    }
+}
+
+impl From<ComponentHandle<GenericAcComponent>> for ShedLoad {
+    fn from(handle: ComponentHandle<GenericAcComponent>) -> Self {
+        ShedLoad::Ac(handle)
+    }
+}
+
+impl From<ComponentHandle<Motor>> for ShedLoad {
+    fn from(handle: ComponentHandle<Motor>) -> Self {
+        ShedLoad::Motor(handle)
+    }
+}
+
+impl From<ComponentHandle<Contactor>> for ShedLoad {
+    fn from(handle: ComponentHandle<Contactor>) -> Self {
+        ShedLoad::Contactor(handle)
+    }
+}
+
+// Where the BPCU senses whether a feed is powered
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SensePoint {
+    Bus(ComponentHandle<Bus>),
+    // The ADG feeds its transfer contactor directly, there is no bus in between
+    Adg(ComponentHandle<AirDrivenGenerator>),
+    // The TRU status, its output terminal shows the bus voltage even when the bus is fed over a tie
+    Tru(ComponentHandle<TransformerRectifierUnit>),
+}
+
+impl SensePoint {
+    // Never powered once the component has been removed from the network
+    fn is_powered(&self, system: &ElectricalSystem, min_voltage: ElectricPotential) -> bool {
+        let voltage = match *self {
+            SensePoint::Bus(bus) => system.get(bus).and(system.get_voltage(bus)),
+            SensePoint::Adg(adg) => system.get(adg).and(system.get_voltage(adg)),
+            SensePoint::Tru(tru) => {
+                return system
+                    .get(tru)
+                    .is_some_and(|tru| tru.converter().is_operating());
+            }
+        };
+        voltage.is_some_and(|voltage| voltage >= min_voltage)
+    }
+}
+
+impl From<ComponentHandle<Bus>> for SensePoint {
+    fn from(handle: ComponentHandle<Bus>) -> Self {
+        SensePoint::Bus(handle)
+    }
+}
+
+impl From<ComponentHandle<AirDrivenGenerator>> for SensePoint {
+    fn from(handle: ComponentHandle<AirDrivenGenerator>) -> Self {
+        SensePoint::Adg(handle)
+    }
+}
+
+impl From<ComponentHandle<TransformerRectifierUnit>> for SensePoint {
+    fn from(handle: ComponentHandle<TransformerRectifierUnit>) -> Self {
+        SensePoint::Tru(handle)
+    }
+}
+
+struct Source {
+    name: String,
+    kind: SourceKind,
+    is_available: bool,
+    is_permitted: bool,
+}
+
+struct Feed {
+    contactor: ComponentHandle<Contactor>,
+    // Bus (or source) whose voltage says this feed can power the transferred bus
+    sensed: SensePoint,
+}
+
+struct BusTransfer {
+    name: String,
+    feeds: Vec<Feed>,
+    min_voltage: ElectricPotential,
+    selected: Option<usize>,
+    transfer_back_timer: Time,
+}
+
+// Joins the bus of `source` to the tie bus
+struct BusTie {
+    contactor: ComponentHandle<Contactor>,
+    source: String,
+}
+
+// Joins two DC buses, each fed by its own converter
+struct DcBusTie {
+    contactor: ComponentHandle<Contactor>,
+    converters: Vec<SensePoint>,
+}
+
+pub struct BusPowerControlUnit {
+    name: String,
+    sources: Vec<Source>,
+    priorities: Vec<Vec<String>>,
+    transfers: Vec<BusTransfer>,
+    bus_ties: Vec<BusTie>,
+    dc_bus_ties: Vec<DcBusTie>,
+    shed_loads: Vec<(ShedLoad, LoadShedLevel)>,
+    coil_supplies: Vec<ComponentHandle<Bus>>,
+    // Loads currently shed
+    shed: Vec<ShedLoad>,
+
+    level: LoadShedLevel,
+    restore_timer: Time,
+}
+
+impl BusPowerControlUnit {
+    pub fn new(name: &str) -> Self {
+        BusPowerControlUnit {
+            name: name.to_string(),
+            sources: Vec::new(),
+            priorities: Vec::new(),
+            transfers: Vec::new(),
+            bus_ties: Vec::new(),
+            dc_bus_ties: Vec::new(),
+            shed_loads: Vec::new(),
+            coil_supplies: Vec::new(),
+            shed: Vec::new(),
+
+            level: LoadShedLevel::Normal,
+            restore_timer: Time::new::<second>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add_source(&mut self, name: &str, kind: SourceKind) {
+        self.sources.push(Source {
+            name: name.to_string(),
+            kind,
+            is_available: false,
+            is_permitted: true,
+        });
+    }
+
+    // Sources sharing a bus, highest priority first. A source listed for several buses is permitted as soon
+    // as one of them has no higher priority source available.
+    pub fn add_source_priority(&mut self, sources: &[&str]) {
+        self.priorities
+            .push(sources.iter().map(|name| name.to_string()).collect());
+    }
+
+    // Whether the source may close its contactor onto the bus
+    pub fn is_source_permitted(&self, name: &str) -> bool {
+        self.sources
+            .iter()
+            .find(|source| source.name == name)
+            .is_none_or(|source| source.is_permitted)
+    }
+
+    pub fn set_source_available(&mut self, name: &str, available: bool) {
+        if let Some(source) = self.sources.iter_mut().find(|source| source.name == name) {
+            source.is_available = available;
+        }
+    }
+
+    // `feeds` are (transfer contactor, sensed bus) pairs in priority order
+    pub fn add_bus_transfer(
+        &mut self,
+        name: &str,
+        feeds: &[(ComponentHandle<Contactor>, SensePoint)],
+        min_voltage: f64,
+    ) {
+        self.transfers.push(BusTransfer {
+            name: name.to_string(),
+            feeds: feeds
+                .iter()
+                .map(|(contactor, sensed)| Feed {
+                    contactor: *contactor,
+                    sensed: *sensed,
+                })
+                .collect(),
+            min_voltage: ElectricPotential::new::<volt>(min_voltage),
+            selected: None,
+            transfer_back_timer: Time::new::<second>(0.0),
+        });
+    }
+
+    // Bus tie contactor of the bus `source` feeds. Sources without a bus tie of their own sit on the tie
+    // bus (APU, ground power) and reach every bus whose tie is closed.
+    pub fn add_bus_tie(&mut self, contactor: ComponentHandle<Contactor>, source: &str) {
+        self.bus_ties.push(BusTie {
+            contactor,
+            source: source.to_string(),
+        });
+    }
+
+    // Closes when some of the `converters` feeding the joined buses work and the others do not
+    pub fn add_dc_bus_tie(
+        &mut self,
+        contactor: ComponentHandle<Contactor>,
+        converters: &[SensePoint],
+    ) {
+        self.dc_bus_ties.push(DcBusTie {
+            contactor,
+            converters: converters.to_vec(),
+        });
+    }
+
+    pub fn is_bus_tie_closed(&self, system: &ElectricalSystem, source: &str) -> bool {
+        self.bus_ties
+            .iter()
+            .find(|tie| tie.source == source)
+            .and_then(|tie| system.get(tie.contactor))
+            .is_some_and(|contactor| contactor.is_closed())
+    }
+
+    // The load (or bus contactor) is switched off once the shed level reaches `level`
+    pub fn add_shed_load(&mut self, load: impl Into<ShedLoad>, level: LoadShedLevel) {
+        self.shed_loads.push((load.into(), level));
+    }
+
+    // Bus the contactor coils are fed from, the coils see the highest of the supplies
+    pub fn add_coil_supply(&mut self, bus: ComponentHandle<Bus>) {
+        self.coil_supplies.push(bus);
+    }
+
+    fn coil_voltage(&self, system: &ElectricalSystem) -> ElectricPotential {
+        self.coil_supplies
+            .iter()
+            .filter(|bus| system.get(**bus).is_some())
+            .filter_map(|bus| system.get_voltage(*bus))
+            .fold(ElectricPotential::new::<volt>(0.0), ElectricPotential::max)
+    }
+
+    pub fn shed_level(&self) -> LoadShedLevel {
+        self.level
+    }
+
+    pub fn is_shed(&self, load: impl Into<ShedLoad>) -> bool {
+        self.shed.contains(&load.into())
+    }
+
+    // Index of the feed currently powering the named bus, 0 being the normal one
+    pub fn selected_feed(&self, bus: &str) -> Option<usize> {
+        self.transfers
+            .iter()
+            .find(|transfer| transfer.name == bus)
+            .and_then(|transfer| transfer.selected)
+    }
+
+    pub fn update(&mut self, dt: f32, system: &mut ElectricalSystem) {
+        let dt = Time::new::<second>(dt as f64);
+        self.update_source_priority();
+        self.update_shed_level(dt);
+        let coil = self.coil_voltage(system);
+        self.update_bus_ties(system, coil);
+        for transfer in &mut self.transfers {
+            transfer.update(dt, &self.name, system, coil);
+        }
+        self.update_load_shed(system, coil);
+    }
+
+    fn update_source_priority(&mut self) {
+        for source in &mut self.sources {
+            source.is_permitted = !self
+                .priorities
+                .iter()
+                .any(|priority| priority.contains(&source.name));
+        }
+        for priority in &self.priorities {
+            let mut higher_available = false;
+            for name in priority {
+                if let Some(source) = self.sources.iter_mut().find(|source| &source.name == name) {
+                    source.is_permitted |= !higher_available;
+                    higher_available |= source.is_available;
+                }
+            }
+        }
+    }
+
+    fn is_source_online(&self, name: &str) -> bool {
+        self.sources
+            .iter()
+            .any(|source| source.name == name && source.is_available && source.is_permitted)
+    }
+
+    fn update_bus_ties(&self, system: &mut ElectricalSystem, coil: ElectricPotential) {
+        // A bus whose generator is off line takes the tie bus. The tie bus has a source of its own or, if
+        // not, is fed from the other buses through their ties. Two generators are never paralleled: a bus
+        // with its own generator only ties in when some other bus has none and nothing else feeds the tie bus.
+        let on_tie_bus = self.sources.iter().any(|source| {
+            source.kind != SourceKind::Emergency
+                && !self.bus_ties.iter().any(|tie| tie.source == source.name)
+                && source.is_available
+                && source.is_permitted
+        });
+        let bus_needs_tie = self
+            .bus_ties
+            .iter()
+            .any(|tie| !self.is_source_online(&tie.source));
+        for tie in &self.bus_ties {
+            let close = !self.is_source_online(&tie.source) || (bus_needs_tie && !on_tie_bus);
+            command(system, tie.contactor, coil, close);
+        }
+
+        let min_voltage = ElectricPotential::new::<volt>(0.0);
+        for tie in &self.dc_bus_ties {
+            let working = tie
+                .converters
+                .iter()
+                .filter(|converter| converter.is_powered(system, min_voltage))
+                .count();
+            command(
+                system,
+                tie.contactor,
+                coil,
+                working > 0 && working < tie.converters.len(),
+            );
+        }
+    }
+
+    fn update_shed_level(&mut self, dt: Time) {
+        // A source held off its bus by a higher priority one does not add capacity
+        let online = |kind| {
+            self.sources
+                .iter()
+                .filter(|source| source.kind == kind && source.is_available && source.is_permitted)
+                .count()
+        };
+        let level = match online(SourceKind::Main) {
+            _ if online(SourceKind::External) > 0 => LoadShedLevel::Normal,
+            0 => LoadShedLevel::Emergency,
+            1 => LoadShedLevel::SingleSource,
+            _ => LoadShedLevel::Normal,
+        };
+
+        // Shed right away, restore only once the better configuration has settled
+        if level > self.level {
+            eprintln!("⚡ {} LOAD SHED: {:?}", self.name, level);
+            self.level = level;
+            self.restore_timer = Time::new::<second>(0.0);
+        } else if level < self.level {
+            self.restore_timer += dt;
+            if self.restore_timer.get::<second>() >= RESTORE_DELAY {
+                eprintln!("⚡ {} LOAD RESTORE: {:?}", self.name, level);
+                self.level = level;
+                self.restore_timer = Time::new::<second>(0.0);
+            }
+        } else {
+            self.restore_timer = Time::new::<second>(0.0);
+        }
+    }
+
+    fn update_load_shed(&mut self, system: &mut ElectricalSystem, coil: ElectricPotential) {
+        // Applied on every update, a bus contactor is only closed by the BPCU
+        for &(load, level) in &self.shed_loads {
+            let should_shed = self.level >= level;
+            if !load.set_shed(system, coil, should_shed) {
+                continue;
+            }
+            let was_shed = self.shed.contains(&load);
+            if should_shed && !was_shed {
+                self.shed.push(load);
+            } else if !should_shed && was_shed {
+                self.shed.retain(|shed| *shed != load);
+            }
+        }
+    }
+}
+
+impl BusTransfer {
+    fn update(
+        &mut self,
+        dt: Time,
+        bpcu: &str,
+        system: &mut ElectricalSystem,
+        coil: ElectricPotential,
+    ) {
+        let healthy: Vec<bool> = self
+            .feeds
+            .iter()
+            .map(|feed| feed.sensed.is_powered(system, self.min_voltage))
+            .collect();
+        let best = healthy.iter().position(|healthy| *healthy);
+
+        let selected_healthy = self.selected.is_some_and(|selected| healthy[selected]);
+        if !selected_healthy {
+            // Lost the feed, go straight to the next one
+            if best != self.selected {
+                if let Some(best) = best {
+                    eprintln!("⚡ {bpcu}: {} transferred to feed {}", self.name, best + 1);
+                }
+                self.selected = best;
+            }
+            self.transfer_back_timer = Time::new::<second>(0.0);
+        } else if best < self.selected {
+            self.transfer_back_timer += dt;
+            if self.transfer_back_timer.get::<second>() >= TRANSFER_BACK_DELAY {
+                if let Some(best) = best {
+                    eprintln!("⚡ {bpcu}: {} transferred to feed {}", self.name, best + 1);
+                }
+                self.selected = best;
+                self.transfer_back_timer = Time::new::<second>(0.0);
+            }
+        } else {
+            self.transfer_back_timer = Time::new::<second>(0.0);
+        }
+
+        // Break before make, the selected contactor only closes once every other feed is open
+        let others_open = self.feeds.iter().enumerate().all(|(index, feed)| {
+            Some(index) == self.selected
+                || system
+                    .get(feed.contactor)
+                    .is_none_or(|contactor| !contactor.is_closed())
+        });
+        for (index, feed) in self.feeds.iter().enumerate() {
+            command(
+                system,
+                feed.contactor,
+                coil,
+                Some(index) == self.selected && others_open,
+            );
+        }
+    }
+}
+
+impl ShedLoad {
+    // Holds the load off or lets it follow its switch again, a shed bus contactor opens. False once the
+    // component has been removed from the network.
+    fn set_shed(&self, system: &mut ElectricalSystem, coil: ElectricPotential, shed: bool) -> bool {
+        match *self {
+            ShedLoad::Dc(load) => system.get_mut(load).map(|load| load.set_shed(shed)),
+            ShedLoad::Ac(load) => system.get_mut(load).map(|load| load.set_shed(shed)),
+            ShedLoad::Motor(motor) => system.get_mut(motor).map(|motor| motor.set_shed(shed)),
+            ShedLoad::Contactor(contactor) => system.get_mut(contactor).map(|contactor| {
+                contactor.set_coil_voltage(coil);
+                contactor.command(!shed);
+            }),
+        }
+        .is_some()
+    }
+}
+
+fn command(
+    system: &mut ElectricalSystem,
+    contactor: ComponentHandle<Contactor>,
+    coil: ElectricPotential,
+    closed: bool,
+) {
+    if let Some(contactor) = system.get_mut(contactor) {
+        contactor.set_coil_voltage(coil);
+        contactor.command(closed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::E170Systems;
+    use crate::systems::electrical::ElectricalComponent;
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::components::shared::motor::MotorSupply;
+    use crate::systems::electrical::wire::Wire;
+    use uom::si::electric_current::ampere;
+    use uom::si::power::watt;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+    // Same limits the transfers sense
+    const AC_POWERED: f64 = 100.0; // Volt
+    const DC_POWERED: f64 = 18.0; // Volt
+    const DRAWING: f64 = 0.1; // Ampere, a load taking less is off
+    // Feeds of the DC ESS bus transfer
+    const DC_ESS_BATTERY: usize = 2;
+
+    fn run(e170: &mut E170Systems, seconds: f64) {
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            e170.update(TIME_STEP);
+        }
+    }
+
+    fn voltage(e170: &E170Systems, bus: &str) -> f64 {
+        let system = e170.electrical_system();
+        system
+            .node(bus)
+            .and_then(|node| system.get_voltage(node))
+            .map_or(0.0, |voltage| voltage.get::<volt>())
+    }
+
+    // Current the load takes from its bus
+    fn load_current(e170: &E170Systems, name: &str) -> f64 {
+        let system = e170.electrical_system();
+        let current = if let Some(load) = system.handle::<GenericAcComponent>(name) {
+            system.get(load).map(|load| load.get_input_current())
+        } else {
+            system
+                .handle::<GenericDcComponent>(name)
+                .and_then(|load| system.get(load))
+                .map(|load| load.get_input_current())
+        };
+        current.map_or(0.0, |current| current.get::<ampere>())
+    }
+
+    fn is_shed(e170: &E170Systems, name: &str) -> bool {
+        let load = ShedLoad::find(e170.electrical_system(), name).unwrap();
+        e170.bpcu.is_shed(load)
+    }
+
+    fn assert_powered(e170: &E170Systems, ac_buses: &[&str], dc_buses: &[&str]) {
+        for bus in ac_buses {
+            assert!(voltage(e170, bus) > AC_POWERED, "{bus} unpowered");
+        }
+        for bus in dc_buses {
+            assert!(voltage(e170, bus) > DC_POWERED, "{bus} unpowered");
+        }
+    }
+
+    // Cruise on both engine generators with the ground power cart unplugged and the test light on
+    fn in_flight() -> E170Systems {
+        let mut e170 = E170Systems::new().unwrap();
+        e170.set_ground_power_connected(false);
+        e170.set_airspeed(250.0);
+        for engine in [1, 2] {
+            e170.set_engine_n2(engine, 80.0);
+            e170.set_generator_switch(engine, true);
+        }
+        e170.set_load_power("Test Light", true);
+        run(&mut e170, 20.0);
+        e170
+    }
+
+    // Long enough for the transfers to return to their normal feed and the shed loads to come back
+    fn settle(e170: &mut E170Systems) {
+        run(e170, 5.0 + TRANSFER_BACK_DELAY + RESTORE_DELAY);
+    }
+
+    fn assert_normal(e170: &E170Systems) {
+        assert_powered(
+            e170,
+            &["AC Bus 1", "AC Bus 2", "AC ESS Bus"],
+            &["DC Bus 1", "DC Bus 2", "DC ESS Bus"],
+        );
+        assert_eq!(e170.bpcu.shed_level(), LoadShedLevel::Normal);
+        assert_eq!(e170.bpcu.selected_feed("AC ESS Bus"), Some(0));
+        assert_eq!(e170.bpcu.selected_feed("DC ESS Bus"), Some(0));
+        // Each generator on its own bus
+        let system = e170.electrical_system();
+        assert!(!e170.bpcu.is_bus_tie_closed(system, "GEN 1"));
+        assert!(!e170.bpcu.is_bus_tie_closed(system, "GEN 2"));
+        for load in ["Galley", "Test Light"] {
+            assert!(!is_shed(e170, load), "{load} shed");
+            assert!(load_current(e170, load) > DRAWING, "{load} off");
+        }
+    }
+
+    #[test]
+    fn dual_generator_powers_every_bus() {
+        assert_normal(&in_flight());
+    }
+
+    #[test]
+    fn only_engines_1_and_2_can_be_commanded() {
+        let mut e170 = in_flight();
+        for engine in [0, 3, usize::MAX] {
+            assert!(!e170.set_engine_n2(engine, 0.0));
+            assert!(!e170.set_generator_switch(engine, false));
+        }
+        run(&mut e170, 1.0);
+        assert_normal(&e170);
+    }
+
+    #[test]
+    fn single_generator_powers_both_sides_and_sheds_the_galley() {
+        let mut e170 = in_flight();
+        e170.set_generator_switch(1, false);
+        run(&mut e170, 1.0);
+
+        assert_eq!(e170.bpcu.shed_level(), LoadShedLevel::SingleSource);
+        // GEN 2 takes AC Bus 1 over the bus ties, both TRUs keep running
+        assert!(
+            e170.bpcu
+                .is_bus_tie_closed(e170.electrical_system(), "GEN 1")
+        );
+        assert_powered(
+            &e170,
+            &["AC Bus 1", "AC Bus 2", "AC ESS Bus"],
+            &["DC Bus 1", "DC Bus 2", "DC ESS Bus"],
+        );
+        // DC ESS stays on a DC bus, the battery is kept for the loss of both generators
+        assert_ne!(e170.bpcu.selected_feed("DC ESS Bus"), Some(DC_ESS_BATTERY));
+        assert!(is_shed(&e170, "Galley"));
+        assert!(load_current(&e170, "Galley") < DRAWING);
+        assert!(!is_shed(&e170, "Test Light"));
+        assert!(load_current(&e170, "Test Light") > DRAWING);
+
+        e170.set_generator_switch(1, true);
+        settle(&mut e170);
+        assert_normal(&e170);
+    }
+
+    #[test]
+    fn dual_generator_loss_runs_the_essential_buses_on_the_adg() {
+        let mut e170 = in_flight();
+        e170.set_generator_switch(1, false);
+        e170.set_generator_switch(2, false);
+        run(&mut e170, 10.0);
+
+        assert_eq!(e170.bpcu.shed_level(), LoadShedLevel::Emergency);
+        assert!(voltage(&e170, "AC Bus 1") < AC_POWERED);
+        assert!(voltage(&e170, "AC Bus 2") < AC_POWERED);
+        assert_eq!(e170.bpcu.selected_feed("AC ESS Bus"), Some(2));
+        assert_eq!(e170.bpcu.selected_feed("DC ESS Bus"), Some(DC_ESS_BATTERY));
+        assert_powered(&e170, &["AC ESS Bus"], &["DC ESS Bus"]);
+        for load in ["Galley", "Test Light"] {
+            assert!(is_shed(&e170, load), "{load} kept");
+            assert!(load_current(&e170, load) < DRAWING, "{load} on");
+        }
+
+        e170.set_generator_switch(1, true);
+        e170.set_generator_switch(2, true);
+        settle(&mut e170);
+        assert_normal(&e170);
+    }
+
+    #[test]
+    fn battery_charges_from_dc_bus_1_until_the_main_sources_are_lost() {
+        let mut e170 = in_flight();
+        let system = e170.electrical_system();
+        let battery = system.handle::<Battery>("Battery 1").unwrap();
+        assert!(system.get(battery).unwrap().is_charging());
+        let charged = system.get(battery).unwrap().state_of_charge();
+        run(&mut e170, 10.0);
+        let system = e170.electrical_system();
+        assert!(system.get(battery).unwrap().state_of_charge() > charged);
+
+        e170.set_generator_switch(1, false);
+        e170.set_generator_switch(2, false);
+        run(&mut e170, 10.0);
+        let system = e170.electrical_system();
+        assert!(is_shed(&e170, "BATT 1 CHG"));
+        assert!(!system.get(battery).unwrap().is_charging());
+        assert!(voltage(&e170, "DC Bus 1") < DC_POWERED);
+    }
+
+    #[test]
+    fn ground_power_alone_powers_every_bus_and_sheds_nothing() {
+        let mut e170 = E170Systems::new().unwrap();
+        // Engines shut down, the cart is plugged in by the network file
+        e170.set_engine_n2(1, 0.0);
+        e170.set_engine_n2(2, 0.0);
+        run(&mut e170, 10.0);
+
+        assert_powered(
+            &e170,
+            &["AC Bus 1", "AC Bus 2", "AC ESS Bus"],
+            &["DC Bus 1", "DC Bus 2", "DC ESS Bus"],
+        );
+        assert_eq!(e170.bpcu.shed_level(), LoadShedLevel::Normal);
+        assert!(!is_shed(&e170, "Galley"));
+        assert!(load_current(&e170, "Galley") > DRAWING);
+    }
+
+    #[test]
+    fn switching_a_shed_load_takes_effect_once_it_is_restored() {
+        let mut e170 = in_flight();
+        e170.set_load_power("Galley", false);
+        e170.set_generator_switch(1, false);
+        run(&mut e170, 1.0);
+        assert!(is_shed(&e170, "Galley"));
+
+        // Switched on while shed, it stays off until the BPCU lets it go
+        e170.set_load_power("Galley", true);
+        run(&mut e170, 1.0);
+        assert!(load_current(&e170, "Galley") < DRAWING);
+
+        e170.set_generator_switch(1, true);
+        settle(&mut e170);
+        assert!(!is_shed(&e170, "Galley"));
+        assert!(load_current(&e170, "Galley") > DRAWING);
+
+        // Switched off while shed, it is not turned back on by the restore
+        e170.set_generator_switch(1, false);
+        run(&mut e170, 1.0);
+        e170.set_load_power("Galley", false);
+        e170.set_generator_switch(1, true);
+        settle(&mut e170);
+        assert!(!is_shed(&e170, "Galley"));
+        assert!(load_current(&e170, "Galley") < DRAWING);
+    }
+
+    #[test]
+    fn motor_loads_are_shed_and_restored() {
+        let mut system = ElectricalSystem::new();
+        let mut pump = Motor::new("Pump", MotorSupply::Dc, 28.0, 500.0, 6000.0, 5.0, 1.0);
+        pump.set_power_state(true);
+        let pump = system.add_component("Pump", pump).unwrap();
+
+        let mut bpcu = BusPowerControlUnit::new("BPCU");
+        bpcu.add_source("GEN 1", SourceKind::Main);
+        bpcu.add_source("GEN 2", SourceKind::Main);
+        bpcu.add_shed_load(pump, LoadShedLevel::SingleSource);
+        bpcu.set_source_available("GEN 1", true);
+        bpcu.set_source_available("GEN 2", true);
+        bpcu.update(TIME_STEP, &mut system);
+        assert!(!system.get(pump).unwrap().is_shed());
+
+        bpcu.set_source_available("GEN 2", false);
+        bpcu.update(TIME_STEP, &mut system);
+        assert!(bpcu.is_shed(pump));
+        assert!(system.get(pump).unwrap().is_shed());
+        // The switch is left alone
+        assert!(system.get(pump).unwrap().is_on());
+
+        bpcu.set_source_available("GEN 2", true);
+        for _ in 0..=(RESTORE_DELAY / TIME_STEP as f64).round() as usize {
+            bpcu.update(TIME_STEP, &mut system);
+        }
+        assert!(!bpcu.is_shed(pump));
+        assert!(!system.get(pump).unwrap().is_shed());
+    }
+
+    #[test]
+    fn contactors_drop_out_with_the_coil_supply() {
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("Battery", 24.0, 44.0, 0.02, 0.95);
+        let battery = system.add_component("Battery", battery).unwrap();
+        let dc_ess = Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        };
+        let dc_ess = system.add_component("DC ESS Bus", dc_ess).unwrap();
+        let contactor = Contactor::new("Bus contactor", 18.0, 12.0, 0.05);
+        let contactor = system.add_component("Bus contactor", contactor).unwrap();
+
+        let mut bpcu = BusPowerControlUnit::new("BPCU");
+        bpcu.add_source("GEN 1", SourceKind::Main);
+        bpcu.set_source_available("GEN 1", true);
+        bpcu.add_shed_load(contactor, LoadShedLevel::Emergency);
+        bpcu.add_coil_supply(dc_ess);
+        let mut run = |system: &mut ElectricalSystem, seconds: f64| {
+            for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+                system.update_system(TIME_STEP);
+                bpcu.update(TIME_STEP, system);
+            }
+        };
+
+        // Commanded closed, but the coil supply is dead
+        run(&mut system, 1.0);
+        assert!(system.get(contactor).unwrap().is_commanded_closed());
+        assert!(!system.get(contactor).unwrap().is_closed());
+
+        system.connect_with_wire(battery, dc_ess, Wire::fixed(0.01));
+        run(&mut system, 1.0);
+        assert!(system.get(contactor).unwrap().is_closed());
+
+        system.disconnect(battery, dc_ess);
+        run(&mut system, 1.0);
+        assert!(!system.get(contactor).unwrap().is_closed());
+    }
+}
@@ -0,0 +1,466 @@
+// Circuit breaker catalogue.
+// Breakers with their rating, the panel coordinate they sit at, the bus that feeds them and the load they
+// protect, per aircraft variant, read from a data file. Instantiating the catalogue for one aircraft puts
+// a `CircuitBreaker` between each bus and load listed for that variant, taking over the wire that ran
+// between them, so the breakers can then be found (and pulled) by their panel coordinate.
+
+use crate::systems::electrical::components::shared::circuit_breaker::{CircuitBreaker, TripBand};
+use crate::systems::electrical::{ComponentHandle, ElectricalSystem};
+
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AircraftVariant {
+    E170,
+    E175,
+    E190,
+    E195,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PanelLocation {
+    pub panel: String,
+    pub row: char,
+    pub column: u32,
+}
+
+impl PanelLocation {
+    pub fn new(panel: &str, row: char, column: u32) -> Self {
+        PanelLocation {
+            panel: panel.to_string(),
+            row: row.to_ascii_uppercase(),
+            column,
+        }
+    }
+}
+
+impl fmt::Display for PanelLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}{}", self.panel, self.row, self.column)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreakerEntry {
+    pub name: String,
+    pub rating: f64,
+    #[serde(default)]
+    pub trip_band: TripBand,
+    pub panel: String,
+    pub row: char,
+    pub column: u32,
+    pub bus: String,
+    pub load: String,
+    pub aircraft: Vec<AircraftVariant>,
+}
+
+impl BreakerEntry {
+    pub fn location(&self) -> PanelLocation {
+        PanelLocation::new(&self.panel, self.row, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum BreakerDatabaseError {
+    Syntax {
+        message: String,
+    },
+    DuplicateName {
+        name: String,
+    },
+    DuplicateLocation {
+        location: PanelLocation,
+        first: String,
+        second: String,
+    },
+    UnknownComponent {
+        breaker: String,
+        component: String,
+    },
+    // The breaker has to take over a wire running straight from its bus to its load
+    NoFeederWire {
+        breaker: String,
+        bus: String,
+        load: String,
+    },
+    // Two breakers listed for the same bus and load
+    SharedFeederWire {
+        first: String,
+        second: String,
+    },
+}
+
+impl fmt::Display for BreakerDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakerDatabaseError::Syntax { message } => {
+                write!(f, "invalid breaker catalogue: {message}")
+            }
+            BreakerDatabaseError::DuplicateName { name } => {
+                write!(f, "breaker \"{name}\" is listed more than once")
+            }
+            BreakerDatabaseError::DuplicateLocation {
+                location,
+                first,
+                second,
+            } => write!(
+                f,
+                "breakers \"{first}\" and \"{second}\" both sit at {location}"
+            ),
+            BreakerDatabaseError::UnknownComponent { breaker, component } => write!(
+                f,
+                "breaker \"{breaker}\" references unknown component \"{component}\""
+            ),
+            BreakerDatabaseError::NoFeederWire { breaker, bus, load } => write!(
+                f,
+                "breaker \"{breaker}\" has no wire from \"{bus}\" to \"{load}\" to sit in"
+            ),
+            BreakerDatabaseError::SharedFeederWire { first, second } => write!(
+                f,
+                "breakers \"{first}\" and \"{second}\" protect the same bus to load wire"
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BreakerFile {
+    #[serde(default)]
+    breaker: Vec<BreakerEntry>,
+}
+
+pub struct CircuitBreakerDatabase {
+    entries: Vec<BreakerEntry>,
+}
+
+impl CircuitBreakerDatabase {
+    pub fn parse(source: &str) -> Result<Self, Vec<BreakerDatabaseError>> {
+        let file: BreakerFile = toml::from_str(source).map_err(|error| {
+            vec![BreakerDatabaseError::Syntax {
+                message: error.to_string(),
+            }]
+        })?;
+
+        let mut errors = Vec::new();
+        for (index, entry) in file.breaker.iter().enumerate() {
+            for earlier in &file.breaker[..index] {
+                if earlier.name == entry.name {
+                    errors.push(BreakerDatabaseError::DuplicateName {
+                        name: entry.name.clone(),
+                    });
+                }
+                // Variants have their own panel layouts, only a clash on a shared aircraft counts
+                let shares_aircraft = entry
+                    .aircraft
+                    .iter()
+                    .any(|variant| earlier.aircraft.contains(variant));
+                if shares_aircraft && earlier.location() == entry.location() {
+                    errors.push(BreakerDatabaseError::DuplicateLocation {
+                        location: entry.location(),
+                        first: earlier.name.clone(),
+                        second: entry.name.clone(),
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(CircuitBreakerDatabase {
+                entries: file.breaker,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn entries(&self, variant: AircraftVariant) -> impl Iterator<Item = &BreakerEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.aircraft.contains(&variant))
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&BreakerEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn entry_at(
+        &self,
+        variant: AircraftVariant,
+        location: &PanelLocation,
+    ) -> Option<&BreakerEntry> {
+        self.entries(variant)
+            .find(|entry| entry.location() == *location)
+    }
+
+    // The breaker at `location` in a network the catalogue was instantiated into
+    pub fn breaker_at(
+        &self,
+        system: &ElectricalSystem,
+        variant: AircraftVariant,
+        location: &PanelLocation,
+    ) -> Option<ComponentHandle<CircuitBreaker>> {
+        system.handle(&self.entry_at(variant, location)?.name)
+    }
+
+    // Adds the breakers of `variant` to the network between their bus and their load. Every entry is checked
+    // first, on an error the network is left as it was.
+    pub fn instantiate(
+        &self,
+        system: &mut ElectricalSystem,
+        variant: AircraftVariant,
+    ) -> Result<(), Vec<BreakerDatabaseError>> {
+        let mut errors = Vec::new();
+        let mut feeders: Vec<(&BreakerEntry, NodeIndex, NodeIndex)> = Vec::new();
+        for entry in self.entries(variant) {
+            let (Some(bus), Some(load)) = (system.node(&entry.bus), system.node(&entry.load))
+            else {
+                let component = if system.node(&entry.bus).is_none() {
+                    entry.bus.clone()
+                } else {
+                    entry.load.clone()
+                };
+                errors.push(BreakerDatabaseError::UnknownComponent {
+                    breaker: entry.name.clone(),
+                    component,
+                });
+                continue;
+            };
+            if system.node(&entry.name).is_some() {
+                errors.push(BreakerDatabaseError::DuplicateName {
+                    name: entry.name.clone(),
+                });
+                continue;
+            }
+            if system.wire(bus, load).is_none() {
+                errors.push(BreakerDatabaseError::NoFeederWire {
+                    breaker: entry.name.clone(),
+                    bus: entry.bus.clone(),
+                    load: entry.load.clone(),
+                });
+                continue;
+            }
+            if let Some((first, ..)) = feeders
+                .iter()
+                .find(|(_, from, to)| (*from, *to) == (bus, load))
+            {
+                errors.push(BreakerDatabaseError::SharedFeederWire {
+                    first: first.name.clone(),
+                    second: entry.name.clone(),
+                });
+                continue;
+            }
+            feeders.push((entry, bus, load));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        for (entry, bus, load) in feeders {
+            let breaker =
+                CircuitBreaker::new(&entry.name, entry.rating, entry.trip_band, false, 0.0);
+            let breaker = system
+                .add_component(&entry.name, breaker)
+                .expect("breaker names were checked against the network");
+            // The feeder wire now leaves the breaker, the breaker itself sits on the bus
+            system.rewire(bus, load, breaker, load);
+            system.connect_no_resistance(bus, breaker);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::ElectricalComponent;
+    use crate::systems::electrical::components::shared::circuit_breaker::BreakerState;
+    use crate::systems::electrical::components::dc::generic_dc_component::GenericDcComponent;
+    use crate::systems::{CIRCUIT_BREAKERS, E170Systems, ELECTRICAL_NETWORK};
+    use crate::systems::electrical::components::dc::diode::Diode;
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::components::shared::contactor::Contactor;
+    use crate::systems::electrical::components::shared::solid_state_power_controller::SolidStatePowerController;
+    use crate::systems::electrical::network_loader::parse_network;
+
+    const NETWORK: &str = r#"
+[[component]]
+name = "DC Bus 1"
+type = "bus"
+
+[[component]]
+name = "Test Light"
+type = "load"
+nominal_voltage = 28.0
+nominal_power = 200.0
+min_voltage = 18.0
+max_voltage = 32.0
+voltage_response = "binary"
+power_factor = 0.9
+"#;
+
+    fn network() -> ElectricalSystem {
+        let mut system = parse_network(NETWORK).unwrap();
+        let bus = system.node("DC Bus 1").unwrap();
+        let light = system.node("Test Light").unwrap();
+        system.connect_no_resistance(bus, light);
+        system
+    }
+
+    fn breaker(name: &str, column: u32, load: &str) -> String {
+        format!(
+            r#"
+[[breaker]]
+name = "{name}"
+rating = 10.0
+panel = "CB PANEL 1"
+row = "A"
+column = {column}
+bus = "DC Bus 1"
+load = "{load}"
+aircraft = ["e170"]
+"#
+        )
+    }
+
+    #[test]
+    fn breaker_takes_over_the_feeder_wire() {
+        let mut system = network();
+        let database =
+            CircuitBreakerDatabase::parse(&breaker("LIGHTS CB", 1, "Test Light")).unwrap();
+        database
+            .instantiate(&mut system, AircraftVariant::E170)
+            .unwrap();
+
+        let bus = system.node("DC Bus 1").unwrap();
+        let light = system.node("Test Light").unwrap();
+        let handle = database
+            .breaker_at(
+                &system,
+                AircraftVariant::E170,
+                &PanelLocation::new("CB PANEL 1", 'a', 1),
+            )
+            .unwrap();
+        assert!(system.wire(bus, light).is_none());
+        assert!(system.wire(bus, handle.node()).is_some());
+        assert!(system.wire(handle.node(), light).is_some());
+        // Not fitted to the other variants
+        assert!(
+            database
+                .breaker_at(
+                    &system,
+                    AircraftVariant::E190,
+                    &PanelLocation::new("CB PANEL 1", 'A', 1)
+                )
+                .is_none()
+        );
+    }
+
+    // Supply voltage and current of a DC load in the aircraft network
+    fn load_supply(e170: &E170Systems, name: &str) -> (f64, f64) {
+        let system = e170.electrical_system();
+        let load = system
+            .handle::<GenericDcComponent>(name)
+            .and_then(|load| system.get(load))
+            .unwrap();
+        (load.input_voltage.value, load.get_input_current().value)
+    }
+
+    #[test]
+    fn pulling_a_breaker_by_its_panel_coordinate_kills_its_load() {
+        let mut e170 = E170Systems::new().unwrap();
+        e170.set_load_power("Test Light", true);
+        e170.set_load_power("Test Display", true);
+        for _ in 0..200 {
+            e170.update(0.1);
+        }
+        for load in ["Test Light", "Test Display"] {
+            let (voltage, current) = load_supply(&e170, load);
+            assert!(voltage > 20.0 && current > 1.0, "{load} unpowered");
+        }
+
+        // LIGHTS CB feeds the test light from DC Bus 1
+        let location = PanelLocation::new("CB PANEL 1", 'A', 2);
+        assert!(e170.pull_circuit_breaker(&location));
+        assert_eq!(e170.circuit_breaker_state(&location), Some(BreakerState::Pulled));
+        for _ in 0..10 {
+            e170.update(0.1);
+        }
+
+        assert_eq!(load_supply(&e170, "Test Light"), (0.0, 0.0));
+        let (voltage, current) = load_supply(&e170, "Test Display");
+        assert!(voltage > 20.0 && current > 1.0, "Test Display lost its supply");
+        assert!(!e170.pull_circuit_breaker(&PanelLocation::new("CB PANEL 9", 'Z', 99)));
+    }
+
+    #[test]
+    fn network_is_untouched_when_an_entry_is_invalid() {
+        let mut system = network();
+        let source = format!(
+            "{}{}{}",
+            breaker("LIGHTS CB", 1, "Test Light"),
+            breaker("GALLEY CB", 2, "Galley"),
+            breaker("LIGHTS 2 CB", 3, "Test Light"),
+        );
+        let database = CircuitBreakerDatabase::parse(&source).unwrap();
+        let errors = database
+            .instantiate(&mut system, AircraftVariant::E170)
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            BreakerDatabaseError::UnknownComponent { component, .. } if component == "Galley"
+        ));
+        assert!(matches!(
+            &errors[1],
+            BreakerDatabaseError::SharedFeederWire { first, second }
+                if first == "LIGHTS CB" && second == "LIGHTS 2 CB"
+        ));
+        // The valid breaker was not added either
+        assert!(system.node("LIGHTS CB").is_none());
+        assert_eq!(system.component_nodes().count(), 2);
+        let bus = system.node("DC Bus 1").unwrap();
+        let light = system.node("Test Light").unwrap();
+        assert!(system.wire(bus, light).is_some());
+    }
+
+    #[test]
+    fn shipped_catalogue_protects_every_bus_fed_load() {
+        let database = CircuitBreakerDatabase::parse(CIRCUIT_BREAKERS).unwrap();
+        for variant in [
+            AircraftVariant::E170,
+            AircraftVariant::E175,
+            AircraftVariant::E190,
+            AircraftVariant::E195,
+        ] {
+            let mut system = parse_network(ELECTRICAL_NETWORK).unwrap();
+            database.instantiate(&mut system, variant).unwrap();
+
+            // Whatever is still wired straight to a bus, apart from the power path, has no breaker
+            let is_switching = |node| {
+                system.handle_at::<Bus>(node).is_some()
+                    || system.handle_at::<Contactor>(node).is_some()
+                    || system.handle_at::<Diode>(node).is_some()
+                    || system.handle_at::<CircuitBreaker>(node).is_some()
+                    || system
+                        .handle_at::<SolidStatePowerController>(node)
+                        .is_some()
+            };
+            let unprotected: Vec<_> = system
+                .wires()
+                .filter(|(from, to, _)| {
+                    system.handle_at::<Bus>(*from).is_some() && !is_switching(*to)
+                })
+                .map(|(_, to, _)| system.component_name(to).unwrap().to_string())
+                .collect();
+            assert!(
+                unprotected.is_empty(),
+                "{variant:?}: no breaker for {unprotected:?}"
+            );
+        }
+    }
+}
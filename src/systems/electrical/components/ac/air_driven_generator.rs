@@ -0,0 +1,361 @@
+// Air Driven Generator (RAT).
+// Emergency AC source stowed in the fuselage. Once deployed the turbine is governed to hold the generator
+// at its rated speed, the power the airstream can give grows with the cube of the airspeed and is capped
+// at the generator rating. The generator first comes on line at the minimum airspeed. Below it the governor
+// runs out of authority, the generator drops off line and only comes back once the airspeed recovers above
+// the minimum plus a margin.
+// Deployment is automatic on the loss of all AC in flight, and can only be undone on the ground.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+use uom::si::power::watt;
+use uom::si::time::second;
+use uom::si::velocity::knot;
+
+const RATED_FREQUENCY: f64 = 400.0; // Hz
+const SPIN_TIME_CONSTANT: f64 = 1.0; // seconds
+const DROPOUT_HYSTERESIS: f64 = 10.0; // knots above the minimum airspeed to come back on line after a dropout
+const MIN_VOLTAGE_RATIO: f64 = 0.9; // of rated voltage, below that the ADG is not available
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdgState {
+    Stowed,
+    Deploying,
+    Deployed,
+}
+
+pub struct AirDrivenGenerator {
+    name: String,
+    rated_power: Power,
+    rated_voltage: ElectricPotential,
+    rated_frequency: Frequency,
+    internal_resistance: ElectricalResistance,
+    min_airspeed: Velocity,
+    rated_airspeed: Velocity,
+    deployment_time: Time,
+
+    state: AdgState,
+    deploy_timer: Time,
+    airspeed: Velocity,
+    speed_ratio: f64,
+    is_dropped_out: bool,
+    // Been on line since the deployment, a recovery from a dropout then takes the hysteresis
+    has_been_on_line: bool,
+
+    internal_voltage: ElectricPotential,
+    output_voltage: ElectricPotential,
+    output_current: ElectricCurrent,
+    output_power: Power,
+}
+
+impl AirDrivenGenerator {
+    pub fn new(
+        name: &str,
+        rated_power: f64,
+        rated_voltage: f64,
+        internal_resistance: f64,
+        min_airspeed: f64,
+        rated_airspeed: f64,
+        deployment_time: f64,
+    ) -> Self {
+        AirDrivenGenerator {
+            name: name.to_string(),
+            rated_power: Power::new::<watt>(rated_power),
+            rated_voltage: ElectricPotential::new::<volt>(rated_voltage),
+            rated_frequency: Frequency::new::<hertz>(RATED_FREQUENCY),
+            internal_resistance: ElectricalResistance::new::<ohm>(internal_resistance),
+            min_airspeed: Velocity::new::<knot>(min_airspeed),
+            rated_airspeed: Velocity::new::<knot>(rated_airspeed),
+            deployment_time: Time::new::<second>(deployment_time),
+
+            state: AdgState::Stowed,
+            deploy_timer: Time::new::<second>(0.0),
+            airspeed: Velocity::new::<knot>(0.0),
+            speed_ratio: 0.0,
+            is_dropped_out: true,
+            has_been_on_line: false,
+
+            internal_voltage: ElectricPotential::new::<volt>(0.0),
+            output_voltage: ElectricPotential::new::<volt>(0.0),
+            output_current: ElectricCurrent::new::<ampere>(0.0),
+            output_power: Power::new::<watt>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_airspeed(&mut self, airspeed: Velocity) {
+        self.airspeed = airspeed;
+    }
+
+    // Automatic deployment logic, AC buses 1 and 2 both unpowered while flying
+    pub fn set_all_ac_lost(&mut self, lost: bool) {
+        if lost && self.state == AdgState::Stowed && self.airspeed > self.min_airspeed {
+            eprintln!("⚡ {} AUTO DEPLOY", self.name);
+            self.deploy();
+        }
+    }
+
+    // Manual deployment handle
+    pub fn deploy(&mut self) {
+        if self.state == AdgState::Stowed {
+            self.state = AdgState::Deploying;
+            self.deploy_timer = Time::new::<second>(0.0);
+        }
+    }
+
+    // Ground maintenance restow, only possible with the turbine stopped
+    pub fn stow(&mut self) -> bool {
+        if self.airspeed.get::<knot>() > 0.0 || self.speed_ratio > 0.01 {
+            return false;
+        }
+        self.state = AdgState::Stowed;
+        self.is_dropped_out = true;
+        self.has_been_on_line = false;
+        true
+    }
+
+    pub fn state(&self) -> AdgState {
+        self.state
+    }
+
+    pub fn is_dropped_out(&self) -> bool {
+        self.is_dropped_out
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.state == AdgState::Deployed
+            && !self.is_dropped_out
+            && self.internal_voltage >= self.rated_voltage * MIN_VOLTAGE_RATIO
+    }
+
+    pub fn rated_power(&self) -> Power {
+        self.rated_power
+    }
+
+    // Power the airstream can give the generator at the current airspeed
+    pub fn available_power(&self) -> Power {
+        if self.state != AdgState::Deployed || self.is_dropped_out {
+            return Power::new::<watt>(0.0);
+        }
+        let airspeed_ratio = (self.airspeed / self.rated_airspeed).value.max(0.0);
+        self.rated_power * airspeed_ratio.powi(3).min(1.0)
+    }
+
+    pub fn output_frequency(&self) -> Frequency {
+        self.rated_frequency * self.speed_ratio
+    }
+}
+
+impl ElectricalComponent for AirDrivenGenerator {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        let dt = Time::new::<second>(dt as f64);
+        if self.state == AdgState::Deploying {
+            self.deploy_timer += dt;
+            if self.deploy_timer >= self.deployment_time {
+                self.state = AdgState::Deployed;
+                eprintln!("⚡ {} DEPLOYED", self.name);
+            }
+        }
+
+        // The blade pitch governor holds rated speed down to the minimum airspeed
+        let target_ratio = match self.state {
+            AdgState::Deployed if self.airspeed >= self.min_airspeed => 1.0,
+            AdgState::Deployed => (self.airspeed / self.min_airspeed).value.max(0.0),
+            _ => 0.0,
+        };
+        let alpha = (dt.get::<second>() / SPIN_TIME_CONSTANT).min(1.0);
+        self.speed_ratio += (target_ratio - self.speed_ratio) * alpha;
+
+        let airspeed = self.airspeed.get::<knot>();
+        let min_airspeed = self.min_airspeed.get::<knot>();
+        if !self.is_dropped_out && airspeed < min_airspeed {
+            self.is_dropped_out = true;
+            eprintln!("⚠️ {} DROPOUT: airspeed {:.0} kt", self.name, airspeed);
+        } else if self.is_dropped_out && self.state == AdgState::Deployed {
+            let on_line_airspeed = if self.has_been_on_line {
+                min_airspeed + DROPOUT_HYSTERESIS
+            } else {
+                min_airspeed
+            };
+            if airspeed >= on_line_airspeed {
+                self.is_dropped_out = false;
+                self.has_been_on_line = true;
+            }
+        }
+
+        if self.state != AdgState::Deployed || self.is_dropped_out {
+            self.internal_voltage = ElectricPotential::new::<volt>(0.0);
+            return;
+        }
+
+        // Same approach as the engine generators, sag the EMF when the network asks for more than the
+        // airstream gives so a resistive load settles at the available power
+        let full_voltage = self.rated_voltage.get::<volt>() * self.speed_ratio.min(1.0);
+        let previous_ratio = if full_voltage > 0.0 {
+            (self.internal_voltage.get::<volt>() / full_voltage).clamp(0.01, 1.0)
+        } else {
+            1.0
+        };
+        let demanded_power = self.output_voltage.get::<volt>()
+            * self.output_current.get::<ampere>()
+            / (previous_ratio * previous_ratio);
+        let available_power = self.available_power().get::<watt>();
+        let sag = if demanded_power > available_power && demanded_power > 0.0 {
+            (available_power / demanded_power).sqrt()
+        } else {
+            1.0
+        };
+
+        self.internal_voltage = ElectricPotential::new::<volt>(full_voltage * sag);
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.output_power
+    }
+
+    fn set_input_power(&mut self, _power: Power) {
+        // Output power follows the delivered current
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.output_voltage
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        // Terminal voltage reported by the network
+        self.output_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.output_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.output_current = current;
+        self.output_power = self.output_voltage * current;
+    }
+
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        Some(self.output_frequency())
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if self.state != AdgState::Deployed || self.is_dropped_out {
+            return CircuitModel::Open;
+        }
+        CircuitModel::Source {
+            voltage: self.internal_voltage,
+            internal_resistance: self.internal_resistance,
+        }
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+    const MIN_AIRSPEED: f64 = 130.0; // knots
+
+    fn adg(airspeed: f64) -> AirDrivenGenerator {
+        let mut adg =
+            AirDrivenGenerator::new("ADG", 15000.0, 115.0, 0.05, MIN_AIRSPEED, 180.0, 8.0);
+        adg.set_airspeed(Velocity::new::<knot>(airspeed));
+        adg
+    }
+
+    fn run(adg: &mut AirDrivenGenerator, airspeed: f64, seconds: f64) {
+        adg.set_airspeed(Velocity::new::<knot>(airspeed));
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            adg.update(TIME_STEP);
+        }
+    }
+
+    // Deployed and on line at 160 kt
+    fn deployed() -> AirDrivenGenerator {
+        let mut adg = adg(160.0);
+        adg.deploy();
+        run(&mut adg, 160.0, 15.0);
+        assert!(adg.is_available());
+        adg
+    }
+
+    #[test]
+    fn auto_deploys_only_above_the_minimum_airspeed() {
+        let mut slow = adg(MIN_AIRSPEED - 10.0);
+        slow.set_all_ac_lost(true);
+        assert_eq!(slow.state(), AdgState::Stowed);
+
+        let mut fast = adg(MIN_AIRSPEED + 10.0);
+        fast.set_all_ac_lost(true);
+        assert_eq!(fast.state(), AdgState::Deploying);
+        run(&mut fast, MIN_AIRSPEED + 10.0, 7.9);
+        assert_eq!(fast.state(), AdgState::Deploying);
+        run(&mut fast, MIN_AIRSPEED + 10.0, 0.2);
+        assert_eq!(fast.state(), AdgState::Deployed);
+    }
+
+    #[test]
+    fn comes_on_line_at_the_minimum_airspeed_after_deployment() {
+        // All AC lost between the minimum and the recovery margin
+        let airspeed = MIN_AIRSPEED + DROPOUT_HYSTERESIS / 2.0;
+        let mut adg = adg(airspeed);
+        adg.set_all_ac_lost(true);
+        run(&mut adg, airspeed, 15.0);
+
+        assert_eq!(adg.state(), AdgState::Deployed);
+        assert!(!adg.is_dropped_out());
+        assert!(adg.is_available());
+    }
+
+    #[test]
+    fn drops_out_below_the_minimum_airspeed_and_needs_the_margin_to_come_back() {
+        let mut adg = deployed();
+        run(&mut adg, MIN_AIRSPEED, 1.0);
+        assert!(!adg.is_dropped_out());
+
+        run(&mut adg, MIN_AIRSPEED - 1.0, TIME_STEP as f64);
+        assert!(adg.is_dropped_out());
+        assert!(!adg.is_available());
+        assert!(matches!(adg.circuit_model(), CircuitModel::Open));
+
+        // Back at the minimum is not enough, it takes the hysteresis on top
+        run(&mut adg, MIN_AIRSPEED + DROPOUT_HYSTERESIS - 1.0, 5.0);
+        assert!(adg.is_dropped_out());
+        run(&mut adg, MIN_AIRSPEED + DROPOUT_HYSTERESIS, 5.0);
+        assert!(!adg.is_dropped_out());
+        assert!(adg.is_available());
+    }
+
+    #[test]
+    fn available_power_goes_with_the_cube_of_the_airspeed() {
+        let mut adg = deployed();
+        // (160 / 180)^3 of the rating
+        let expected = 15000.0 * (160.0f64 / 180.0).powi(3);
+        assert!((adg.available_power().get::<watt>() - expected).abs() < 1e-6);
+
+        run(&mut adg, 250.0, 1.0);
+        assert_eq!(adg.available_power(), adg.rated_power());
+    }
+}
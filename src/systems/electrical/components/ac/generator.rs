@@ -1,4 +1,11 @@
+// AC generator.
+// The rotor follows the shaft speed it is driven at with the time constant of the drive train, the EMF
+// goes with the speed and the field. A voltage regulator holds the terminal voltage at the rated voltage
+// through the exciter: a load step sags the voltage across the internal resistance until the regulator has
+// brought the field up, dropping load leaves the voltage high until it has brought it down again. The
+// field cannot be forced past its ceiling and the EMF is held back when the shaft cannot carry the load.
 use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
 use std::any::Any;
 
 use uom::si::angular_velocity::revolution_per_minute;
@@ -9,23 +16,34 @@ use uom::si::f64::*;
 use uom::si::frequency::hertz;
 use uom::si::power::watt;
 use uom::si::ratio::ratio;
-use uom::si::time::millisecond;
+use uom::si::time::second;
+
+// Regulator, PI on the terminal voltage in per unit of the rated voltage, driving the exciter field
+const REGULATOR_GAIN: f64 = 3.0;
+const REGULATOR_INTEGRAL_TIME: f64 = 0.1; // seconds
+const EXCITER_TIME_CONSTANT: f64 = 0.08; // seconds
+const CEILING_EXCITATION: f64 = 1.6; // of the field at rated EMF
+// The regulator runs faster than the simulation, against the network as it was last solved
+const REGULATOR_STEP: f64 = 0.005; // seconds
 
 pub struct Generator {
     num_poles: f64,
     rated_power: Power,                        //Watts
     rated_voltage: ElectricPotential,          // Volt
     rated_frequency: Frequency,                // Hz
-    efficiency: Ratio,                         // Percent
+    efficiency: Ratio,                         // 0 to 1
     internal_resistance: ElectricalResistance, // Ohm
     mechanical_input_power: Power,             //Watt
     rpm: AngularVelocity,                      // RPM
     output_power: Power,                       // Watts
-    output_voltage: ElectricPotential,         // Volts
-    spin_up_time: Time,                        // Ms
+    output_voltage: ElectricPotential,         // Volts, terminal voltage reported by the network
+    output_current: ElectricCurrent,           // Amps
+    internal_voltage: ElectricPotential,       // Volts, EMF behind the internal resistance
+    spin_up_time: Time,                        // Seconds, time constant of the rotor
     current_rpm: AngularVelocity,              // RPM
     is_on: bool,
-    time_on: Time, // Ms
+    excitation: f64,         // Field, 1.0 gives the rated voltage at rated speed
+    regulator_integral: f64, // Per unit
     phase_count: u8,
 }
 
@@ -45,37 +63,113 @@ impl Generator {
             rated_power: Power::new::<watt>(rated_power),
             rated_voltage: ElectricPotential::new::<volt>(rated_voltage),
             rated_frequency: Frequency::new::<hertz>(rated_frequency),
-            efficiency: Ratio::new::<ratio>(efficiency * 100.0),
+            efficiency: Ratio::new::<ratio>(efficiency),
             internal_resistance: ElectricalResistance::new::<ohm>(internal_resistance),
             mechanical_input_power: Power::new::<watt>(0.0),
             rpm: AngularVelocity::new::<revolution_per_minute>(rated_frequency * 60.0 / num_poles),
             output_power: Power::new::<watt>(0.0),
             output_voltage: ElectricPotential::new::<volt>(0.0),
-            spin_up_time: Time::new::<millisecond>(spin_up_time),
+            output_current: ElectricCurrent::new::<ampere>(0.0),
+            internal_voltage: ElectricPotential::new::<volt>(0.0),
+            spin_up_time: Time::new::<second>(spin_up_time),
             current_rpm: AngularVelocity::new::<revolution_per_minute>(0.0),
             is_on: false,
-            time_on: Time::new::<millisecond>(0.0),
+            excitation: 0.0,
+            regulator_integral: 0.0,
             phase_count,
         }
     }
 
+    // The shaft turns whether the field is on or not
     pub fn set_mechanical_input(&mut self, power: f64, rpm: f64) {
-        if self.is_on {
-            self.mechanical_input_power = Power::new::<watt>(power);
-            self.rpm = AngularVelocity::new::<revolution_per_minute>(rpm);
-        }
+        self.mechanical_input_power = Power::new::<watt>(power);
+        self.rpm = AngularVelocity::new::<revolution_per_minute>(rpm);
     }
 
     pub fn turn_on(&mut self) {
         self.is_on = true;
-        self.time_on = Time::new::<millisecond>(0.0);
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    pub fn rated_power(&self) -> Power {
+        self.rated_power
+    }
+
+    pub fn rated_voltage(&self) -> ElectricPotential {
+        self.rated_voltage
+    }
+
+    pub fn rated_frequency(&self) -> Frequency {
+        self.rated_frequency
+    }
+
+    // Shaft speed that produces the rated frequency
+    pub fn rated_rpm(&self) -> AngularVelocity {
+        AngularVelocity::new::<revolution_per_minute>(
+            self.rated_frequency.get::<hertz>() * 60.0 / self.num_poles,
+        )
+    }
+
+    pub fn current_rpm(&self) -> AngularVelocity {
+        self.current_rpm
+    }
+
+    pub fn phase_count(&self) -> u8 {
+        self.phase_count
+    }
+
+    pub fn excitation(&self) -> f64 {
+        self.excitation
+    }
+
+    pub fn output_frequency(&self) -> Frequency {
+        Frequency::new::<hertz>(
+            self.current_rpm.get::<revolution_per_minute>() * self.num_poles / 60.0,
+        )
     }
 
     pub fn turn_off(&mut self) {
         self.is_on = false;
         self.output_voltage = ElectricPotential::new::<volt>(0.0);
+        self.internal_voltage = ElectricPotential::new::<volt>(0.0);
         self.output_power = Power::new::<watt>(0.0);
-        self.current_rpm = AngularVelocity::new::<revolution_per_minute>(0.0);
+        self.excitation = 0.0;
+        self.regulator_integral = 0.0;
+    }
+
+    // Load the network put on the terminals at the last solve, None when it takes no current
+    fn load_resistance(&self) -> Option<f64> {
+        let current = self.output_current.get::<ampere>();
+        let voltage = self.output_voltage.get::<volt>();
+        (current > 0.0 && voltage > 0.0).then(|| voltage / current)
+    }
+
+    fn regulate(&mut self, dt: f64, speed_ratio: f64) {
+        let rated_voltage = self.rated_voltage.get::<volt>();
+        let internal_resistance = self.internal_resistance.get::<ohm>();
+        let load_resistance = self.load_resistance();
+        let steps = (dt / REGULATOR_STEP).ceil().max(1.0);
+        let step = dt / steps;
+        let exciter_alpha = 1.0 - (-step / EXCITER_TIME_CONSTANT).exp();
+        let max_integral = CEILING_EXCITATION / REGULATOR_GAIN;
+
+        for _ in 0..steps as usize {
+            let emf = rated_voltage * speed_ratio * self.excitation;
+            let terminal_voltage = match load_resistance {
+                Some(load) => emf * load / (load + internal_resistance),
+                None => emf,
+            };
+            let error = (rated_voltage - terminal_voltage) / rated_voltage;
+            self.regulator_integral = (self.regulator_integral
+                + error * step / REGULATOR_INTEGRAL_TIME)
+                .clamp(0.0, max_integral);
+            let command =
+                (REGULATOR_GAIN * (error + self.regulator_integral)).clamp(0.0, CEILING_EXCITATION);
+            self.excitation += (command - self.excitation) * exciter_alpha;
+        }
     }
 }
 
@@ -88,45 +182,45 @@ impl ElectricalComponent for Generator {
         self
     }
     fn update(&mut self, dt: f32) {
-        if !self.is_on {
-            self.output_power = Power::new::<watt>(0.0);
-            self.output_voltage = ElectricPotential::new::<volt>(0.0);
-            self.current_rpm = AngularVelocity::new::<revolution_per_minute>(0.0);
-            return;
-        }
-        println!("Generator is on");
-        self.time_on += Time::new::<millisecond>(dt as f64);
-
-        let spin_progress = if self.spin_up_time.get::<millisecond>() > 0.0 {
-            (self.time_on.get::<millisecond>() / self.spin_up_time.get::<millisecond>() * 1000.0)
-                .min(1.0)
+        let dt = dt as f64;
+        let time_constant = self.spin_up_time.get::<second>();
+        let alpha = if time_constant > 0.0 {
+            1.0 - (-dt / time_constant).exp()
         } else {
             1.0
         };
-
+        let rpm = self.current_rpm.get::<revolution_per_minute>();
         self.current_rpm = AngularVelocity::new::<revolution_per_minute>(
-            self.rpm.get::<revolution_per_minute>() * spin_progress,
+            rpm + (self.rpm.get::<revolution_per_minute>() - rpm) * alpha,
         );
 
+        if !self.is_on {
+            self.output_power = Power::new::<watt>(0.0);
+            self.output_voltage = ElectricPotential::new::<volt>(0.0);
+            self.internal_voltage = ElectricPotential::new::<volt>(0.0);
+            return;
+        }
         let expected_rpm = self.rated_frequency.get::<hertz>() * 60.0 / self.num_poles;
-        let efficiency_factor = if self.current_rpm.get::<revolution_per_minute>() >= expected_rpm {
-            self.efficiency.get::<ratio>()
-        } else {
-            self.efficiency.get::<ratio>()
-                * (self.rpm.get::<revolution_per_minute>() / expected_rpm)
-        };
+        let speed_ratio = self.current_rpm.get::<revolution_per_minute>() / expected_rpm;
+        let efficiency_factor = self.efficiency.get::<ratio>() * speed_ratio.min(1.0);
 
         let available_electrical_power =
             self.mechanical_input_power.get::<watt>() * efficiency_factor;
-        self.output_power =
-            Power::new::<watt>(available_electrical_power.min(self.rated_power.get::<watt>()));
-
-        let current = self.output_power.get::<watt>() / self.rated_voltage.get::<volt>()
-            * self.phase_count as f64;
-        let voltage_drop = current * self.internal_resistance.get::<ohm>();
-        self.output_voltage = ElectricPotential::new::<volt>(
-            (self.rated_voltage.get::<volt>() - voltage_drop).max(0.0),
-        );
+        let available_power = available_electrical_power.min(self.rated_power.get::<watt>());
+
+        self.regulate(dt, speed_ratio);
+        let emf = self.rated_voltage.get::<volt>() * speed_ratio * self.excitation;
+
+        // The network decides how much current we deliver, we only set the EMF. If the load of the last
+        // solve would take more than the shaft can give, hold the EMF where it settles at the available power.
+        let limit = match self.load_resistance() {
+            Some(load) => {
+                (load + self.internal_resistance.get::<ohm>())
+                    * (available_power.max(0.0) / load).sqrt()
+            }
+            None => f64::INFINITY,
+        };
+        self.internal_voltage = ElectricPotential::new::<volt>(emf.min(limit));
     }
 
     fn get_output_power(&self) -> Power {
@@ -134,26 +228,167 @@ impl ElectricalComponent for Generator {
     }
 
     fn set_input_power(&mut self, _power: Power) {
-        // Generators do not take input power
+        // Generators do not take input power, output power follows the delivered current
     }
 
     fn get_output_voltage(&self) -> ElectricPotential {
         self.output_voltage
     }
 
-    fn set_input_voltage(&mut self, _voltage: ElectricPotential) {
-        // Generators don't take input, this will just be generated in the update
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        // The solver reports the terminal voltage back to us through the input
+        self.output_voltage = voltage;
     }
 
     fn get_output_current(&self) -> ElectricCurrent {
-        if self.output_voltage.value > 0.0 {
-            ElectricCurrent::new::<ampere>(self.output_power.value / self.output_voltage.value)
-        } else {
-            ElectricCurrent::new::<ampere>(0.0)
+        self.output_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        // Current delivered into the network
+        self.output_current = current;
+        self.output_power = self.output_voltage * current;
+    }
+
+    // The shaft power that does not become EMF, the windings are counted by the network
+    fn heat_loss(&self) -> Power {
+        let efficiency = self.efficiency.get::<ratio>().clamp(0.01, 1.0);
+        let electrical_power = (self.internal_voltage * self.output_current).abs();
+        electrical_power * (1.0 / efficiency - 1.0)
+    }
+
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        Some(self.output_frequency())
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if !self.is_on {
+            return CircuitModel::Open;
+        }
+        CircuitModel::Source {
+            voltage: self.internal_voltage,
+            internal_resistance: self.internal_resistance,
         }
     }
 
-    fn set_input_current(&mut self, _current: ElectricCurrent) {
-        // No need to set on generator
+    fn is_source(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::components::ac::generic_ac_component::{
+        FrequencyResponse, GenericAcComponent, PhaseConnection,
+    };
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::wire::Wire;
+    use crate::systems::electrical::{ComponentHandle, ElectricalSystem};
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+
+    struct Bench {
+        system: ElectricalSystem,
+        generator: ComponentHandle<Generator>,
+        step_load: ComponentHandle<GenericAcComponent>,
+    }
+
+    fn heater(name: &str, power: f64) -> GenericAcComponent {
+        GenericAcComponent::new(
+            name,
+            power,
+            1.0,
+            PhaseConnection::ThreePhase,
+            FrequencyResponse::Impedance,
+            90.0,
+            130.0,
+        )
+    }
+
+    // 40 kVA generator at rated speed feeding a 10 kW base load, with 20 kW more to switch on
+    fn bench() -> Bench {
+        let mut system = ElectricalSystem::new();
+        let mut generator = Generator::new(2.0, 40000.0, 115.0, 400.0, 0.95, 0.05, 0.0, 3);
+        generator.set_mechanical_input(60000.0, 12000.0);
+        generator.turn_on();
+        let generator = system.add_component("GEN", generator).unwrap();
+        let bus = Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        };
+        let bus = system.add_component("AC Bus", bus).unwrap();
+        let mut base_load = heater("Base load", 10000.0);
+        base_load.set_power_state(true);
+        let base_load = system.add_component("Base load", base_load).unwrap();
+        let step_load = system
+            .add_component("Step load", heater("Step load", 20000.0))
+            .unwrap();
+        system.connect_no_resistance(generator, bus);
+        system.connect_with_wire(bus, base_load, Wire::fixed(0.0));
+        system.connect_with_wire(bus, step_load, Wire::fixed(0.0));
+
+        let mut bench = Bench {
+            system,
+            generator,
+            step_load,
+        };
+        bench.run(2.0);
+        assert!((bench.voltage() - 115.0).abs() < 0.5);
+        bench
+    }
+
+    impl Bench {
+        fn run(&mut self, seconds: f64) {
+            for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+                self.system.update_system(TIME_STEP);
+            }
+        }
+
+        fn voltage(&self) -> f64 {
+            self.system
+                .get(self.generator)
+                .unwrap()
+                .get_output_voltage()
+                .get::<volt>()
+        }
+
+        fn switch_step_load(&mut self, on: bool) {
+            self.system
+                .get_mut(self.step_load)
+                .unwrap()
+                .set_power_state(on);
+        }
+    }
+
+    #[test]
+    fn load_step_sags_the_voltage_until_the_regulator_catches_up() {
+        let mut bench = bench();
+        let excitation = bench.system.get(bench.generator).unwrap().excitation();
+
+        bench.switch_step_load(true);
+        bench.run(0.1);
+        let sag = bench.voltage();
+        assert!(sag < 110.0, "{sag:.1}V right after the step");
+
+        bench.run(1.0);
+        assert!((bench.voltage() - 115.0).abs() < 0.5);
+        // The field carries the extra drop across the internal resistance
+        assert!(bench.system.get(bench.generator).unwrap().excitation() > excitation);
+    }
+
+    #[test]
+    fn dropped_load_leaves_the_voltage_high_until_the_field_comes_down() {
+        let mut bench = bench();
+        bench.switch_step_load(true);
+        bench.run(2.0);
+
+        bench.switch_step_load(false);
+        bench.run(0.1);
+        let swell = bench.voltage();
+        assert!(swell > 117.0, "{swell:.1}V right after the load dropped");
+
+        bench.run(1.0);
+        assert!((bench.voltage() - 115.0).abs() < 0.5);
     }
 }
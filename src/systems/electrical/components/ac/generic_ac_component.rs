@@ -0,0 +1,481 @@
+// Generic AC consumer.
+// A load on a 115V 400Hz bus, given by its real power and its true power factor. Single phase loads sit on
+// one phase, three phase loads share their power over all three (the voltage is the phase voltage). How the
+// load follows the bus frequency depends on what it is, see `FrequencyResponse`.
+// The network is a single line equivalent of the three phase system, like the generators and motors on it:
+// a bus carries the whole real power of its loads at the phase voltage, so the current it solves for a
+// load is real power / phase voltage. That is phase count x power factor x `line_current`, the RMS current
+// in each phase conductor. The reactive power stays out of the network and shows in the apparent power,
+// the line current and the VA of the load analysis.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use serde::Deserialize;
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+use uom::si::power::watt;
+
+// Every AC bus runs at the same nominal supply, the power of a load is given at these
+const NOMINAL_VOLTAGE: f64 = 115.0; // Volt, phase to neutral
+const NOMINAL_FREQUENCY: f64 = 400.0; // Hz
+// Fraction of the nominal voltage below which a load no longer draws a constant power
+const CONSTANT_POWER_FLOOR: f64 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseConnection {
+    SinglePhase,
+    ThreePhase,
+}
+
+impl PhaseConnection {
+    pub fn phase_count(&self) -> u8 {
+        match self {
+            PhaseConnection::SinglePhase => 1,
+            PhaseConnection::ThreePhase => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencyResponse {
+    // Power supplies, draw the same power at any frequency
+    Constant,
+    // Heaters and other fixed impedances, the inductive part grows with the frequency
+    Impedance,
+    // Fans and pumps, the motor follows the frequency and the power goes with the cube of the speed
+    Fan,
+}
+
+// Supply voltage against the limits of the load
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum VoltageCondition {
+    Normal,
+    Over,
+    Under,
+}
+
+pub struct GenericAcComponent {
+    name: String,
+    nominal_voltage: ElectricPotential,
+    nominal_frequency: Frequency,
+    nominal_power: Power,
+    power_factor: f64,
+    connection: PhaseConnection,
+    frequency_response: FrequencyResponse,
+    min_voltage: ElectricPotential,
+    max_voltage: ElectricPotential,
+
+    input_voltage: ElectricPotential,
+    input_frequency: Frequency,
+    input_current: ElectricCurrent,
+
+    is_on: bool,
+    // Held off by the BPCU load shedding whatever the switch says
+    is_shed: bool,
+    load_factor: f64,
+    voltage_condition: VoltageCondition,
+}
+
+impl GenericAcComponent {
+    pub fn new(
+        name: &str,
+        nominal_power: f64,
+        power_factor: f64,
+        connection: PhaseConnection,
+        frequency_response: FrequencyResponse,
+        min_voltage: f64,
+        max_voltage: f64,
+    ) -> Self {
+        GenericAcComponent {
+            name: name.to_string(),
+            nominal_voltage: ElectricPotential::new::<volt>(NOMINAL_VOLTAGE),
+            nominal_frequency: Frequency::new::<hertz>(NOMINAL_FREQUENCY),
+            nominal_power: Power::new::<watt>(nominal_power),
+            power_factor: power_factor.clamp(0.01, 1.0),
+            connection,
+            frequency_response,
+            min_voltage: ElectricPotential::new::<volt>(min_voltage),
+            max_voltage: ElectricPotential::new::<volt>(max_voltage),
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            input_frequency: Frequency::new::<hertz>(0.0),
+            input_current: ElectricCurrent::new::<ampere>(0.0),
+
+            is_on: false,
+            is_shed: false,
+            load_factor: 1.0,
+            voltage_condition: VoltageCondition::Normal,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_power_state(&mut self, on: bool) {
+        self.is_on = on;
+    }
+
+    pub fn set_load_factor(&mut self, factor: f64) {
+        self.load_factor = factor.clamp(0.0, 1.0);
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    pub fn set_shed(&mut self, shed: bool) {
+        self.is_shed = shed;
+    }
+
+    pub fn is_shed(&self) -> bool {
+        self.is_shed
+    }
+
+    // Switched on and not shed
+    fn is_active(&self) -> bool {
+        self.is_on && !self.is_shed
+    }
+
+    pub fn connection(&self) -> PhaseConnection {
+        self.connection
+    }
+
+    pub fn frequency_response(&self) -> FrequencyResponse {
+        self.frequency_response
+    }
+
+    pub fn input_frequency(&self) -> Frequency {
+        self.input_frequency
+    }
+
+    fn is_powered(&self) -> bool {
+        self.is_active()
+            && self.input_voltage >= self.min_voltage
+            && self.input_frequency.get::<hertz>() > 0.0
+    }
+
+    // A de-energised bus is not an undervoltage, the load is just off
+    fn voltage_condition(&self) -> VoltageCondition {
+        if !self.is_active() || self.input_voltage.get::<volt>() <= 0.0 {
+            VoltageCondition::Normal
+        } else if self.input_voltage > self.max_voltage {
+            VoltageCondition::Over
+        } else if self.input_voltage < self.min_voltage {
+            VoltageCondition::Under
+        } else {
+            VoltageCondition::Normal
+        }
+    }
+
+    // Real and reactive (var) power at the present bus voltage and frequency
+    fn operating_point(&self) -> (Power, Power) {
+        if !self.is_powered() {
+            return (Power::new::<watt>(0.0), Power::new::<watt>(0.0));
+        }
+        let nominal_real = self.nominal_power * self.load_factor;
+        let nominal_reactive = nominal_real * self.power_factor.acos().tan();
+        let frequency_ratio = (self.input_frequency / self.nominal_frequency).value;
+
+        match self.frequency_response {
+            FrequencyResponse::Constant => (nominal_real, nominal_reactive),
+            FrequencyResponse::Impedance => {
+                // Fixed R with X scaling with the frequency, both per unit of the nominal impedance
+                let resistance = self.power_factor;
+                let reactance = self.power_factor.acos().sin() * frequency_ratio;
+                let impedance = resistance.hypot(reactance);
+                let current_ratio = (self.input_voltage / self.nominal_voltage).value / impedance;
+                let current_squared = current_ratio * current_ratio;
+                (
+                    nominal_real * current_squared,
+                    nominal_reactive * current_squared * frequency_ratio,
+                )
+            }
+            FrequencyResponse::Fan => {
+                let speed_factor = frequency_ratio.powi(3);
+                (nominal_real * speed_factor, nominal_reactive * speed_factor)
+            }
+        }
+    }
+
+    pub fn real_power(&self) -> Power {
+        self.operating_point().0
+    }
+
+    // In var
+    pub fn reactive_power(&self) -> Power {
+        self.operating_point().1
+    }
+
+    // In VA
+    pub fn apparent_power(&self) -> Power {
+        let (real, reactive) = self.operating_point();
+        Power::new::<watt>(real.get::<watt>().hypot(reactive.get::<watt>()))
+    }
+
+    // True power factor at the operating point, the nominal one while the load is off
+    pub fn power_factor(&self) -> f64 {
+        let apparent = self.apparent_power();
+        if apparent.get::<watt>() > 0.0 {
+            (self.real_power() / apparent).value
+        } else {
+            self.power_factor
+        }
+    }
+
+    // RMS current in each phase conductor
+    pub fn line_current(&self) -> ElectricCurrent {
+        if self.input_voltage.get::<volt>() <= 0.0 {
+            return ElectricCurrent::new::<ampere>(0.0);
+        }
+        let phases = self.connection.phase_count() as f64;
+        self.apparent_power() / (self.input_voltage * phases)
+    }
+}
+
+impl ElectricalComponent for GenericAcComponent {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, _dt: f32) {
+        // Reported when the voltage leaves the limits and when it comes back, not on every tick
+        let condition = self.voltage_condition();
+        if condition != self.voltage_condition {
+            match condition {
+                VoltageCondition::Over => eprintln!(
+                    "⚠️ OVERVOLTAGE: {}V > {}V max for {}",
+                    self.input_voltage.value, self.max_voltage.value, self.name
+                ),
+                VoltageCondition::Under => eprintln!(
+                    "⚠️ UNDERVOLTAGE: {}V < {}V min for {}",
+                    self.input_voltage.value, self.min_voltage.value, self.name
+                ),
+                VoltageCondition::Normal => {
+                    eprintln!("⚡ {} voltage back within limits", self.name)
+                }
+            }
+        }
+        self.voltage_condition = condition;
+    }
+
+    fn get_output_power(&self) -> Power {
+        Power::new::<watt>(0.0)
+    }
+
+    fn set_input_power(&mut self, _power: Power) {}
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        ElectricPotential::new::<volt>(0.0)
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.input_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        ElectricCurrent::new::<ampere>(0.0)
+    }
+
+    fn get_input_current(&self) -> ElectricCurrent {
+        self.input_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.input_current = current;
+    }
+
+    fn set_input_frequency(&mut self, frequency: Frequency) {
+        self.input_frequency = frequency;
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        // Real power on the single line equivalent, linearised around the last solved voltage. As for the DC
+        // loads, a load that has not seen any voltage stays off the network and below the floor it is held
+        // at the resistance it has there.
+        let power = self.real_power();
+        let resistance = if power.get::<watt>() > 0.0 && self.input_voltage.get::<volt>() > 0.0 {
+            let voltage = self
+                .input_voltage
+                .max(self.nominal_voltage * CONSTANT_POWER_FLOOR);
+            ElectricalResistance::new::<ohm>(voltage.get::<volt>().powi(2) / power.get::<watt>())
+        } else {
+            ElectricalResistance::new::<ohm>(f64::INFINITY)
+        };
+        CircuitModel::Load { resistance }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(connection: PhaseConnection, response: FrequencyResponse) -> GenericAcComponent {
+        let mut load =
+            GenericAcComponent::new("Load", 3000.0, 0.8, connection, response, 90.0, 130.0);
+        load.set_power_state(true);
+        supply(&mut load, 115.0, 400.0);
+        load
+    }
+
+    fn supply(load: &mut GenericAcComponent, voltage: f64, frequency: f64) {
+        load.set_input_voltage(ElectricPotential::new::<volt>(voltage));
+        load.set_input_frequency(Frequency::new::<hertz>(frequency));
+    }
+
+    #[test]
+    fn reactive_and_apparent_power_follow_the_power_factor() {
+        let load = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+
+        assert!((load.real_power().get::<watt>() - 3000.0).abs() < 1e-9);
+        assert!((load.reactive_power().get::<watt>() - 2250.0).abs() < 1e-9);
+        assert!((load.apparent_power().get::<watt>() - 3750.0).abs() < 1e-9);
+        assert!((load.power_factor() - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn line_current_is_shared_over_the_phases() {
+        let three_phase = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+        let single_phase = load(PhaseConnection::SinglePhase, FrequencyResponse::Constant);
+
+        // 3750 VA over three 115V phases, all of it on the one phase
+        assert!((three_phase.line_current().get::<ampere>() - 3750.0 / 345.0).abs() < 1e-9);
+        assert!((single_phase.line_current().get::<ampere>() - 3750.0 / 115.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn network_carries_the_real_power_on_the_single_line() {
+        let load = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+        let CircuitModel::Load { resistance } = load.circuit_model() else {
+            panic!("an AC consumer is a load");
+        };
+
+        let network_current = ElectricPotential::new::<volt>(115.0) / resistance;
+        assert!((network_current.get::<ampere>() - 3000.0 / 115.0).abs() < 1e-9);
+        assert!(
+            (network_current - load.line_current() * 3.0 * 0.8)
+                .get::<ampere>()
+                .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn constant_load_ignores_the_frequency() {
+        let mut load = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+        supply(&mut load, 115.0, 360.0);
+
+        assert!((load.real_power().get::<watt>() - 3000.0).abs() < 1e-9);
+        assert!((load.reactive_power().get::<watt>() - 2250.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn impedance_follows_the_voltage_and_the_reactance_the_frequency() {
+        let mut load = load(PhaseConnection::ThreePhase, FrequencyResponse::Impedance);
+        supply(&mut load, 103.5, 400.0);
+        // 90 % voltage on the same impedance
+        assert!((load.real_power().get::<watt>() - 3000.0 * 0.81).abs() < 1e-9);
+
+        supply(&mut load, 115.0, 360.0);
+        // Per unit R 0.8 and X 0.6 x 0.9, the lower impedance draws more current
+        let current_squared = 1.0 / (0.8f64.powi(2) + 0.54f64.powi(2));
+        assert!((load.real_power().get::<watt>() - 3000.0 * current_squared).abs() < 1e-9);
+        assert!(
+            (load.reactive_power().get::<watt>() - 2250.0 * current_squared * 0.9).abs() < 1e-9
+        );
+        assert!(load.power_factor() > 0.8);
+    }
+
+    #[test]
+    fn fan_power_goes_with_the_cube_of_the_frequency() {
+        let mut load = load(PhaseConnection::ThreePhase, FrequencyResponse::Fan);
+        supply(&mut load, 115.0, 360.0);
+
+        assert!((load.real_power().get::<watt>() - 3000.0 * 0.729).abs() < 1e-9);
+        assert!((load.power_factor() - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dead_bus_draws_nothing() {
+        let mut load = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+        supply(&mut load, 115.0, 0.0);
+
+        assert_eq!(load.apparent_power().get::<watt>(), 0.0);
+        assert_eq!(load.line_current().get::<ampere>(), 0.0);
+        assert!(matches!(
+            load.circuit_model(),
+            CircuitModel::Load { resistance } if resistance.get::<ohm>().is_infinite()
+        ));
+    }
+
+    fn resistance(load: &GenericAcComponent) -> f64 {
+        match load.circuit_model() {
+            CircuitModel::Load { resistance } => resistance.get::<ohm>(),
+            _ => panic!("an AC consumer is a load"),
+        }
+    }
+
+    #[test]
+    fn unpowered_load_is_not_a_short() {
+        // No minimum voltage and a live frequency, the load asks for its power at 0 V
+        let mut load = GenericAcComponent::new(
+            "Load",
+            3000.0,
+            0.8,
+            PhaseConnection::ThreePhase,
+            FrequencyResponse::Constant,
+            0.0,
+            130.0,
+        );
+        load.set_power_state(true);
+        supply(&mut load, 0.0, 400.0);
+        assert!(resistance(&load).is_infinite());
+
+        supply(&mut load, 115.0, 400.0);
+        assert!((resistance(&load) - 115.0 * 115.0 / 3000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn constant_power_load_is_held_below_the_floor() {
+        let mut load = GenericAcComponent::new(
+            "Load",
+            3000.0,
+            0.8,
+            PhaseConnection::ThreePhase,
+            FrequencyResponse::Constant,
+            0.0,
+            130.0,
+        );
+        load.set_power_state(true);
+        supply(&mut load, 20.0, 400.0);
+
+        // Linearised at half the nominal voltage rather than the sagging bus
+        assert!((resistance(&load) - 57.5 * 57.5 / 3000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn voltage_condition_is_tracked_across_ticks() {
+        let mut load = load(PhaseConnection::ThreePhase, FrequencyResponse::Constant);
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Normal);
+
+        supply(&mut load, 80.0, 400.0);
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Under);
+
+        // A dead bus is not an undervoltage
+        supply(&mut load, 0.0, 0.0);
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Normal);
+    }
+}
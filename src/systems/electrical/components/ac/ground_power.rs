@@ -0,0 +1,312 @@
+// External (ground) power.
+// The GPU cart plugs into the external power receptacle. Its voltage and frequency are monitored and
+// the power has to stay inside the limits for a qualification time before it is declared available,
+// out of tolerance power is rejected. The external power contactor closes once the power is available,
+// the crew selected it and the BPCU gave it priority over the other sources feeding the same bus.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+use uom::si::power::watt;
+use uom::si::time::second;
+
+const QUALIFICATION_TIME: f64 = 3.0; // seconds of good power before it is available
+const REJECTION_TIME: f64 = 0.5; // seconds of bad power before it is rejected
+const MIN_VOLTAGE: f64 = 108.0; // Volt
+const MAX_VOLTAGE: f64 = 122.0; // Volt
+const MIN_FREQUENCY: f64 = 380.0; // Hz
+const MAX_FREQUENCY: f64 = 420.0; // Hz
+
+pub struct GroundPower {
+    name: String,
+    internal_resistance: ElectricalResistance,
+
+    supply_voltage: ElectricPotential,
+    supply_frequency: Frequency,
+
+    is_connected: bool,
+    is_selected: bool,
+    is_permitted: bool,
+    is_available: bool,
+    quality_timer: Time,
+
+    output_voltage: ElectricPotential,
+    output_current: ElectricCurrent,
+    output_power: Power,
+}
+
+impl GroundPower {
+    pub fn new(
+        name: &str,
+        supply_voltage: f64,
+        supply_frequency: f64,
+        internal_resistance: f64,
+    ) -> Self {
+        GroundPower {
+            name: name.to_string(),
+            internal_resistance: ElectricalResistance::new::<ohm>(internal_resistance),
+
+            supply_voltage: ElectricPotential::new::<volt>(supply_voltage),
+            supply_frequency: Frequency::new::<hertz>(supply_frequency),
+
+            is_connected: false,
+            is_selected: false,
+            is_permitted: true,
+            is_available: false,
+            quality_timer: Time::new::<second>(0.0),
+
+            output_voltage: ElectricPotential::new::<volt>(0.0),
+            output_current: ElectricCurrent::new::<ampere>(0.0),
+            output_power: Power::new::<watt>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn connect(&mut self) {
+        self.is_connected = true;
+    }
+
+    pub fn disconnect(&mut self) {
+        self.is_connected = false;
+        self.is_available = false;
+        self.quality_timer = Time::new::<second>(0.0);
+    }
+
+    // What the cart is delivering, lets a scenario feed out of tolerance power
+    pub fn set_supply(&mut self, voltage: ElectricPotential, frequency: Frequency) {
+        self.supply_voltage = voltage;
+        self.supply_frequency = frequency;
+    }
+
+    // Cockpit GPU button
+    pub fn set_selected(&mut self, selected: bool) {
+        self.is_selected = selected;
+    }
+
+    // Source priority from the BPCU
+    pub fn set_permitted(&mut self, permitted: bool) {
+        self.is_permitted = permitted;
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.is_connected
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.is_selected
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.is_available
+    }
+
+    // External power contactor closed, the GPU is feeding the aircraft
+    pub fn is_in_use(&self) -> bool {
+        self.is_available && self.is_selected && self.is_permitted
+    }
+
+    pub fn is_quality_good(&self) -> bool {
+        let voltage = self.supply_voltage.get::<volt>();
+        let frequency = self.supply_frequency.get::<hertz>();
+        (MIN_VOLTAGE..=MAX_VOLTAGE).contains(&voltage)
+            && (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency)
+    }
+
+    pub fn output_frequency(&self) -> Frequency {
+        if self.is_in_use() {
+            self.supply_frequency
+        } else {
+            Frequency::new::<hertz>(0.0)
+        }
+    }
+}
+
+impl ElectricalComponent for GroundPower {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        if !self.is_connected {
+            return;
+        }
+
+        // The timer counts how long the power has been on the other side of the current decision
+        let good = self.is_quality_good();
+        if good != self.is_available {
+            self.quality_timer += Time::new::<second>(dt as f64);
+        } else {
+            self.quality_timer = Time::new::<second>(0.0);
+        }
+
+        if good && !self.is_available && self.quality_timer.get::<second>() >= QUALIFICATION_TIME {
+            self.is_available = true;
+            self.quality_timer = Time::new::<second>(0.0);
+            eprintln!("🔌 {} AVAILABLE", self.name);
+        } else if !good && self.is_available && self.quality_timer.get::<second>() >= REJECTION_TIME
+        {
+            self.is_available = false;
+            self.quality_timer = Time::new::<second>(0.0);
+            eprintln!(
+                "⚠️ {} REJECTED: {:.1}V {:.1}Hz",
+                self.name,
+                self.supply_voltage.get::<volt>(),
+                self.supply_frequency.get::<hertz>()
+            );
+        }
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.output_power
+    }
+
+    fn set_input_power(&mut self, _power: Power) {
+        // Output power follows the delivered current
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.output_voltage
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        // Terminal voltage reported by the network
+        self.output_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.output_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.output_current = current;
+        self.output_power = self.output_voltage * current;
+    }
+
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        Some(self.output_frequency())
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if !self.is_in_use() {
+            return CircuitModel::Open;
+        }
+        CircuitModel::Source {
+            voltage: self.supply_voltage,
+            internal_resistance: self.internal_resistance,
+        }
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+
+    fn run(gpu: &mut GroundPower, seconds: f64) {
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            gpu.update(TIME_STEP);
+        }
+    }
+
+    fn supply(gpu: &mut GroundPower, voltage: f64, frequency: f64) {
+        gpu.set_supply(
+            ElectricPotential::new::<volt>(voltage),
+            Frequency::new::<hertz>(frequency),
+        );
+    }
+
+    fn cart() -> GroundPower {
+        let mut gpu = GroundPower::new("GPU", 115.0, 400.0, 0.05);
+        gpu.connect();
+        gpu
+    }
+
+    // Plugged in, selected and qualified
+    fn in_use() -> GroundPower {
+        let mut gpu = cart();
+        gpu.set_selected(true);
+        run(&mut gpu, QUALIFICATION_TIME + 0.5);
+        assert!(gpu.is_in_use());
+        gpu
+    }
+
+    #[test]
+    fn power_is_available_after_the_qualification_time() {
+        let mut gpu = cart();
+        run(&mut gpu, QUALIFICATION_TIME - 0.5);
+        assert!(!gpu.is_available());
+        run(&mut gpu, 1.0);
+        assert!(gpu.is_available());
+
+        // Available is not in use until the crew selects it
+        assert!(matches!(gpu.circuit_model(), CircuitModel::Open));
+        gpu.set_selected(true);
+        assert!(matches!(gpu.circuit_model(), CircuitModel::Source { .. }));
+        assert_eq!(gpu.output_frequency().get::<hertz>(), 400.0);
+    }
+
+    #[test]
+    fn limits_are_inclusive() {
+        let mut gpu = cart();
+        for (voltage, frequency, good) in [
+            (MIN_VOLTAGE, MIN_FREQUENCY, true),
+            (MAX_VOLTAGE, MAX_FREQUENCY, true),
+            (MIN_VOLTAGE - 0.1, 400.0, false),
+            (MAX_VOLTAGE + 0.1, 400.0, false),
+            (115.0, MIN_FREQUENCY - 0.1, false),
+            (115.0, MAX_FREQUENCY + 0.1, false),
+        ] {
+            supply(&mut gpu, voltage, frequency);
+            assert_eq!(gpu.is_quality_good(), good, "{voltage}V {frequency}Hz");
+        }
+    }
+
+    #[test]
+    fn out_of_tolerance_power_is_rejected_after_the_rejection_time() {
+        let mut gpu = in_use();
+        supply(&mut gpu, 125.0, 400.0);
+        run(&mut gpu, REJECTION_TIME - 0.2);
+        assert!(gpu.is_in_use());
+        run(&mut gpu, 0.3);
+        assert!(!gpu.is_available());
+        assert!(matches!(gpu.circuit_model(), CircuitModel::Open));
+        assert_eq!(gpu.output_frequency().get::<hertz>(), 0.0);
+
+        // Good power again has to qualify all over
+        supply(&mut gpu, 115.0, 400.0);
+        run(&mut gpu, QUALIFICATION_TIME - 0.5);
+        assert!(!gpu.is_available());
+        run(&mut gpu, 1.0);
+        assert!(gpu.is_in_use());
+    }
+
+    #[test]
+    fn short_glitch_is_ridden_through() {
+        let mut gpu = in_use();
+        supply(&mut gpu, 115.0, 370.0);
+        run(&mut gpu, REJECTION_TIME - 0.2);
+        supply(&mut gpu, 115.0, 400.0);
+        run(&mut gpu, TIME_STEP as f64);
+        // The timer starts over, another glitch of the same length is ridden through as well
+        supply(&mut gpu, 115.0, 370.0);
+        run(&mut gpu, REJECTION_TIME - 0.2);
+        assert!(gpu.is_in_use());
+    }
+}
@@ -0,0 +1,403 @@
+// Integrated Drive Generator.
+// The constant speed drive sits between the engine accessory gearbox and the generator and holds the
+// generator at its rated speed (400Hz) across the governing range of N2. Below the underspeed N2 the
+// drive runs out of authority and the frequency follows the engine down. The drive can be mechanically
+// disconnected from the cockpit, this is one way and can only be undone on the ground with the engine
+// stopped.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::components::ac::generator::Generator;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::angular_velocity::revolution_per_minute;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::pressure::psi;
+use uom::si::ratio::percent;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+const GOVERNOR_TIME_CONSTANT: f64 = 0.3; // seconds
+const COAST_DOWN_TIME_CONSTANT: f64 = 4.0; // seconds, generator spinning down after a disconnect
+const UNDERSPEED_RATIO: f64 = 0.95; // of rated speed
+const MIN_DISCONNECT_N2: f64 = 5.0; // percent, the pawl needs the input shaft turning to engage
+
+const NOMINAL_OIL_PRESSURE: f64 = 200.0; // psi
+const LOW_OIL_PRESSURE: f64 = 100.0; // psi
+const HIGH_OIL_TEMPERATURE: f64 = 160.0; // degC
+const OIL_THERMAL_MASS: f64 = 8000.0; // J/degC
+const OIL_COOLER_CONDUCTANCE: f64 = 150.0; // W/degC
+const LOAD_HEAT_RATIO: f64 = 0.08; // fraction of the electrical load lost as heat in the drive
+const CHURNING_HEAT_RATIO: f64 = 0.02; // fraction of the rated power lost while turning
+
+pub struct IntegratedDriveGenerator {
+    name: String,
+    generator: Generator,
+    underspeed_n2: Ratio,
+
+    engine_n2: Ratio,
+    output_rpm: AngularVelocity,
+
+    oil_temperature: ThermodynamicTemperature,
+    oil_pressure: Pressure,
+    cooling_temperature: ThermodynamicTemperature,
+
+    // Weight on wheels, the disconnect can only be reset on the ground
+    is_on_ground: bool,
+    disconnect_commanded: bool,
+    is_disconnected: bool,
+}
+
+impl IntegratedDriveGenerator {
+    pub fn new(name: &str, generator: Generator, underspeed_n2: f64) -> Self {
+        IntegratedDriveGenerator {
+            name: name.to_string(),
+            generator,
+            underspeed_n2: Ratio::new::<percent>(underspeed_n2),
+
+            engine_n2: Ratio::new::<percent>(0.0),
+            output_rpm: AngularVelocity::new::<revolution_per_minute>(0.0),
+
+            oil_temperature: ThermodynamicTemperature::new::<degree_celsius>(20.0),
+            oil_pressure: Pressure::new::<psi>(0.0),
+            cooling_temperature: ThermodynamicTemperature::new::<degree_celsius>(20.0),
+
+            is_on_ground: true,
+            disconnect_commanded: false,
+            is_disconnected: false,
+        }
+    }
+
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+
+    pub fn generator_mut(&mut self) -> &mut Generator {
+        &mut self.generator
+    }
+
+    pub fn set_engine_n2(&mut self, n2: Ratio) {
+        self.engine_n2 = n2;
+    }
+
+    pub fn set_on_ground(&mut self, on_ground: bool) {
+        self.is_on_ground = on_ground;
+    }
+
+    // Fuel/oil heat exchanger temperature the drive oil is cooled against
+    pub fn set_cooling_temperature(&mut self, temperature: ThermodynamicTemperature) {
+        self.cooling_temperature = temperature;
+    }
+
+    pub fn command_disconnect(&mut self) {
+        self.disconnect_commanded = true;
+    }
+
+    // Ground maintenance reset of the disconnect mechanism, only possible on the ground with the engine
+    // stopped
+    pub fn reconnect(&mut self) -> bool {
+        if !self.is_on_ground || self.engine_n2.get::<percent>() > 0.0 {
+            return false;
+        }
+        self.disconnect_commanded = false;
+        self.is_disconnected = false;
+        true
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.is_disconnected
+    }
+
+    pub fn is_underspeed(&self) -> bool {
+        self.output_rpm < self.generator.rated_rpm() * UNDERSPEED_RATIO
+    }
+
+    // N2 below the underspeed N2, the drive cannot hold the rated speed
+    pub fn is_below_governing_range(&self) -> bool {
+        self.engine_n2 < self.underspeed_n2
+    }
+
+    pub fn output_rpm(&self) -> AngularVelocity {
+        self.output_rpm
+    }
+
+    pub fn output_frequency(&self) -> Frequency {
+        self.generator.output_frequency()
+    }
+
+    pub fn oil_temperature(&self) -> ThermodynamicTemperature {
+        self.oil_temperature
+    }
+
+    pub fn oil_pressure(&self) -> Pressure {
+        self.oil_pressure
+    }
+
+    pub fn is_low_oil_pressure(&self) -> bool {
+        self.oil_pressure.get::<psi>() < LOW_OIL_PRESSURE
+    }
+
+    pub fn is_high_oil_temperature(&self) -> bool {
+        self.oil_temperature.get::<degree_celsius>() > HIGH_OIL_TEMPERATURE
+    }
+
+    fn update_drive(&mut self, dt: f64) {
+        let n2 = self.engine_n2.get::<percent>();
+        if self.disconnect_commanded && !self.is_disconnected && n2 > MIN_DISCONNECT_N2 {
+            self.is_disconnected = true;
+            eprintln!("⚠️ {} DISCONNECTED", self.name);
+        }
+
+        let rated_rpm = self.generator.rated_rpm().get::<revolution_per_minute>();
+        let underspeed_n2 = self.underspeed_n2.get::<percent>();
+        let drive_authority = if underspeed_n2 > 0.0 {
+            (n2 / underspeed_n2).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let (target_rpm, time_constant) = if self.is_disconnected {
+            (0.0, COAST_DOWN_TIME_CONSTANT)
+        } else {
+            (rated_rpm * drive_authority, GOVERNOR_TIME_CONSTANT)
+        };
+        let rpm = self.output_rpm.get::<revolution_per_minute>();
+        let alpha = 1.0 - (-dt / time_constant).exp();
+        self.output_rpm =
+            AngularVelocity::new::<revolution_per_minute>(rpm + (target_rpm - rpm) * alpha);
+
+        // Charge pump runs off the input shaft
+        self.oil_pressure = if self.is_disconnected {
+            Pressure::new::<psi>(0.0)
+        } else {
+            Pressure::new::<psi>(NOMINAL_OIL_PRESSURE * drive_authority)
+        };
+
+        let speed_ratio = if rated_rpm > 0.0 {
+            self.output_rpm.get::<revolution_per_minute>() / rated_rpm
+        } else {
+            0.0
+        };
+        let load = self.generator.get_output_power().get::<watt>().max(0.0);
+        let heat = LOAD_HEAT_RATIO * load
+            + CHURNING_HEAT_RATIO * self.generator.rated_power().get::<watt>() * speed_ratio;
+        let was_high_oil_temperature = self.is_high_oil_temperature();
+        let temperature = self.oil_temperature.get::<degree_celsius>();
+        let cooling = (temperature - self.cooling_temperature.get::<degree_celsius>())
+            * OIL_COOLER_CONDUCTANCE
+            * speed_ratio.max(0.1);
+        self.oil_temperature = ThermodynamicTemperature::new::<degree_celsius>(
+            temperature + (heat - cooling) * dt / OIL_THERMAL_MASS,
+        );
+
+        // Reported when the oil goes over the limit, not on every tick it stays there
+        if self.is_high_oil_temperature() && !was_high_oil_temperature {
+            eprintln!(
+                "⚠️ {} OIL HIGH TEMP: {:.1}°C",
+                self.name,
+                self.oil_temperature.get::<degree_celsius>()
+            );
+        }
+
+        // The gearbox can give the generator more than its rating, the CSD output speed sets the frequency
+        self.generator.set_mechanical_input(
+            self.generator.rated_power().get::<watt>() * 1.5 * speed_ratio,
+            self.output_rpm.get::<revolution_per_minute>(),
+        );
+    }
+}
+
+impl ElectricalComponent for IntegratedDriveGenerator {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.update_drive(dt as f64);
+        self.generator.update(dt);
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.generator.get_output_power()
+    }
+
+    fn set_input_power(&mut self, power: Power) {
+        self.generator.set_input_power(power);
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.generator.get_output_voltage()
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.generator.set_input_voltage(voltage);
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.generator.get_output_current()
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.generator.set_input_current(current);
+    }
+
+    fn heat_loss(&self) -> Power {
+        self.generator.heat_loss()
+    }
+
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        self.generator.get_output_frequency()
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        self.generator.circuit_model()
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::electric_current::ampere;
+    use uom::si::electric_potential::volt;
+    use uom::si::frequency::hertz;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+    const UNDERSPEED_N2: f64 = 56.0; // percent
+    const RATED_RPM: f64 = 12000.0;
+
+    fn idg() -> IntegratedDriveGenerator {
+        let generator = Generator::new(2.0, 40000.0, 115.0, 400.0, 0.95, 0.05, 0.0, 3);
+        IntegratedDriveGenerator::new("IDG", generator, UNDERSPEED_N2)
+    }
+
+    fn run(idg: &mut IntegratedDriveGenerator, n2: f64, seconds: f64) {
+        idg.set_engine_n2(Ratio::new::<percent>(n2));
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            idg.update(TIME_STEP);
+        }
+    }
+
+    fn rpm(idg: &IntegratedDriveGenerator) -> f64 {
+        idg.output_rpm().get::<revolution_per_minute>()
+    }
+
+    #[test]
+    fn holds_rated_speed_in_the_governing_range_only() {
+        let mut idg = idg();
+        run(&mut idg, 80.0, 3.0);
+        assert!((rpm(&idg) - RATED_RPM).abs() < 1.0);
+        assert!((idg.output_frequency().get::<hertz>() - 400.0).abs() < 0.1);
+        assert!(!idg.is_underspeed());
+
+        run(&mut idg, UNDERSPEED_N2, 3.0);
+        assert!(!idg.is_below_governing_range());
+        assert!(!idg.is_underspeed());
+
+        // Half the underspeed N2, the frequency follows the engine down
+        run(&mut idg, UNDERSPEED_N2 / 2.0, 3.0);
+        assert!(idg.is_below_governing_range());
+        assert!(idg.is_underspeed());
+        assert!((rpm(&idg) - RATED_RPM / 2.0).abs() < 1.0);
+        assert!((idg.output_frequency().get::<hertz>() - 200.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn spin_up_does_not_depend_on_the_time_step() {
+        let mut coarse = idg();
+        coarse.set_engine_n2(Ratio::new::<percent>(80.0));
+        let mut fine = idg();
+        fine.set_engine_n2(Ratio::new::<percent>(80.0));
+        for _ in 0..5 {
+            coarse.update(0.1);
+            for _ in 0..10 {
+                fine.update(0.01);
+            }
+        }
+        assert!((rpm(&coarse) - rpm(&fine)).abs() < 1e-3 * RATED_RPM);
+    }
+
+    #[test]
+    fn disconnect_needs_the_input_shaft_turning_and_is_one_way() {
+        let mut idg = idg();
+        idg.command_disconnect();
+        run(&mut idg, MIN_DISCONNECT_N2 - 2.0, 1.0);
+        assert!(!idg.is_disconnected());
+
+        run(&mut idg, 80.0, TIME_STEP as f64);
+        assert!(idg.is_disconnected());
+        assert_eq!(idg.oil_pressure().get::<psi>(), 0.0);
+        assert!(idg.is_low_oil_pressure());
+        // The generator coasts down whatever the engine does
+        run(&mut idg, 80.0, 30.0);
+        assert!(rpm(&idg) < 0.01 * RATED_RPM);
+
+        // No reset with the engine turning
+        assert!(!idg.reconnect());
+        run(&mut idg, 80.0, 1.0);
+        assert!(idg.is_disconnected());
+
+        run(&mut idg, 0.0, 1.0);
+        assert!(idg.reconnect());
+        run(&mut idg, 80.0, 3.0);
+        assert!(!idg.is_disconnected());
+        assert!((rpm(&idg) - RATED_RPM).abs() < 1.0);
+    }
+
+    #[test]
+    fn disconnect_cannot_be_reset_in_flight() {
+        let mut idg = idg();
+        idg.set_on_ground(false);
+        idg.command_disconnect();
+        run(&mut idg, 80.0, 1.0);
+        assert!(idg.is_disconnected());
+
+        // Engine shut down in flight, still no reset
+        run(&mut idg, 0.0, 1.0);
+        assert!(!idg.reconnect());
+        assert!(idg.is_disconnected());
+
+        idg.set_on_ground(true);
+        assert!(idg.reconnect());
+        assert!(!idg.is_disconnected());
+    }
+
+    #[test]
+    fn oil_pressure_follows_the_input_shaft() {
+        let mut idg = idg();
+        run(&mut idg, 80.0, 1.0);
+        assert_eq!(idg.oil_pressure().get::<psi>(), NOMINAL_OIL_PRESSURE);
+        assert!(!idg.is_low_oil_pressure());
+
+        run(&mut idg, UNDERSPEED_N2 / 4.0, 1.0);
+        assert!((idg.oil_pressure().get::<psi>() - NOMINAL_OIL_PRESSURE / 4.0).abs() < 1e-9);
+        assert!(idg.is_low_oil_pressure());
+    }
+
+    #[test]
+    fn oil_heats_up_with_the_electrical_load() {
+        let mut idg = idg();
+        idg.set_cooling_temperature(ThermodynamicTemperature::new::<degree_celsius>(140.0));
+        idg.generator_mut().turn_on();
+
+        // Unloaded the churning losses keep the oil a few degrees over the cooler
+        run(&mut idg, 80.0, 600.0);
+        let unloaded = idg.oil_temperature().get::<degree_celsius>();
+        assert!((unloaded - (140.0 + 800.0 / OIL_COOLER_CONDUCTANCE)).abs() < 0.5);
+        assert!(!idg.is_high_oil_temperature());
+
+        // At the rated 40 kW the drive losses take it over the limit
+        idg.set_input_voltage(ElectricPotential::new::<volt>(115.0));
+        idg.set_input_current(ElectricCurrent::new::<ampere>(40000.0 / 115.0));
+        run(&mut idg, 80.0, 600.0);
+        let loaded = idg.oil_temperature().get::<degree_celsius>();
+        assert!((loaded - (140.0 + 4000.0 / OIL_COOLER_CONDUCTANCE)).abs() < 0.5);
+        assert!(idg.is_high_oil_temperature());
+    }
+}
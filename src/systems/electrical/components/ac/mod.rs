@@ -1 +1,6 @@
+pub mod air_driven_generator;
 pub mod generator;
+pub mod generic_ac_component;
+pub mod ground_power;
+pub mod integrated_drive_generator;
+pub mod static_inverter;
@@ -0,0 +1,114 @@
+// Static inverter, feeds a 115V AC bus from a 28V DC bus.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::components::shared::converter::Converter;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+
+const OUTPUT_FREQUENCY: f64 = 400.0; // Hz
+
+pub struct StaticInverter {
+    converter: Converter,
+}
+
+impl StaticInverter {
+    pub fn new(
+        name: &str,
+        output_voltage: f64,
+        rated_current: f64,
+        efficiency: f64,
+        output_resistance: f64,
+    ) -> Self {
+        StaticInverter {
+            // Regulates from 18 to 32V DC input
+            converter: Converter::new(
+                name,
+                18.0,
+                32.0,
+                output_voltage,
+                output_resistance,
+                rated_current,
+                efficiency,
+            ),
+        }
+    }
+
+    pub fn converter(&self) -> &Converter {
+        &self.converter
+    }
+
+    pub fn converter_mut(&mut self) -> &mut Converter {
+        &mut self.converter
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.converter.is_failed()
+    }
+}
+
+impl ElectricalComponent for StaticInverter {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.converter.update(dt);
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.converter.output_power()
+    }
+
+    fn set_input_power(&mut self, _power: Power) {
+        // Input power is derived from the delivered output power and the efficiency
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.converter.output_voltage()
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.converter.set_input_voltage(voltage);
+    }
+
+    fn set_output_voltage(&mut self, voltage: ElectricPotential) {
+        self.converter.set_output_voltage(voltage);
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.converter.output_current()
+    }
+
+    fn get_input_current(&self) -> ElectricCurrent {
+        self.converter.input_current()
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        // The solver reports the delivered output current for converters
+        self.converter.set_output_current(current);
+    }
+
+    // The inverter makes its own 400Hz
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        let frequency = if self.converter.is_operating() {
+            OUTPUT_FREQUENCY
+        } else {
+            0.0
+        };
+        Some(Frequency::new::<hertz>(frequency))
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        self.converter.circuit_model()
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,370 @@
+// NiCd aircraft battery (main and APU batteries).
+// Modelled as an open circuit voltage behind an internal resistance so the network decides whether it
+// discharges into a bus or gets charged from it. State of charge, the cell temperature and the usable
+// capacity (derated in the cold) are integrated from the current the network reports back.
+// A flat battery, its cells at the 1.0 V end of discharge, has nothing left to give: it drops off the
+// network and only comes back once the bus is above its open circuit voltage and can charge it.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+use crate::utils::interpolation::interpolate;
+
+use uom::si::electric_charge::ampere_hour;
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::time::{hour, second};
+
+const CELL_NOMINAL_VOLTAGE: f64 = 1.2; // Volt
+
+// Open circuit voltage of one cell against state of charge
+const CELL_OCV_CURVE: [(f64, f64); 7] = [
+    (0.0, 1.00),
+    (0.1, 1.18),
+    (0.2, 1.22),
+    (0.5, 1.26),
+    (0.8, 1.30),
+    (0.95, 1.35),
+    (1.0, 1.40),
+];
+
+// Usable capacity fraction against cell temperature in degC
+const CAPACITY_DERATE_CURVE: [(f64, f64); 5] = [
+    (-40.0, 0.40),
+    (-20.0, 0.65),
+    (0.0, 0.85),
+    (25.0, 1.0),
+    (60.0, 1.0),
+];
+
+const THERMAL_MASS: f64 = 25000.0; // J/degC
+const THERMAL_RESISTANCE: f64 = 0.5; // degC/W to the surrounding bay
+const OVERTEMPERATURE: f64 = 60.0; // degC
+
+pub struct Battery {
+    name: String,
+    cell_count: u32,
+    capacity: ElectricCharge,
+    nominal_internal_resistance: ElectricalResistance,
+
+    state_of_charge: Ratio,
+    temperature: ThermodynamicTemperature,
+    ambient_temperature: ThermodynamicTemperature,
+
+    terminal_voltage: ElectricPotential,
+    // Bus side while the battery is off the network
+    bus_voltage: ElectricPotential,
+    output_current: ElectricCurrent, // positive while discharging
+    depleted: bool,
+}
+
+impl Battery {
+    pub fn new(
+        name: &str,
+        nominal_voltage: f64,
+        capacity_ah: f64,
+        internal_resistance: f64,
+        state_of_charge: f64,
+    ) -> Self {
+        Battery {
+            name: name.to_string(),
+            cell_count: (nominal_voltage / CELL_NOMINAL_VOLTAGE).round().max(1.0) as u32,
+            capacity: ElectricCharge::new::<ampere_hour>(capacity_ah),
+            nominal_internal_resistance: ElectricalResistance::new::<ohm>(internal_resistance),
+
+            state_of_charge: Ratio::new::<ratio>(state_of_charge.clamp(0.0, 1.0)),
+            temperature: ThermodynamicTemperature::new::<degree_celsius>(20.0),
+            ambient_temperature: ThermodynamicTemperature::new::<degree_celsius>(20.0),
+
+            terminal_voltage: ElectricPotential::new::<volt>(0.0),
+            bus_voltage: ElectricPotential::new::<volt>(0.0),
+            output_current: ElectricCurrent::new::<ampere>(0.0),
+            depleted: false,
+        }
+    }
+
+    pub fn set_ambient_temperature(&mut self, temperature: ThermodynamicTemperature) {
+        self.ambient_temperature = temperature;
+    }
+
+    pub fn state_of_charge(&self) -> Ratio {
+        self.state_of_charge
+    }
+
+    pub fn temperature(&self) -> ThermodynamicTemperature {
+        self.temperature
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.output_current.value < 0.0
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.depleted
+    }
+
+    pub fn is_overtemperature(&self) -> bool {
+        self.temperature.get::<degree_celsius>() > OVERTEMPERATURE
+    }
+
+    pub fn open_circuit_voltage(&self) -> ElectricPotential {
+        let cell_voltage = interpolate(&CELL_OCV_CURVE, self.state_of_charge.get::<ratio>());
+        ElectricPotential::new::<volt>(cell_voltage * self.cell_count as f64)
+    }
+
+    pub fn usable_capacity(&self) -> ElectricCharge {
+        self.capacity
+            * interpolate(
+                &CAPACITY_DERATE_CURVE,
+                self.temperature.get::<degree_celsius>(),
+            )
+    }
+
+    // Nominal voltage at the one hour (1C) discharge rate
+    pub fn rated_power(&self) -> Power {
+        let nominal_voltage =
+            ElectricPotential::new::<volt>(CELL_NOMINAL_VOLTAGE * self.cell_count as f64);
+        nominal_voltage * (self.capacity / Time::new::<hour>(1.0))
+    }
+
+    pub fn internal_resistance(&self) -> ElectricalResistance {
+        // Electrolyte resistance climbs in the cold and as the plates run out of active material
+        let cold = ((20.0 - self.temperature.get::<degree_celsius>()) / 40.0).max(0.0);
+        let depletion = 1.0 - self.state_of_charge.get::<ratio>();
+        self.nominal_internal_resistance * (1.0 + cold) * (1.0 + 0.5 * depletion * depletion)
+    }
+
+    // Fraction of the charging current that ends up stored, the rest is gassing and heat
+    fn charge_acceptance(&self) -> f64 {
+        let soc = self.state_of_charge.get::<ratio>();
+        if soc < 0.8 {
+            0.95
+        } else {
+            0.95 - (soc - 0.8) / 0.2 * 0.65
+        }
+    }
+}
+
+impl ElectricalComponent for Battery {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        let dt = Time::new::<second>(dt as f64);
+        let current = self.output_current.get::<ampere>();
+        let usable_capacity = self.usable_capacity().get::<ampere_hour>();
+
+        let mut heat = current * current * self.internal_resistance().get::<ohm>();
+        if usable_capacity > 0.0 {
+            let charge_ah = current * dt.get::<hour>();
+            let delta_soc = if current >= 0.0 {
+                -charge_ah / usable_capacity
+            } else {
+                let acceptance = self.charge_acceptance();
+                heat += -current * self.open_circuit_voltage().get::<volt>() * (1.0 - acceptance);
+                -charge_ah * acceptance / usable_capacity
+            };
+            self.state_of_charge = Ratio::new::<ratio>(
+                (self.state_of_charge.get::<ratio>() + delta_soc).clamp(0.0, 1.0),
+            );
+        }
+
+        if !self.depleted && current > 0.0 && self.state_of_charge.get::<ratio>() <= 0.0 {
+            self.depleted = true;
+            eprintln!("⚠️ BATTERY DEPLETED: {} off the network", self.name);
+        } else if self.depleted
+            && self.terminal_voltage.max(self.bus_voltage) > self.open_circuit_voltage()
+        {
+            self.depleted = false;
+        }
+
+        let was_overtemperature = self.is_overtemperature();
+        let temperature = self.temperature.get::<degree_celsius>();
+        let cooling =
+            (temperature - self.ambient_temperature.get::<degree_celsius>()) / THERMAL_RESISTANCE;
+        self.temperature = ThermodynamicTemperature::new::<degree_celsius>(
+            temperature + (heat - cooling) * dt.get::<second>() / THERMAL_MASS,
+        );
+
+        // Reported when the battery goes over the limit, not on every tick it stays there
+        if self.is_overtemperature() && !was_overtemperature {
+            eprintln!(
+                "⚠️ BATTERY OVERTEMP: {} at {:.1}°C",
+                self.name,
+                self.temperature.get::<degree_celsius>()
+            );
+        }
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.terminal_voltage * self.output_current
+    }
+
+    fn set_input_power(&mut self, _power: Power) {
+        // Power follows the current and terminal voltage
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.terminal_voltage
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.terminal_voltage = voltage;
+        self.bus_voltage = ElectricPotential::new::<volt>(0.0);
+    }
+
+    // Only solved while the battery is open, the bus lands on the output side then
+    fn set_output_voltage(&mut self, voltage: ElectricPotential) {
+        self.bus_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.output_current.max(ElectricCurrent::new::<ampere>(0.0))
+    }
+
+    fn get_input_current(&self) -> ElectricCurrent {
+        (-self.output_current).max(ElectricCurrent::new::<ampere>(0.0))
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.output_current = current;
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if self.depleted {
+            return CircuitModel::Open;
+        }
+        CircuitModel::Source {
+            voltage: self.open_circuit_voltage(),
+            internal_resistance: self.internal_resistance(),
+        }
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::ElectricalSystem;
+    use crate::systems::electrical::components::ac::ground_power::GroundPower;
+    use crate::systems::electrical::components::dc::generic_dc_component::{
+        GenericDcComponent, VoltageResponse,
+    };
+    use crate::systems::electrical::components::dc::transformer_rectifier_unit::TransformerRectifierUnit;
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::wire::Wire;
+    use uom::si::power::watt;
+
+    const TIME_STEP: f32 = 1.0; // Seconds
+
+    fn battery(state_of_charge: f64) -> Battery {
+        Battery::new("BATT", 24.0, 44.0, 0.02, state_of_charge)
+    }
+
+    // Positive current discharges
+    fn run(battery: &mut Battery, current: f64, seconds: f64) {
+        battery.set_input_current(ElectricCurrent::new::<ampere>(current));
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            battery.update(TIME_STEP);
+        }
+    }
+
+    fn soc(battery: &Battery) -> f64 {
+        battery.state_of_charge().get::<ratio>()
+    }
+
+    #[test]
+    fn state_of_charge_follows_the_discharge_current() {
+        let mut battery = battery(0.9);
+        let capacity = battery.usable_capacity().get::<ampere_hour>();
+        // 44 A for 6 minutes takes out 4.4 Ah
+        run(&mut battery, 44.0, 360.0);
+
+        let expected = 0.9 - 4.4 / capacity;
+        assert!(
+            (soc(&battery) - expected).abs() < 0.01 * 4.4 / capacity,
+            "{} instead of {expected}",
+            soc(&battery)
+        );
+    }
+
+    #[test]
+    fn charge_acceptance_falls_off_near_full() {
+        let mut half = battery(0.5);
+        let mut nearly_full = battery(0.95);
+        run(&mut half, -20.0, 60.0);
+        run(&mut nearly_full, -20.0, 60.0);
+
+        let stored_half = soc(&half) - 0.5;
+        let stored_nearly_full = soc(&nearly_full) - 0.95;
+        assert!(stored_half > 0.0);
+        // 95% below 80% state of charge, 46% at 95%
+        let accepted = stored_nearly_full / stored_half;
+        assert!((accepted - 0.4625 / 0.95).abs() < 0.02, "{accepted}");
+        // The rest of the charge current heats the battery
+        assert!(nearly_full.temperature() > half.temperature());
+    }
+
+    #[test]
+    fn depleted_battery_drops_off_and_reconnects_once_the_bus_can_charge_it() {
+        let mut system = ElectricalSystem::new();
+        let mut gpu = GroundPower::new("GPU", 115.0, 400.0, 0.05);
+        gpu.set_selected(true);
+        let gpu = system.add_component("GPU", gpu).unwrap();
+        let tru = TransformerRectifierUnit::new("TRU", 28.0, 100.0, 0.9, 0.01);
+        let tru = system.add_component("TRU", tru).unwrap();
+        let bus = Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        };
+        let bus = system.add_component("BUS", bus).unwrap();
+        let battery = system.add_component("BATT", battery(0.002)).unwrap();
+        let mut load = GenericDcComponent::new(
+            "LOAD",
+            28.0,
+            200.0,
+            10.0,
+            32.0,
+            VoltageResponse::Regulated,
+            1.0,
+        );
+        load.set_power_state(true);
+        let load = system.add_component("LOAD", load).unwrap();
+        system.connect_no_resistance(gpu, tru);
+        system.connect_no_resistance(tru, bus);
+        system.connect_with_wire(battery, bus, Wire::fixed(0.01));
+        system.connect_no_resistance(bus, load);
+
+        // The battery alone runs flat and leaves the bus dead
+        for _ in 0..600 {
+            system.update_system(0.1);
+            if system.get(battery).unwrap().is_depleted() {
+                break;
+            }
+        }
+        assert!(system.get(battery).unwrap().is_depleted());
+        system.update_system(0.1);
+        assert!(system.get_voltage(bus).unwrap().get::<volt>() < 1.0);
+
+        // Ground power brings the bus above the open circuit voltage, the battery comes back and charges
+        system.get_mut(gpu).unwrap().connect();
+        for _ in 0..100 {
+            system.update_system(0.1);
+        }
+        let battery = system.get(battery).unwrap();
+        assert!(!battery.is_depleted());
+        assert!(battery.is_charging());
+        assert!(battery.state_of_charge().get::<ratio>() > 0.0);
+    }
+}
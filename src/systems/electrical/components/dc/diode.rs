@@ -0,0 +1,98 @@
+// Blocking diode.
+// Lets current through from its input to its output only, so a bus fed through it cannot feed back into
+// the bus behind it (a battery backfeeding a main bus through the essential bus feeder). While conducting
+// it drops its forward voltage on top of the resistive drop. A reverse current relay is the same with no
+// forward voltage, it is a contactor that opens on reverse current.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::power::watt;
+
+pub struct Diode {
+    name: String,
+    forward_voltage: ElectricPotential,
+    forward_resistance: ElectricalResistance,
+
+    input_voltage: ElectricPotential,
+    input_current: ElectricCurrent,
+}
+
+impl Diode {
+    pub fn new(name: &str, forward_voltage: f64, forward_resistance: f64) -> Self {
+        Diode {
+            name: name.to_string(),
+            forward_voltage: ElectricPotential::new::<volt>(forward_voltage.max(0.0)),
+            forward_resistance: ElectricalResistance::new::<ohm>(forward_resistance),
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            input_current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // At the last solve, a blocking diode carries no current at all
+    pub fn is_conducting(&self) -> bool {
+        self.input_current.get::<ampere>() > 0.0
+    }
+
+    pub fn current(&self) -> ElectricCurrent {
+        self.input_current
+    }
+}
+
+impl ElectricalComponent for Diode {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, _dt: f32) {}
+
+    fn get_output_power(&self) -> Power {
+        if self.is_conducting() {
+            self.get_output_voltage() * self.input_current
+        } else {
+            Power::new::<watt>(0.0)
+        }
+    }
+
+    fn set_input_power(&mut self, _power: Power) {}
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        if self.is_conducting() {
+            self.input_voltage - self.forward_voltage - self.input_current * self.forward_resistance
+        } else {
+            ElectricPotential::new::<volt>(0.0)
+        }
+    }
+
+    fn set_input_voltage(&mut self, input_voltage: ElectricPotential) {
+        self.input_voltage = input_voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.input_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.input_current = current;
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        CircuitModel::Diode {
+            forward_voltage: self.forward_voltage,
+            forward_resistance: self.forward_resistance,
+        }
+    }
+}
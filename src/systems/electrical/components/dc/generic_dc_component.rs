@@ -1,11 +1,18 @@
 use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
 
+use serde::Deserialize;
 use uom::si::electric_current::ampere;
 use uom::si::electric_potential::volt;
 use uom::si::electrical_resistance::ohm;
 use uom::si::f64::*;
 use uom::si::power::watt;
 
+// Fraction of the nominal voltage below which a load no longer draws a constant power
+const CONSTANT_POWER_FLOOR: f64 = 0.5;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VoltageResponse {
     Linear,
     Binary,
@@ -13,6 +20,14 @@ pub enum VoltageResponse {
     Proportional,
 }
 
+// Supply voltage against the limits of the load
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum VoltageCondition {
+    Normal,
+    Over,
+    Under,
+}
+
 pub struct GenericDcComponent {
     name: String,
     nominal_voltage: ElectricPotential,
@@ -28,7 +43,10 @@ pub struct GenericDcComponent {
     input_current: ElectricCurrent,
 
     is_on: bool,
+    // Held off by the BPCU load shedding whatever the switch says
+    is_shed: bool,
     load_factor: f64, // could be useful for dimming lights (non displays)
+    voltage_condition: VoltageCondition,
 }
 
 impl GenericDcComponent {
@@ -65,7 +83,9 @@ impl GenericDcComponent {
             input_current: ElectricCurrent::new::<ampere>(0.0),
 
             is_on: false,
+            is_shed: false,
             load_factor: 1.0,
+            voltage_condition: VoltageCondition::Normal,
         }
     }
 
@@ -81,17 +101,47 @@ impl GenericDcComponent {
         self.is_on
     }
 
+    pub fn set_shed(&mut self, shed: bool) {
+        self.is_shed = shed;
+    }
+
+    pub fn is_shed(&self) -> bool {
+        self.is_shed
+    }
+
+    // Switched on and not shed
+    fn is_active(&self) -> bool {
+        self.is_on && !self.is_shed
+    }
+
+    // A de-energised bus is not an undervoltage, the load is just off
+    fn voltage_condition(&self) -> VoltageCondition {
+        if !self.is_active() || self.input_voltage.value <= 0.0 {
+            VoltageCondition::Normal
+        } else if self.input_voltage.value > self.max_voltage.value {
+            VoltageCondition::Over
+        } else if self.input_voltage.value < self.min_voltage.value {
+            VoltageCondition::Under
+        } else {
+            VoltageCondition::Normal
+        }
+    }
+
+    pub fn power_factor(&self) -> f64 {
+        self.power_factor
+    }
+
     pub fn get_actual_power(&self) -> Power {
         match self.voltage_response {
             VoltageResponse::Binary => {
-                if self.input_voltage.value >= self.min_voltage.value && self.is_on {
+                if self.input_voltage.value >= self.min_voltage.value && self.is_active() {
                     self.nominal_power * self.load_factor * self.power_factor
                 } else {
                     Power::new::<watt>(0.0)
                 }
             }
             VoltageResponse::Linear => {
-                if !self.is_on || self.input_voltage.value < self.min_voltage.value {
+                if !self.is_active() || self.input_voltage.value < self.min_voltage.value {
                     Power::new::<watt>(0.0)
                 } else {
                     let voltage_ratio = self.input_voltage.value / self.nominal_voltage.value;
@@ -99,14 +149,14 @@ impl GenericDcComponent {
                 }
             }
             VoltageResponse::Regulated => {
-                if !self.is_on || self.input_voltage.value < self.min_voltage.value {
+                if !self.is_active() || self.input_voltage.value < self.min_voltage.value {
                     Power::new::<watt>(0.0)
                 } else {
                     self.nominal_power * self.load_factor * self.power_factor
                 }
             }
             VoltageResponse::Proportional => {
-                if !self.is_on || self.input_voltage.value < self.min_voltage.value {
+                if !self.is_active() || self.input_voltage.value < self.min_voltage.value {
                     Power::new::<watt>(0.0)
                 } else {
                     let voltage_factor = ((self.input_voltage.value - self.min_voltage.value)
@@ -127,27 +177,25 @@ impl ElectricalComponent for GenericDcComponent {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
-    fn update(&mut self, dt: f32) {
-        if self.is_on {
-            if self.input_voltage.value > self.max_voltage.value {
-                println!(
+    fn update(&mut self, _dt: f32) {
+        // Reported when the voltage leaves the limits and when it comes back, not on every tick
+        let condition = self.voltage_condition();
+        if condition != self.voltage_condition {
+            match condition {
+                VoltageCondition::Over => eprintln!(
                     "⚠️ OVERVOLTAGE: {}V > {}V max for {}",
                     self.input_voltage.value, self.max_voltage.value, self.name
-                );
-            } else if self.input_voltage.value < self.min_voltage.value {
-                println!(
+                ),
+                VoltageCondition::Under => eprintln!(
                     "⚠️ UNDERVOLTAGE: {}V < {}V min for {}",
                     self.input_voltage.value, self.min_voltage.value, self.name
-                );
+                ),
+                VoltageCondition::Normal => {
+                    eprintln!("⚡ {} voltage back within limits", self.name)
+                }
             }
-
-            println!(
-                "💡 {} consuming {:.1}W at {:.1}V",
-                self.name,
-                self.get_actual_power().value,
-                self.input_voltage.value
-            );
         }
+        self.voltage_condition = condition;
     }
 
     fn get_output_power(&self) -> Power {
@@ -182,4 +230,114 @@ impl ElectricalComponent for GenericDcComponent {
     fn set_input_current(&mut self, current: ElectricCurrent) {
         self.input_current = current;
     }
+
+    fn circuit_model(&self) -> CircuitModel {
+        // Linearise around the last solved voltage, the load converges on its power curve over a few ticks.
+        // A load that has not seen any voltage yet stays off the network, V²/P would make it a dead short.
+        // Below the floor the load is held at the resistance it has there: a constant power load on a weak
+        // source would otherwise keep lowering its resistance and pull the bus down to nothing.
+        let power = self.get_actual_power();
+        let resistance = if power.value > 0.0 && self.input_voltage.value > 0.0 {
+            let voltage = self
+                .input_voltage
+                .max(self.nominal_voltage * CONSTANT_POWER_FLOOR);
+            ElectricalResistance::new::<ohm>(voltage.get::<volt>().powi(2) / power.get::<watt>())
+        } else {
+            ElectricalResistance::new::<ohm>(f64::INFINITY)
+        };
+        CircuitModel::Load { resistance }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::ElectricalSystem;
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::wire::Wire;
+
+    fn resistance(load: &GenericDcComponent) -> f64 {
+        match load.circuit_model() {
+            CircuitModel::Load { resistance } => resistance.get::<ohm>(),
+            _ => panic!("a DC load is a load"),
+        }
+    }
+
+    #[test]
+    fn unpowered_load_is_not_a_short() {
+        // No minimum voltage, the load asks for its power at 0 V
+        let mut load = GenericDcComponent::new(
+            "Load",
+            28.0,
+            280.0,
+            0.0,
+            32.0,
+            VoltageResponse::Regulated,
+            1.0,
+        );
+        load.set_power_state(true);
+        assert!(resistance(&load).is_infinite());
+
+        load.set_input_voltage(ElectricPotential::new::<volt>(28.0));
+        assert!((resistance(&load) - 2.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn load_on_a_weak_source_settles_instead_of_collapsing() {
+        // 280 W is far more than a 2 Ohm feed can deliver from ~25 V, the bus sags but holds
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("Battery", 24.0, 44.0, 0.02, 0.5);
+        let battery = system.add_component("Battery", battery).unwrap();
+        let mut load = GenericDcComponent::new(
+            "Load",
+            28.0,
+            280.0,
+            0.0,
+            32.0,
+            VoltageResponse::Regulated,
+            1.0,
+        );
+        load.set_power_state(true);
+        let load = system.add_component("Load", load).unwrap();
+        system.connect_with_wire(battery, load, Wire::fixed(2.0));
+
+        let mut voltages = Vec::new();
+        for _ in 0..20 {
+            system.update_system(0.1);
+            voltages.push(system.get_voltage(load).unwrap().get::<volt>());
+        }
+        let settled = voltages[voltages.len() - 1];
+        assert!(settled > 5.0 && settled < 24.0, "settled at {settled} V");
+        assert!((settled - voltages[voltages.len() - 2]).abs() < 0.01);
+        assert!(resistance(system.get(load).unwrap()) >= 14.0 * 14.0 / 280.0 - 1e-9);
+    }
+
+    #[test]
+    fn voltage_condition_is_tracked_across_ticks() {
+        let mut load = GenericDcComponent::new(
+            "Load",
+            28.0,
+            280.0,
+            20.0,
+            32.0,
+            VoltageResponse::Regulated,
+            1.0,
+        );
+        load.set_power_state(true);
+        // A dead bus is not an undervoltage
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Normal);
+
+        load.set_input_voltage(ElectricPotential::new::<volt>(18.0));
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Under);
+
+        load.set_input_voltage(ElectricPotential::new::<volt>(34.0));
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Over);
+
+        load.set_input_voltage(ElectricPotential::new::<volt>(28.0));
+        load.update(0.1);
+        assert_eq!(load.voltage_condition, VoltageCondition::Normal);
+    }
 }
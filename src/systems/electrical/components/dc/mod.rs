@@ -1 +1,4 @@
+pub mod battery;
+pub mod diode;
 pub mod generic_dc_component;
+pub mod transformer_rectifier_unit;
@@ -0,0 +1,101 @@
+// Transformer Rectifier Unit, feeds a 28V DC bus from a 115V AC bus.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::components::shared::converter::Converter;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::f64::*;
+
+pub struct TransformerRectifierUnit {
+    converter: Converter,
+}
+
+impl TransformerRectifierUnit {
+    pub fn new(
+        name: &str,
+        output_voltage: f64,
+        rated_current: f64,
+        efficiency: f64,
+        output_resistance: f64,
+    ) -> Self {
+        TransformerRectifierUnit {
+            // Regulates from 90 to 130V AC input
+            converter: Converter::new(
+                name,
+                90.0,
+                130.0,
+                output_voltage,
+                output_resistance,
+                rated_current,
+                efficiency,
+            ),
+        }
+    }
+
+    pub fn converter(&self) -> &Converter {
+        &self.converter
+    }
+
+    pub fn converter_mut(&mut self) -> &mut Converter {
+        &mut self.converter
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.converter.is_failed()
+    }
+}
+
+impl ElectricalComponent for TransformerRectifierUnit {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.converter.update(dt);
+    }
+
+    fn get_output_power(&self) -> Power {
+        self.converter.output_power()
+    }
+
+    fn set_input_power(&mut self, _power: Power) {
+        // Input power is derived from the delivered output power and the efficiency
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        self.converter.output_voltage()
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.converter.set_input_voltage(voltage);
+    }
+
+    fn set_output_voltage(&mut self, voltage: ElectricPotential) {
+        self.converter.set_output_voltage(voltage);
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.converter.output_current()
+    }
+
+    fn get_input_current(&self) -> ElectricCurrent {
+        self.converter.input_current()
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        // The solver reports the delivered output current for converters
+        self.converter.set_output_current(current);
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        self.converter.circuit_model()
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+}
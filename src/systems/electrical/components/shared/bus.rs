@@ -1,4 +1,5 @@
 use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
 
 use uom::si::electric_potential::volt;
 use uom::si::f64::*;
@@ -38,4 +39,8 @@ impl ElectricalComponent for Bus {
     }
 
     fn set_input_current(&mut self, _current: ElectricCurrent) {}
+
+    fn circuit_model(&self) -> CircuitModel {
+        CircuitModel::Junction
+    }
 }
@@ -1,80 +1,197 @@
+// Thermal circuit breaker.
+// A bimetal element heats with the square of the current and cools towards the temperature the current
+// holds it at, the breaker pops once the element reaches its trip point. The element keeps its heat
+// between overloads so repeated overloads trip sooner, and a popped breaker only latches again once the
+// element has cooled down. Pushing it in is trip-free, it does not latch on a fault that is still there.
+// The crew can pull a breaker and put a collar on it to keep it out.
+
 use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
 
+use serde::Deserialize;
 use uom::si::electric_current::ampere;
 use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
 use uom::si::f64::*;
 use uom::si::power::watt;
 use uom::si::time::second;
 
-pub enum TripCurve {
-    Instantaneous,
-    ShortDelay(f64), // Constant delay in seconds float
-    LongDelay(f64),  // Constant delay in seconds float
-    InverseTime,     // Trip time inversely proportional to current
+const CONTACT_RESISTANCE: f64 = 0.002; // Ohm
+// Ratio of the rating the breaker carries forever without tripping, between the 115% must hold and
+// the 145% must trip points
+const ULTIMATE_TRIP_RATIO: f64 = 1.3;
+// The element has to cool below this share of its trip heat before the breaker latches again
+const RESET_HEAT: f64 = 0.6;
+
+// Trip time-current bands, the time constant of the element sets how quickly an overload trips:
+// at 200% of the rating Fast trips in ~2 s, Standard in ~11 s and Slow (motor loads) in ~40 s.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TripBand {
+    Fast,
+    #[default]
+    Standard,
+    Slow,
+}
+
+impl TripBand {
+    fn time_constant(&self) -> Time {
+        let seconds = match self {
+            TripBand::Fast => 4.0,
+            TripBand::Standard => 20.0,
+            TripBand::Slow => 70.0,
+        };
+        Time::new::<second>(seconds)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakerState {
+    Closed,
+    // Popped out by an overload
+    Tripped,
+    // Pulled out by the crew
+    Pulled,
+    // Pulled with a collar fitted, cannot be pushed in until the collar is removed
+    Collared,
 }
 
 pub struct CircuitBreaker {
     name: String,
     rating: ElectricCurrent,
-    is_tripped: bool,
+    trip_band: TripBand,
+    state: BreakerState,
     input_voltage: ElectricPotential,
     input_power: Power,
     input_current: ElectricCurrent,
-    trip_curve: TripCurve,
-    overcurrent_time: f64, // time spent in overcurrent state
+    // Bimetal heat, 1.0 is the trip point
+    heat: f64,
     auto_reset: bool,
-    reset_delay: f64, // time to wait before resetting
-    trip_time: f64,   // time spent in tripped state
+    reset_delay: Time, // time to wait before resetting
+    trip_time: Time,   // time spent in tripped state
 }
 
 impl CircuitBreaker {
     pub fn new(
         name: &str,
         rating_amps: f64,
-        trip_curve: TripCurve,
+        trip_band: TripBand,
         auto_reset: bool,
         reset_delay: f64,
     ) -> Self {
         CircuitBreaker {
             name: name.to_string(),
             rating: ElectricCurrent::new::<ampere>(rating_amps),
-            is_tripped: false,
+            trip_band,
+            state: BreakerState::Closed,
             input_voltage: ElectricPotential::new::<volt>(0.0),
             input_power: Power::new::<watt>(0.0),
             input_current: ElectricCurrent::new::<ampere>(0.0),
-            trip_curve,
-            overcurrent_time: 0.0,
+            heat: 0.0,
             auto_reset,
-            reset_delay,
-            trip_time: 0.0,
+            reset_delay: Time::new::<second>(reset_delay),
+            trip_time: Time::new::<second>(0.0),
         }
     }
 
-    pub fn reset(&mut self) {
-        self.is_tripped = false;
-        self.trip_time = 0.0;
-        self.overcurrent_time = 0.0;
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rating(&self) -> ElectricCurrent {
+        self.rating
+    }
+
+    pub fn trip_band(&self) -> TripBand {
+        self.trip_band
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == BreakerState::Closed
     }
 
     pub fn is_tripped(&self) -> bool {
-        self.is_tripped
+        self.state == BreakerState::Tripped
+    }
+
+    // Bimetal heat as a share of the trip point
+    pub fn heat(&self) -> f64 {
+        self.heat
     }
 
-    pub fn should_trip(&self, dt: f32) -> bool {
-        if self.input_current.value <= self.rating.value {
+    // Push the breaker in. Trip-free: refused while collared, while the element is still hot from the last
+    // trip or while `prospective_current`, what the breaker would carry once closed, is over its trip point.
+    pub fn reset(&mut self, prospective_current: ElectricCurrent) -> bool {
+        if self.state != BreakerState::Closed && self.would_trip(prospective_current) {
             return false;
         }
+        self.latch()
+    }
+
+    // The fault is still there if the breaker would pop again at this current, however long it takes
+    pub fn would_trip(&self, current: ElectricCurrent) -> bool {
+        self.trip_time(current).is_some()
+    }
+
+    pub fn is_hot(&self) -> bool {
+        self.heat > RESET_HEAT
+    }
+
+    // What the network sees once the breaker is pushed in, to find its prospective current
+    pub fn closed_circuit_model(&self) -> CircuitModel {
+        CircuitModel::Series {
+            resistance: ElectricalResistance::new::<ohm>(CONTACT_RESISTANCE),
+        }
+    }
 
-        match self.trip_curve {
-            TripCurve::Instantaneous => true,
-            TripCurve::ShortDelay(delay) => self.overcurrent_time >= delay,
-            TripCurve::LongDelay(delay) => self.overcurrent_time >= delay,
-            TripCurve::InverseTime => {
-                let overload_ratio = self.input_current.value / self.rating.value;
-                self.overcurrent_time >= 0.1 / (overload_ratio * overload_ratio)
+    fn latch(&mut self) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::Collared => false,
+            // Only a popped breaker waits for its element, a pulled one was opened by hand however warm
+            BreakerState::Tripped if self.is_hot() => false,
+            BreakerState::Tripped | BreakerState::Pulled => {
+                self.state = BreakerState::Closed;
+                self.trip_time = Time::new::<second>(0.0);
+                true
             }
         }
     }
+
+    pub fn pull(&mut self) {
+        if self.state != BreakerState::Collared {
+            self.state = BreakerState::Pulled;
+        }
+    }
+
+    // Collars only go on pulled (or popped) breakers
+    pub fn collar(&mut self) -> bool {
+        if self.state == BreakerState::Closed {
+            return false;
+        }
+        self.state = BreakerState::Collared;
+        true
+    }
+
+    pub fn remove_collar(&mut self) {
+        if self.state == BreakerState::Collared {
+            self.state = BreakerState::Pulled;
+        }
+    }
+
+    // Time a cold breaker takes to trip at `current`, None if it carries it forever
+    pub fn trip_time(&self, current: ElectricCurrent) -> Option<Time> {
+        let ratio = (current / self.rating).value.abs() / ULTIMATE_TRIP_RATIO;
+        let ratio_squared = ratio * ratio;
+        if ratio_squared <= 1.0 {
+            return None;
+        }
+        Some(self.trip_band.time_constant() * (ratio_squared / (ratio_squared - 1.0)).ln())
+    }
 }
 
 impl ElectricalComponent for CircuitBreaker {
@@ -85,34 +202,47 @@ impl ElectricalComponent for CircuitBreaker {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
+
     fn update(&mut self, dt: f32) {
-        let dt_seconds: f64 = dt as f64 / 1000.0;
+        let dt = Time::new::<second>(dt as f64);
+
+        // The element heats towards (I / I ultimate)² and cools back the same way once the current drops
+        let current = if self.is_closed() {
+            self.input_current
+        } else {
+            ElectricCurrent::new::<ampere>(0.0)
+        };
+        let ratio = (current / self.rating).value.abs() / ULTIMATE_TRIP_RATIO;
+        let alpha = 1.0 - (-(dt / self.trip_band.time_constant()).value).exp();
+        self.heat += (ratio * ratio - self.heat) * alpha;
 
-        if self.is_tripped {
-            if self.auto_reset {
-                self.trip_time += dt_seconds;
+        match self.state {
+            BreakerState::Closed if self.heat >= 1.0 => {
+                self.state = BreakerState::Tripped;
+                self.trip_time = Time::new::<second>(0.0);
+                eprintln!(
+                    "⚡ {} TRIPPED at {:.1} A",
+                    self.name,
+                    current.get::<ampere>().abs()
+                );
+            }
+            BreakerState::Tripped if self.auto_reset => {
+                self.trip_time += dt;
+                // An automatic reset breaker cannot see the current before it closes, on a fault that
+                // stays it cycles
                 if self.trip_time >= self.reset_delay {
-                    self.reset();
+                    self.latch();
                 }
             }
-            return;
-        }
-        if self.input_current.value > self.rating.value {
-            self.overcurrent_time += dt_seconds;
-        } else {
-            self.overcurrent_time = 0.0;
-        }
-        if self.should_trip(dt) {
-            self.is_tripped = true;
-            self.trip_time = 0.0;
+            _ => {}
         }
     }
 
     fn get_output_power(&self) -> Power {
-        if self.is_tripped {
-            Power::new::<watt>(0.0)
-        } else {
+        if self.is_closed() {
             self.input_power
+        } else {
+            Power::new::<watt>(0.0)
         }
     }
 
@@ -121,10 +251,10 @@ impl ElectricalComponent for CircuitBreaker {
     }
 
     fn get_output_voltage(&self) -> ElectricPotential {
-        if self.is_tripped {
-            ElectricPotential::new::<volt>(0.0)
-        } else {
+        if self.is_closed() {
             self.input_voltage
+        } else {
+            ElectricPotential::new::<volt>(0.0)
         }
     }
 
@@ -133,13 +263,164 @@ impl ElectricalComponent for CircuitBreaker {
     }
 
     fn get_output_current(&self) -> ElectricCurrent {
-        if self.is_tripped {
-            ElectricCurrent::new::<ampere>(0.0)
-        } else {
+        if self.is_closed() {
             self.input_current
+        } else {
+            ElectricCurrent::new::<ampere>(0.0)
         }
     }
     fn set_input_current(&mut self, current: ElectricCurrent) {
         self.input_current = current;
     }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if self.is_closed() {
+            self.closed_circuit_model()
+        } else {
+            CircuitModel::Open
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATING: f64 = 10.0; // Ampere
+    const TIME_STEP: f32 = 0.01; // Seconds
+
+    fn breaker(trip_band: TripBand) -> CircuitBreaker {
+        CircuitBreaker::new("CB", RATING, trip_band, false, 0.0)
+    }
+
+    fn amps(current: f64) -> ElectricCurrent {
+        ElectricCurrent::new::<ampere>(current)
+    }
+
+    // Seconds a breaker takes to trip at `current`, None if it still holds after `limit`
+    fn time_to_trip(breaker: &mut CircuitBreaker, current: f64, limit: f64) -> Option<f64> {
+        breaker.set_input_current(amps(current));
+        let mut elapsed = 0.0;
+        while elapsed < limit {
+            breaker.update(TIME_STEP);
+            elapsed += TIME_STEP as f64;
+            if breaker.is_tripped() {
+                return Some(elapsed);
+            }
+        }
+        None
+    }
+
+    fn cool(breaker: &mut CircuitBreaker, seconds: f64) {
+        breaker.set_input_current(amps(0.0));
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            breaker.update(TIME_STEP);
+        }
+    }
+
+    #[test]
+    fn trip_times_follow_the_band() {
+        // Seconds to trip at 200% and 500% of the rating
+        let bands = [
+            (TripBand::Fast, 2.2, 0.28),
+            (TripBand::Standard, 11.0, 1.4),
+            (TripBand::Slow, 38.4, 4.9),
+        ];
+        for (band, at_200, at_500) in bands {
+            for (overload, expected) in [(2.0, at_200), (5.0, at_500)] {
+                let mut breaker = breaker(band);
+                let current = overload * RATING;
+                let tripped = time_to_trip(&mut breaker, current, 100.0)
+                    .unwrap_or_else(|| panic!("{band:?} held {overload}x"));
+                let predicted = breaker.trip_time(amps(current)).unwrap().get::<second>();
+                assert!(
+                    (tripped - predicted).abs() < 0.02 + 0.01 * predicted,
+                    "{band:?} {overload}x tripped after {tripped} s, predicted {predicted} s"
+                );
+                assert!(
+                    (predicted - expected).abs() < 0.05 * expected,
+                    "{band:?} {overload}x {predicted} s instead of {expected} s"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn holds_115_percent_and_trips_at_145_percent() {
+        let mut breaker = breaker(TripBand::Fast);
+        assert_eq!(time_to_trip(&mut breaker, 1.15 * RATING, 60.0), None);
+        assert!(breaker.trip_time(amps(1.15 * RATING)).is_none());
+        assert!(time_to_trip(&mut breaker, 1.45 * RATING, 60.0).is_some());
+    }
+
+    #[test]
+    fn element_has_to_cool_before_the_breaker_latches() {
+        let mut breaker = breaker(TripBand::Fast);
+        time_to_trip(&mut breaker, 5.0 * RATING, 10.0).unwrap();
+        assert!(breaker.is_hot());
+        assert!(!breaker.reset(amps(0.0)));
+        assert!(breaker.is_tripped());
+
+        // From the trip point down to the reset heat takes ln(1 / 0.6) time constants, 2 s for Fast
+        cool(&mut breaker, 1.5);
+        assert!(!breaker.reset(amps(0.0)));
+        cool(&mut breaker, 1.0);
+        assert!(!breaker.is_hot());
+        assert!(breaker.reset(amps(0.0)));
+        assert!(breaker.is_closed());
+    }
+
+    #[test]
+    fn warm_breaker_pulled_by_the_crew_goes_straight_back_in() {
+        let mut breaker = breaker(TripBand::Fast);
+        // Carrying 110% for long enough to warm the element past the reset heat without tripping
+        assert_eq!(time_to_trip(&mut breaker, 1.1 * RATING, 30.0), None);
+        assert!(breaker.is_hot());
+
+        breaker.pull();
+        assert!(breaker.reset(amps(1.1 * RATING)));
+        assert!(breaker.is_closed());
+    }
+
+    #[test]
+    fn reset_is_refused_on_a_fault_that_is_still_there() {
+        let mut breaker = breaker(TripBand::Standard);
+        time_to_trip(&mut breaker, 5.0 * RATING, 10.0).unwrap();
+        cool(&mut breaker, 60.0);
+
+        assert!(!breaker.reset(amps(1.35 * RATING)));
+        assert!(!breaker.reset(amps(-1.35 * RATING)));
+        assert!(breaker.is_tripped());
+        assert!(breaker.reset(amps(1.25 * RATING)));
+        assert!(breaker.is_closed());
+    }
+
+    #[test]
+    fn pulled_breaker_is_open_and_collared_one_stays_out() {
+        let mut breaker = breaker(TripBand::Standard);
+        // Collars only go on breakers that are out
+        assert!(!breaker.collar());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        breaker.pull();
+        assert_eq!(breaker.state(), BreakerState::Pulled);
+        assert!(matches!(breaker.circuit_model(), CircuitModel::Open));
+        // Nothing flows through a pulled breaker, it does not heat
+        assert_eq!(time_to_trip(&mut breaker, 5.0 * RATING, 5.0), None);
+        assert_eq!(breaker.heat(), 0.0);
+
+        assert!(breaker.collar());
+        assert_eq!(breaker.state(), BreakerState::Collared);
+        assert!(!breaker.reset(amps(0.0)));
+        breaker.pull();
+        assert_eq!(breaker.state(), BreakerState::Collared);
+
+        breaker.remove_collar();
+        assert_eq!(breaker.state(), BreakerState::Pulled);
+        assert!(breaker.reset(amps(0.0)));
+        assert!(matches!(
+            breaker.circuit_model(),
+            CircuitModel::Series { .. }
+        ));
+    }
 }
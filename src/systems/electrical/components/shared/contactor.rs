@@ -0,0 +1,320 @@
+// Generator line, bus tie and DC tie contactors.
+// The contacts follow the commanded state only while the coil is powered: the coil has to reach the
+// pull-in voltage to close and the contacts drop out once it falls below the drop-out voltage.
+// Every change of the contacts takes the transfer time to complete.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::time::second;
+
+const CONTACT_RESISTANCE: f64 = 0.0005; // Ohm
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContactorFailure {
+    None,
+    WeldedClosed,
+    StuckOpen,
+}
+
+pub struct Contactor {
+    name: String,
+    pull_in_voltage: ElectricPotential,
+    drop_out_voltage: ElectricPotential,
+    transfer_time: Time,
+
+    commanded_closed: bool,
+    coil_voltage: ElectricPotential,
+    is_closed: bool,
+    transfer_timer: Time,
+    disagree_timer: Time,
+    failure: ContactorFailure,
+
+    input_voltage: ElectricPotential,
+    input_power: Power,
+    input_current: ElectricCurrent,
+}
+
+impl Contactor {
+    pub fn new(
+        name: &str,
+        pull_in_voltage: f64,
+        drop_out_voltage: f64,
+        transfer_time: f64,
+    ) -> Self {
+        Contactor {
+            name: name.to_string(),
+            pull_in_voltage: ElectricPotential::new::<volt>(pull_in_voltage),
+            drop_out_voltage: ElectricPotential::new::<volt>(drop_out_voltage),
+            transfer_time: Time::new::<second>(transfer_time),
+
+            commanded_closed: false,
+            coil_voltage: ElectricPotential::new::<volt>(0.0),
+            is_closed: false,
+            transfer_timer: Time::new::<second>(0.0),
+            disagree_timer: Time::new::<second>(0.0),
+            failure: ContactorFailure::None,
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            input_power: Power::new::<watt>(0.0),
+            input_current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn command(&mut self, closed: bool) {
+        self.commanded_closed = closed;
+    }
+
+    pub fn is_commanded_closed(&self) -> bool {
+        self.commanded_closed
+    }
+
+    pub fn set_coil_voltage(&mut self, voltage: ElectricPotential) {
+        self.coil_voltage = voltage;
+    }
+
+    pub fn set_failure(&mut self, failure: ContactorFailure) {
+        self.failure = failure;
+    }
+
+    pub fn failure(&self) -> ContactorFailure {
+        self.failure
+    }
+
+    // Auxiliary contact, what the control logic sees
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    // True when the contacts disagree with the command once the transfer had time to finish
+    pub fn is_disagree(&self) -> bool {
+        self.disagree_timer > self.transfer_time
+    }
+
+    fn coil_holds(&self) -> bool {
+        if self.is_closed {
+            self.coil_voltage >= self.drop_out_voltage
+        } else {
+            self.coil_voltage >= self.pull_in_voltage
+        }
+    }
+}
+
+impl ElectricalComponent for Contactor {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        let dt = Time::new::<second>(dt as f64);
+
+        let wants_closed = self.commanded_closed && self.coil_holds();
+        // Welded contacts never leave the closed position, stuck contacts never leave the open one
+        let blocked = match self.failure {
+            ContactorFailure::WeldedClosed => self.is_closed,
+            ContactorFailure::StuckOpen => !self.is_closed,
+            ContactorFailure::None => false,
+        };
+
+        if wants_closed != self.is_closed && !blocked {
+            self.transfer_timer += dt;
+            if self.transfer_timer >= self.transfer_time {
+                self.is_closed = wants_closed;
+                self.transfer_timer = Time::new::<second>(0.0);
+            }
+        } else {
+            self.transfer_timer = Time::new::<second>(0.0);
+        }
+
+        // Counted once the contacts had their chance to move, a transfer that completes is no disagreement
+        if self.commanded_closed != self.is_closed {
+            self.disagree_timer += dt;
+        } else {
+            self.disagree_timer = Time::new::<second>(0.0);
+        }
+    }
+
+    fn get_output_power(&self) -> Power {
+        if self.is_closed {
+            self.input_power
+        } else {
+            Power::new::<watt>(0.0)
+        }
+    }
+
+    fn set_input_power(&mut self, power: Power) {
+        self.input_power = power;
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        if self.is_closed {
+            self.input_voltage
+        } else {
+            ElectricPotential::new::<volt>(0.0)
+        }
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.input_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        if self.is_closed {
+            self.input_current
+        } else {
+            ElectricCurrent::new::<ampere>(0.0)
+        }
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.input_current = current;
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if self.is_closed {
+            CircuitModel::Series {
+                resistance: ElectricalResistance::new::<ohm>(CONTACT_RESISTANCE),
+            }
+        } else {
+            CircuitModel::Open
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: f32 = 0.01; // Seconds
+    const TRANSFER_TIME: f64 = 0.05; // Seconds
+
+    fn contactor() -> Contactor {
+        Contactor::new("GLC", 18.0, 12.0, TRANSFER_TIME)
+    }
+
+    fn coil(contactor: &mut Contactor, voltage: f64) {
+        contactor.set_coil_voltage(ElectricPotential::new::<volt>(voltage));
+    }
+
+    fn run(contactor: &mut Contactor, seconds: f64) {
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            contactor.update(TIME_STEP);
+        }
+    }
+
+    // Commanded closed on a 28V coil and settled
+    fn closed() -> Contactor {
+        let mut contactor = contactor();
+        coil(&mut contactor, 28.0);
+        contactor.command(true);
+        run(&mut contactor, 0.5);
+        assert!(contactor.is_closed());
+        contactor
+    }
+
+    #[test]
+    fn closes_at_pull_in_and_holds_down_to_drop_out() {
+        let mut contactor = contactor();
+        contactor.command(true);
+        coil(&mut contactor, 17.0);
+        run(&mut contactor, 1.0);
+        assert!(!contactor.is_closed());
+
+        coil(&mut contactor, 18.0);
+        run(&mut contactor, 0.5);
+        assert!(contactor.is_closed());
+
+        // Between drop-out and pull-in a closed contactor stays closed
+        coil(&mut contactor, 12.5);
+        run(&mut contactor, 1.0);
+        assert!(contactor.is_closed());
+
+        coil(&mut contactor, 11.5);
+        run(&mut contactor, 0.5);
+        assert!(!contactor.is_closed());
+
+        // And an open one stays open
+        coil(&mut contactor, 15.0);
+        run(&mut contactor, 1.0);
+        assert!(!contactor.is_closed());
+    }
+
+    #[test]
+    fn contacts_take_the_transfer_time_both_ways() {
+        let mut contactor = contactor();
+        coil(&mut contactor, 28.0);
+        contactor.command(true);
+        run(&mut contactor, TRANSFER_TIME - 0.02);
+        assert!(!contactor.is_closed());
+        assert!(matches!(contactor.circuit_model(), CircuitModel::Open));
+        run(&mut contactor, 0.03);
+        assert!(contactor.is_closed());
+        assert!(matches!(
+            contactor.circuit_model(),
+            CircuitModel::Series { .. }
+        ));
+
+        contactor.command(false);
+        run(&mut contactor, TRANSFER_TIME - 0.02);
+        assert!(contactor.is_closed());
+        run(&mut contactor, 0.03);
+        assert!(!contactor.is_closed());
+    }
+
+    #[test]
+    fn welded_contacts_stay_closed_and_stuck_ones_stay_open() {
+        let mut welded = closed();
+        welded.set_failure(ContactorFailure::WeldedClosed);
+        welded.command(false);
+        coil(&mut welded, 0.0);
+        run(&mut welded, 1.0);
+        assert!(welded.is_closed());
+
+        let mut stuck = contactor();
+        stuck.set_failure(ContactorFailure::StuckOpen);
+        coil(&mut stuck, 28.0);
+        stuck.command(true);
+        run(&mut stuck, 1.0);
+        assert!(!stuck.is_closed());
+    }
+
+    #[test]
+    fn disagree_only_once_the_transfer_time_has_passed() {
+        // A healthy transfer never disagrees
+        let mut healthy = contactor();
+        coil(&mut healthy, 28.0);
+        healthy.command(true);
+        for _ in 0..20 {
+            healthy.update(TIME_STEP);
+            assert!(!healthy.is_disagree());
+        }
+        assert!(healthy.is_closed());
+
+        let mut stuck = contactor();
+        stuck.set_failure(ContactorFailure::StuckOpen);
+        coil(&mut stuck, 28.0);
+        stuck.command(true);
+        run(&mut stuck, TRANSFER_TIME - 0.01);
+        assert!(!stuck.is_disagree());
+        run(&mut stuck, 0.02);
+        assert!(stuck.is_disagree());
+
+        // Commanding it back open ends the disagreement
+        stuck.command(false);
+        run(&mut stuck, TIME_STEP as f64);
+        assert!(!stuck.is_disagree());
+    }
+}
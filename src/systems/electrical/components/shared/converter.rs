@@ -0,0 +1,281 @@
+// Shared behaviour of the power conversion units (TRUs and static inverters).
+// The output is regulated to a fixed voltage while the input stays inside its operating range, the
+// input side draws whatever the output delivers divided by the efficiency. When the output current
+// exceeds the limit the regulation setpoint folds back so the current sits at the limit, and an
+// output that stays low while the input is healthy latches the unit as failed. A low output while
+// current limiting is the overload, not the unit, and is left to the breakers downstream.
+
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::ratio::ratio;
+use uom::si::time::second;
+
+const FAIL_VOLTAGE_RATIO: f64 = 0.75;
+const FAIL_DETECTION_TIME: f64 = 2.0; // seconds
+const NO_LOAD_LOSS_RATIO: f64 = 0.01; // fraction of the rated output power drawn with no load
+
+pub struct Converter {
+    name: String,
+    min_input_voltage: ElectricPotential,
+    max_input_voltage: ElectricPotential,
+    regulated_voltage: ElectricPotential,
+    output_resistance: ElectricalResistance,
+    current_limit: ElectricCurrent,
+    efficiency: Ratio,
+
+    is_on: bool,
+    is_failed: bool,
+    failure_injected: bool,
+    limit_factor: f64,
+    low_output_time: Time,
+
+    input_voltage: ElectricPotential,
+    output_voltage: ElectricPotential,
+    output_current: ElectricCurrent,
+}
+
+impl Converter {
+    pub fn new(
+        name: &str,
+        min_input_voltage: f64,
+        max_input_voltage: f64,
+        regulated_voltage: f64,
+        output_resistance: f64,
+        current_limit: f64,
+        efficiency: f64,
+    ) -> Self {
+        Converter {
+            name: name.to_string(),
+            min_input_voltage: ElectricPotential::new::<volt>(min_input_voltage),
+            max_input_voltage: ElectricPotential::new::<volt>(max_input_voltage),
+            regulated_voltage: ElectricPotential::new::<volt>(regulated_voltage),
+            output_resistance: ElectricalResistance::new::<ohm>(output_resistance),
+            current_limit: ElectricCurrent::new::<ampere>(current_limit),
+            efficiency: Ratio::new::<ratio>(efficiency.clamp(0.01, 1.0)),
+
+            is_on: true,
+            is_failed: false,
+            failure_injected: false,
+            limit_factor: 1.0,
+            low_output_time: Time::new::<second>(0.0),
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            output_voltage: ElectricPotential::new::<volt>(0.0),
+            output_current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_power_state(&mut self, on: bool) {
+        self.is_on = on;
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    // Internal failure, the unit stops converting and the failure gets latched by the monitoring
+    pub fn inject_failure(&mut self, failed: bool) {
+        self.failure_injected = failed;
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.is_failed
+    }
+
+    pub fn reset(&mut self) {
+        self.is_failed = false;
+        self.low_output_time = Time::new::<second>(0.0);
+        self.limit_factor = 1.0;
+    }
+
+    pub fn is_current_limiting(&self) -> bool {
+        self.limit_factor < 1.0
+    }
+
+    pub fn is_input_valid(&self) -> bool {
+        self.input_voltage >= self.min_input_voltage && self.input_voltage <= self.max_input_voltage
+    }
+
+    pub fn is_operating(&self) -> bool {
+        self.is_on && !self.is_failed && !self.failure_injected && self.is_input_valid()
+    }
+
+    pub fn input_voltage(&self) -> ElectricPotential {
+        self.input_voltage
+    }
+
+    pub fn output_voltage(&self) -> ElectricPotential {
+        self.output_voltage
+    }
+
+    pub fn output_current(&self) -> ElectricCurrent {
+        self.output_current
+    }
+
+    // What the unit delivers at its regulated voltage and current limit
+    pub fn rated_power(&self) -> Power {
+        self.regulated_voltage * self.current_limit
+    }
+
+    pub fn output_power(&self) -> Power {
+        (self.output_voltage * self.output_current).max(Power::new::<watt>(0.0))
+    }
+
+    pub fn input_power(&self) -> Power {
+        if !self.is_operating() {
+            return Power::new::<watt>(0.0);
+        }
+        let no_load_loss = self.regulated_voltage * self.current_limit * NO_LOAD_LOSS_RATIO;
+        self.output_power() / self.efficiency.get::<ratio>() + no_load_loss
+    }
+
+    pub fn input_current(&self) -> ElectricCurrent {
+        if self.input_voltage.value > 0.0 {
+            self.input_power() / self.input_voltage
+        } else {
+            ElectricCurrent::new::<ampere>(0.0)
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if !self.is_on || !self.is_input_valid() {
+            self.low_output_time = Time::new::<second>(0.0);
+            self.limit_factor = 1.0;
+            return;
+        }
+
+        // Fold the setpoint back to hold the output at the current limit, and recover once the load drops
+        let current = self.output_current.get::<ampere>();
+        if current > 0.0 {
+            self.limit_factor =
+                (self.limit_factor * self.current_limit.get::<ampere>() / current).min(1.0);
+        } else {
+            self.limit_factor = 1.0;
+        }
+
+        let output_low = !self.is_current_limiting()
+            && self.output_voltage < self.regulated_voltage * FAIL_VOLTAGE_RATIO;
+        if self.failure_injected || output_low {
+            self.low_output_time += Time::new::<second>(dt as f64);
+        } else {
+            self.low_output_time = Time::new::<second>(0.0);
+        }
+        if !self.is_failed && self.low_output_time.get::<second>() > FAIL_DETECTION_TIME {
+            self.is_failed = true;
+            eprintln!("⚠️ {} FAIL", self.name);
+        }
+    }
+
+    pub fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.input_voltage = voltage;
+    }
+
+    pub fn set_output_voltage(&mut self, voltage: ElectricPotential) {
+        self.output_voltage = voltage;
+    }
+
+    pub fn set_output_current(&mut self, current: ElectricCurrent) {
+        self.output_current = current;
+    }
+
+    pub fn circuit_model(&self) -> CircuitModel {
+        if !self.is_operating() {
+            return CircuitModel::Open;
+        }
+
+        let input_power = self.input_power().get::<watt>();
+        let input_resistance = if input_power > 0.0 {
+            self.input_voltage.get::<volt>().powi(2) / input_power
+        } else {
+            f64::INFINITY
+        };
+
+        CircuitModel::Converter {
+            input_resistance: ElectricalResistance::new::<ohm>(input_resistance),
+            output_voltage: self.regulated_voltage * self.limit_factor,
+            output_resistance: self.output_resistance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+
+    // 28V 100A TRU on a healthy 115V input
+    fn tru() -> Converter {
+        let mut converter = Converter::new("TRU", 100.0, 130.0, 28.0, 0.01, 100.0, 0.9);
+        converter.set_input_voltage(ElectricPotential::new::<volt>(115.0));
+        converter
+    }
+
+    // The output feeds a plain resistance, solved the way the network would
+    fn run(converter: &mut Converter, load: f64, seconds: f64) {
+        for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+            let (voltage, current) = match converter.circuit_model() {
+                CircuitModel::Converter {
+                    output_voltage,
+                    output_resistance,
+                    ..
+                } => {
+                    let current =
+                        output_voltage.get::<volt>() / (load + output_resistance.get::<ohm>());
+                    (current * load, current)
+                }
+                _ => (0.0, 0.0),
+            };
+            converter.set_output_voltage(ElectricPotential::new::<volt>(voltage));
+            converter.set_output_current(ElectricCurrent::new::<ampere>(current));
+            converter.update(TIME_STEP);
+        }
+    }
+
+    #[test]
+    fn overload_folds_back_to_the_current_limit_without_failing() {
+        let mut converter = tru();
+        // 0.1 Ohm would take 280A at 28V
+        run(&mut converter, 0.1, 5.0);
+
+        assert!(converter.is_current_limiting());
+        assert!((converter.output_current().get::<ampere>() - 100.0).abs() < 1.0);
+        assert!(converter.output_voltage().get::<volt>() < 28.0 * FAIL_VOLTAGE_RATIO);
+        assert!(!converter.is_failed());
+
+        // Back to regulating once the overload is gone
+        run(&mut converter, 1.0, 1.0);
+        assert!(!converter.is_current_limiting());
+        assert!((converter.output_voltage().get::<volt>() - 28.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn low_output_latches_the_failure() {
+        let mut converter = tru();
+        run(&mut converter, 1.0, 1.0);
+        converter.inject_failure(true);
+
+        run(&mut converter, 1.0, FAIL_DETECTION_TIME - 0.5);
+        assert!(!converter.is_failed());
+        run(&mut converter, 1.0, 1.0);
+        assert!(converter.is_failed());
+
+        // Stays failed until it is reset
+        converter.inject_failure(false);
+        run(&mut converter, 1.0, 1.0);
+        assert!(converter.is_failed());
+        converter.reset();
+        run(&mut converter, 1.0, 1.0);
+        assert!(!converter.is_failed());
+        assert!((converter.output_voltage().get::<volt>() - 28.0).abs() < 0.5);
+    }
+}
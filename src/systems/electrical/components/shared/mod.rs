@@ -1,2 +1,6 @@
 pub mod bus;
 pub mod circuit_breaker;
+pub mod contactor;
+pub mod converter;
+pub mod motor;
+pub mod solid_state_power_controller;
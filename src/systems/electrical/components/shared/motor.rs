@@ -0,0 +1,500 @@
+// Electric motor.
+// Fuel pumps, hydraulic ACMPs, recirculation fans and the APU starter. The motor is its back EMF behind the
+// winding resistance, the EMF grows with the shaft speed so a motor at standstill draws its stall current
+// off the bus and the current falls away as it spins up. That inrush is what trips breakers, which is why
+// motor loads sit behind slow breakers. The shaft follows the motor torque against the torque of what it
+// drives, a load it cannot turn keeps it stalled on the stall current. The network sees the motor as a
+// load, it does not feed a dead bus while it runs down.
+// Both supplies run on the same straight torque-speed line from the stall point through the rated point to
+// the no-load speed. On AC that line scales with the bus frequency (the no-load speed is the synchronous
+// speed) and the power factor climbs from the locked rotor one to the rated one as the motor comes up.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::components::ac::generic_ac_component::PhaseConnection;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::angular_velocity::{radian_per_second, revolution_per_minute};
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+use uom::si::power::watt;
+use uom::si::torque::newton_meter;
+
+const NOMINAL_FREQUENCY: f64 = 400.0; // Hz
+const LOCKED_ROTOR_POWER_FACTOR: f64 = 0.3;
+// Below this share of the no-load speed a motor that cannot turn its load counts as stalled
+const STALL_SPEED_RATIO: f64 = 0.02;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MotorSupply {
+    Dc,
+    Ac {
+        connection: PhaseConnection,
+        // At the rated point
+        power_factor: f64,
+    },
+}
+
+pub struct Motor {
+    name: String,
+    supply: MotorSupply,
+    rated_voltage: ElectricPotential,
+    rated_power: Power, // shaft
+    rated_speed: AngularVelocity,
+    // Stall current over rated current (the real current the network carries), sets the winding resistance
+    stall_current_ratio: f64,
+    // Spin-up time constant unloaded, sets the rotor inertia
+    spin_up_time: f64, // seconds
+
+    input_voltage: ElectricPotential,
+    input_frequency: Frequency,
+    input_current: ElectricCurrent,
+
+    is_on: bool,
+    // Held off by the BPCU load shedding whatever the switch says
+    is_shed: bool,
+    speed: AngularVelocity,
+    load_torque: Torque,
+    stalled: bool,
+}
+
+impl Motor {
+    pub fn new(
+        name: &str,
+        supply: MotorSupply,
+        rated_voltage: f64,
+        rated_power: f64,
+        rated_speed: f64, // rpm
+        stall_current_ratio: f64,
+        spin_up_time: f64,
+    ) -> Self {
+        Motor {
+            name: name.to_string(),
+            supply,
+            rated_voltage: ElectricPotential::new::<volt>(rated_voltage),
+            rated_power: Power::new::<watt>(rated_power),
+            rated_speed: AngularVelocity::new::<revolution_per_minute>(rated_speed),
+            stall_current_ratio: stall_current_ratio.max(1.5),
+            spin_up_time: spin_up_time.max(0.01),
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            input_frequency: Frequency::new::<hertz>(0.0),
+            input_current: ElectricCurrent::new::<ampere>(0.0),
+
+            is_on: false,
+            is_shed: false,
+            speed: AngularVelocity::new::<radian_per_second>(0.0),
+            load_torque: Torque::new::<newton_meter>(0.0),
+            stalled: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn supply(&self) -> MotorSupply {
+        self.supply
+    }
+
+    pub fn set_power_state(&mut self, on: bool) {
+        self.is_on = on;
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    pub fn set_shed(&mut self, shed: bool) {
+        self.is_shed = shed;
+    }
+
+    pub fn is_shed(&self) -> bool {
+        self.is_shed
+    }
+
+    // Switched on and not shed
+    fn is_active(&self) -> bool {
+        self.is_on && !self.is_shed
+    }
+
+    // Torque the driven machine (pump, fan, APU spool) takes off the shaft
+    pub fn set_load_torque(&mut self, torque: Torque) {
+        self.load_torque = torque.max(Torque::new::<newton_meter>(0.0));
+    }
+
+    pub fn load_torque(&self) -> Torque {
+        self.load_torque
+    }
+
+    pub fn rated_torque(&self) -> Torque {
+        Torque::new::<newton_meter>(
+            self.rated_power.get::<watt>() / self.rated_speed.get::<radian_per_second>(),
+        )
+    }
+
+    pub fn shaft_speed(&self) -> AngularVelocity {
+        self.speed
+    }
+
+    // Mechanical power handed to the driven machine
+    pub fn shaft_power(&self) -> Power {
+        Power::new::<watt>(
+            self.load_torque.get::<newton_meter>() * self.speed.get::<radian_per_second>(),
+        )
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    // Efficiency at the rated point follows from the stall current, the rest is lost in the windings
+    fn rated_efficiency(&self) -> f64 {
+        1.0 - 1.0 / self.stall_current_ratio
+    }
+
+    fn rated_current(&self) -> f64 {
+        self.rated_power.get::<watt>()
+            / (self.rated_efficiency() * self.rated_voltage.get::<volt>())
+    }
+
+    fn winding_resistance(&self) -> f64 {
+        self.rated_voltage.get::<volt>() / (self.stall_current_ratio * self.rated_current())
+    }
+
+    // Torque per Ampere of motor current
+    fn torque_constant(&self) -> f64 {
+        self.rated_torque().get::<newton_meter>() / self.rated_current()
+    }
+
+    // Frequency over nominal on AC, the torque-speed line moves with it
+    fn frequency_ratio(&self) -> f64 {
+        match self.supply {
+            MotorSupply::Dc => 1.0,
+            MotorSupply::Ac { .. } => self.input_frequency.get::<hertz>() / NOMINAL_FREQUENCY,
+        }
+    }
+
+    // At rated voltage and nominal frequency
+    fn no_load_speed(&self) -> f64 {
+        self.rated_speed.get::<radian_per_second>() / self.rated_efficiency()
+    }
+
+    fn is_powered(&self) -> bool {
+        self.is_active() && self.frequency_ratio() > 0.0
+    }
+
+    fn back_emf(&self) -> f64 {
+        let speed_ratio = self.speed.get::<radian_per_second>() / self.no_load_speed();
+        self.rated_voltage.get::<volt>() * speed_ratio / self.frequency_ratio()
+    }
+
+    pub fn current(&self) -> ElectricCurrent {
+        self.input_current
+    }
+
+    // The flux, and with it the torque per Ampere, drops as the AC frequency rises
+    pub fn torque(&self) -> Torque {
+        if !self.is_powered() {
+            return Torque::new::<newton_meter>(0.0);
+        }
+        Torque::new::<newton_meter>(
+            self.torque_constant() * self.current().get::<ampere>() / self.frequency_ratio(),
+        )
+    }
+
+    pub fn power_factor(&self) -> f64 {
+        match self.supply {
+            MotorSupply::Dc => 1.0,
+            MotorSupply::Ac { power_factor, .. } => {
+                let speed_ratio = (self.speed / self.rated_speed).value.clamp(0.0, 1.0);
+                LOCKED_ROTOR_POWER_FACTOR + (power_factor - LOCKED_ROTOR_POWER_FACTOR) * speed_ratio
+            }
+        }
+    }
+
+    pub fn real_power(&self) -> Power {
+        Power::new::<watt>(
+            (self.input_voltage.get::<volt>() * self.current().get::<ampere>()).max(0.0),
+        )
+    }
+
+    // In VA
+    pub fn apparent_power(&self) -> Power {
+        self.real_power() / self.power_factor()
+    }
+
+    // RMS current in each phase conductor
+    pub fn line_current(&self) -> ElectricCurrent {
+        match self.supply {
+            MotorSupply::Dc => self.current(),
+            MotorSupply::Ac { connection, .. } => {
+                if self.input_voltage.get::<volt>() <= 0.0 {
+                    return ElectricCurrent::new::<ampere>(0.0);
+                }
+                let phases = connection.phase_count() as f64;
+                self.apparent_power() / (self.input_voltage * phases)
+            }
+        }
+    }
+}
+
+impl ElectricalComponent for Motor {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        let speed = self.speed.get::<radian_per_second>();
+        let motor_torque = self.torque().get::<newton_meter>();
+        // A turning shaft always has its load against it, a still one only breaks away once the motor
+        // torque overcomes it
+        let load_torque = self.load_torque.get::<newton_meter>();
+        let net_torque = if speed > 0.0 {
+            motor_torque - load_torque
+        } else {
+            (motor_torque - load_torque).max(0.0)
+        };
+
+        let stall_torque = self.rated_torque().get::<newton_meter>() * self.stall_current_ratio;
+        let inertia = self.spin_up_time * stall_torque / self.no_load_speed();
+        let speed = (speed + net_torque / inertia * dt as f64).max(0.0);
+        self.speed = AngularVelocity::new::<radian_per_second>(speed);
+
+        let stalled = motor_torque > 0.0
+            && speed < self.no_load_speed() * STALL_SPEED_RATIO
+            && load_torque >= motor_torque;
+        if stalled && !self.stalled {
+            eprintln!(
+                "⚠️ MOTOR STALLED: {} drawing {:.1}A",
+                self.name,
+                self.current().get::<ampere>()
+            );
+        }
+        self.stalled = stalled;
+    }
+
+    fn get_output_power(&self) -> Power {
+        Power::new::<watt>(0.0)
+    }
+
+    fn set_input_power(&mut self, _power: Power) {}
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        ElectricPotential::new::<volt>(0.0)
+    }
+
+    fn set_input_voltage(&mut self, voltage: ElectricPotential) {
+        self.input_voltage = voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        ElectricCurrent::new::<ampere>(0.0)
+    }
+
+    fn get_input_current(&self) -> ElectricCurrent {
+        self.input_current
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.input_current = current;
+    }
+
+    fn set_input_frequency(&mut self, frequency: Frequency) {
+        self.input_frequency = frequency;
+    }
+
+    // Copper loss, the rest of the drawn power goes out through the shaft
+    fn heat_loss(&self) -> Power {
+        let current = self.current().get::<ampere>();
+        Power::new::<watt>(current * current * self.winding_resistance())
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if !self.is_powered() {
+            return CircuitModel::Open;
+        }
+        // Back EMF behind the winding resistance, linearised around the last solved voltage. A still
+        // rotor is the bare winding.
+        let voltage = self.input_voltage.get::<volt>();
+        let back_emf = self.back_emf();
+        let resistance = if back_emf <= 0.0 {
+            self.winding_resistance()
+        } else if voltage > back_emf {
+            voltage * self.winding_resistance() / (voltage - back_emf)
+        } else {
+            f64::INFINITY
+        };
+        CircuitModel::Load {
+            resistance: ElectricalResistance::new::<ohm>(resistance),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME_STEP: f32 = 0.01; // Seconds
+    const SUPPLY: f64 = 28.0; // Volt
+    // 1 kW at 28V with a stall current of 6 times the rated one, 1000 / (5/6 x 28)
+    const RATED_CURRENT: f64 = 1000.0 / (5.0 / 6.0 * 28.0);
+
+    fn pump() -> Motor {
+        let mut motor = Motor::new("Pump", MotorSupply::Dc, SUPPLY, 1000.0, 8000.0, 6.0, 0.5);
+        motor.set_power_state(true);
+        motor.set_input_voltage(ElectricPotential::new::<volt>(SUPPLY));
+        motor
+    }
+
+    // 1 kW three phase fan at 115V with a 0.85 power factor at the rated point
+    fn fan(frequency: f64) -> Motor {
+        let supply = MotorSupply::Ac {
+            connection: PhaseConnection::ThreePhase,
+            power_factor: 0.85,
+        };
+        let mut motor = Motor::new("Fan", supply, 115.0, 1000.0, 11000.0, 6.0, 0.5);
+        motor.set_power_state(true);
+        motor.set_input_voltage(ElectricPotential::new::<volt>(115.0));
+        motor.set_input_frequency(Frequency::new::<hertz>(frequency));
+        motor
+    }
+
+    // A stiff bus, the motor draws whatever its circuit model takes at the supply voltage
+    fn step(motor: &mut Motor) -> f64 {
+        let CircuitModel::Load { resistance } = motor.circuit_model() else {
+            panic!("a powered motor is a load");
+        };
+        let current = motor.input_voltage / resistance;
+        motor.set_input_current(current);
+        motor.update(TIME_STEP);
+        current.get::<ampere>()
+    }
+
+    #[test]
+    fn inrush_settles_to_the_run_current() {
+        let mut motor = pump();
+        motor.set_load_torque(motor.rated_torque());
+
+        let inrush = step(&mut motor);
+        assert!((inrush - 6.0 * RATED_CURRENT).abs() < 1e-6);
+
+        let mut current = inrush;
+        for _ in 0..500 {
+            let next = step(&mut motor);
+            assert!(
+                next <= current + 1e-9,
+                "the current only falls as the motor spins up"
+            );
+            current = next;
+        }
+        assert!((current - RATED_CURRENT).abs() < 0.01 * RATED_CURRENT);
+        assert!((motor.shaft_speed().get::<revolution_per_minute>() - 8000.0).abs() < 80.0);
+        assert!(!motor.is_stalled());
+    }
+
+    #[test]
+    fn seized_load_keeps_the_stall_current() {
+        let mut motor = pump();
+        motor.set_load_torque(motor.rated_torque() * 7.0);
+
+        for _ in 0..100 {
+            assert!((step(&mut motor) - 6.0 * RATED_CURRENT).abs() < 1e-6);
+        }
+        assert!(motor.is_stalled());
+        assert_eq!(motor.shaft_speed().get::<radian_per_second>(), 0.0);
+    }
+
+    #[test]
+    fn ac_torque_speed_line_scales_with_frequency() {
+        let mut nominal = fan(400.0);
+        let mut fast = fan(440.0);
+        let speed = AngularVelocity::new::<revolution_per_minute>(5000.0);
+        let current = ElectricCurrent::new::<ampere>(10.0);
+        for motor in [&mut nominal, &mut fast] {
+            motor.speed = speed;
+            motor.set_input_current(current);
+        }
+        assert!((fast.back_emf() - nominal.back_emf() / 1.1).abs() < 1e-9);
+        assert!(
+            (fast.torque() - nominal.torque() / 1.1)
+                .get::<newton_meter>()
+                .abs()
+                < 1e-9
+        );
+
+        // Unloaded the shaft runs up to the synchronous speed of the supply
+        for frequency in [360.0, 400.0, 440.0] {
+            let mut motor = fan(frequency);
+            for _ in 0..2000 {
+                step(&mut motor);
+            }
+            let no_load_speed = motor.no_load_speed() * frequency / 400.0;
+            let speed = motor.shaft_speed().get::<radian_per_second>();
+            assert!(
+                (speed - no_load_speed).abs() < 0.01 * no_load_speed,
+                "{speed} rad/s at {frequency} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn ac_power_factor_climbs_from_locked_rotor_to_rated() {
+        for frequency in [360.0, 400.0, 440.0] {
+            let mut motor = fan(frequency);
+            motor.set_load_torque(motor.rated_torque() * 0.25);
+            assert_eq!(motor.power_factor(), LOCKED_ROTOR_POWER_FACTOR);
+
+            let mut power_factor = motor.power_factor();
+            for _ in 0..2000 {
+                step(&mut motor);
+                assert!(motor.power_factor() >= power_factor - 1e-12);
+                power_factor = motor.power_factor();
+            }
+            // A quarter load runs above the rated speed at any of these frequencies
+            assert!(
+                (power_factor - 0.85).abs() < 1e-9,
+                "{power_factor} at {frequency} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn ac_line_current_is_per_phase() {
+        for frequency in [360.0, 400.0, 440.0] {
+            let mut motor = fan(frequency);
+            motor.set_load_torque(motor.rated_torque());
+            let inrush = step(&mut motor);
+            // The network carries the real power, each phase conductor a third of the apparent power
+            let line_current = motor.line_current().get::<ampere>();
+            assert!((line_current - inrush / (3.0 * motor.power_factor())).abs() < 1e-9);
+
+            for _ in 0..2000 {
+                step(&mut motor);
+            }
+            let current = motor.current().get::<ampere>();
+            let line_current = motor.line_current().get::<ampere>();
+            assert!((line_current - current / (3.0 * motor.power_factor())).abs() < 1e-9);
+            assert!(line_current < inrush / (3.0 * LOCKED_ROTOR_POWER_FACTOR));
+        }
+
+        let mut motor = fan(400.0);
+        motor.supply = MotorSupply::Ac {
+            connection: PhaseConnection::SinglePhase,
+            power_factor: 0.85,
+        };
+        let current = step(&mut motor);
+        assert!(
+            (motor.line_current().get::<ampere>() - current / motor.power_factor()).abs() < 1e-9
+        );
+
+        motor.set_input_voltage(ElectricPotential::new::<volt>(0.0));
+        assert_eq!(motor.line_current().get::<ampere>(), 0.0);
+    }
+}
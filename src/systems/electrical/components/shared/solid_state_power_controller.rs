@@ -0,0 +1,268 @@
+// Solid State Power Controller (SSPC).
+// Electronic breaker and relay in one, it switches its load on command from the SPDA and protects the
+// wire with an I²t trip: the overload above the rating is integrated and the SSPC trips once the
+// integral reaches its limit, a dead short trips it at once. A trip is latched until the SSPC is reset.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::CircuitModel;
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::time::second;
+
+const ON_RESISTANCE: f64 = 0.01; // Ohm
+// Seconds of (I / I rated)² - 1, 200% trips in 1 s and 500% in 125 ms
+const I2T_LIMIT: f64 = 3.0;
+// Current limit, beyond this the SSPC trips within the frame
+const INSTANT_TRIP_RATIO: f64 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SspcState {
+    Closed,
+    Open,
+    Tripped,
+}
+
+pub struct SolidStatePowerController {
+    name: String,
+    rating: ElectricCurrent,
+    commanded_closed: bool,
+    is_tripped: bool,
+    trip_integral: f64,
+
+    input_voltage: ElectricPotential,
+    input_power: Power,
+    input_current: ElectricCurrent,
+}
+
+impl SolidStatePowerController {
+    pub fn new(name: &str, rating_amps: f64, closed: bool) -> Self {
+        SolidStatePowerController {
+            name: name.to_string(),
+            rating: ElectricCurrent::new::<ampere>(rating_amps),
+            commanded_closed: closed,
+            is_tripped: false,
+            trip_integral: 0.0,
+
+            input_voltage: ElectricPotential::new::<volt>(0.0),
+            input_power: Power::new::<watt>(0.0),
+            input_current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rating(&self) -> ElectricCurrent {
+        self.rating
+    }
+
+    pub fn command(&mut self, closed: bool) {
+        self.commanded_closed = closed;
+    }
+
+    // Clears a latched trip, the SSPC goes back to its commanded state
+    pub fn reset(&mut self) {
+        self.is_tripped = false;
+        self.trip_integral = 0.0;
+    }
+
+    pub fn state(&self) -> SspcState {
+        if self.is_tripped {
+            SspcState::Tripped
+        } else if self.commanded_closed {
+            SspcState::Closed
+        } else {
+            SspcState::Open
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state() == SspcState::Closed
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.is_tripped
+    }
+
+    pub fn current(&self) -> ElectricCurrent {
+        if self.is_closed() {
+            self.input_current
+        } else {
+            ElectricCurrent::new::<ampere>(0.0)
+        }
+    }
+
+    // Time to trip at `current`, None if it is carried forever
+    pub fn trip_time(&self, current: ElectricCurrent) -> Option<Time> {
+        let ratio = (current / self.rating).value.abs();
+        if ratio >= INSTANT_TRIP_RATIO {
+            return Some(Time::new::<second>(0.0));
+        }
+        if ratio <= 1.0 {
+            return None;
+        }
+        Some(Time::new::<second>(I2T_LIMIT / (ratio * ratio - 1.0)))
+    }
+}
+
+impl ElectricalComponent for SolidStatePowerController {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn update(&mut self, dt: f32) {
+        if !self.is_closed() {
+            self.trip_integral = 0.0;
+            return;
+        }
+
+        // Below the rating the integral bleeds off the same way it builds up
+        let ratio = (self.input_current / self.rating).value.abs();
+        self.trip_integral = (self.trip_integral + (ratio * ratio - 1.0) * dt as f64).max(0.0);
+
+        if ratio >= INSTANT_TRIP_RATIO || self.trip_integral >= I2T_LIMIT {
+            self.is_tripped = true;
+            eprintln!(
+                "⚡ {} TRIPPED at {:.1} A",
+                self.name,
+                self.input_current.get::<ampere>().abs()
+            );
+        }
+    }
+
+    fn get_output_power(&self) -> Power {
+        if self.is_closed() {
+            self.input_power
+        } else {
+            Power::new::<watt>(0.0)
+        }
+    }
+
+    fn set_input_power(&mut self, input_power: Power) {
+        self.input_power = input_power;
+    }
+
+    fn get_output_voltage(&self) -> ElectricPotential {
+        if self.is_closed() {
+            self.input_voltage
+        } else {
+            ElectricPotential::new::<volt>(0.0)
+        }
+    }
+
+    fn set_input_voltage(&mut self, input_voltage: ElectricPotential) {
+        self.input_voltage = input_voltage;
+    }
+
+    fn get_output_current(&self) -> ElectricCurrent {
+        self.current()
+    }
+
+    fn set_input_current(&mut self, current: ElectricCurrent) {
+        self.input_current = current;
+    }
+
+    fn circuit_model(&self) -> CircuitModel {
+        if self.is_closed() {
+            CircuitModel::Series {
+                resistance: ElectricalResistance::new::<ohm>(ON_RESISTANCE),
+            }
+        } else {
+            CircuitModel::Open
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATING: f64 = 5.0; // Ampere
+    const TIME_STEP: f32 = 0.005; // Seconds
+
+    fn sspc() -> SolidStatePowerController {
+        SolidStatePowerController::new("SSPC", RATING, true)
+    }
+
+    // Runs at `current` for up to `limit` seconds, returns when the SSPC tripped
+    fn run(sspc: &mut SolidStatePowerController, current: f64, limit: f64) -> Option<f64> {
+        sspc.set_input_current(ElectricCurrent::new::<ampere>(current));
+        let mut elapsed = 0.0;
+        while elapsed < limit {
+            sspc.update(TIME_STEP);
+            elapsed += TIME_STEP as f64;
+            if sspc.is_tripped() {
+                return Some(elapsed);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn i2t_trip_times() {
+        for (overload, expected) in [(2.0, 1.0), (5.0, 0.125)] {
+            let mut sspc = sspc();
+            let tripped = run(&mut sspc, overload * RATING, 10.0).unwrap();
+            assert!(
+                (tripped - expected).abs() <= 2.0 * TIME_STEP as f64,
+                "{overload}x tripped after {tripped} s instead of {expected} s"
+            );
+            assert_eq!(sspc.state(), SspcState::Tripped);
+        }
+
+        // At the rating it never trips, at ten times it trips in the first frame
+        assert_eq!(run(&mut sspc(), RATING, 60.0), None);
+        let tripped = run(&mut sspc(), 10.0 * RATING, 1.0).unwrap();
+        assert_eq!(tripped, TIME_STEP as f64);
+    }
+
+    #[test]
+    fn overload_integral_bleeds_off_below_the_rating() {
+        let mut sspc = sspc();
+        // Two thirds of the way to a trip at 200%, then back to half the rating
+        assert_eq!(run(&mut sspc, 2.0 * RATING, 0.66), None);
+        assert_eq!(run(&mut sspc, 0.5 * RATING, 3.0), None);
+        // Cooled right down, a fresh 200% overload takes the full second again
+        let tripped = run(&mut sspc, 2.0 * RATING, 10.0).unwrap();
+        assert!((tripped - 1.0).abs() <= 2.0 * TIME_STEP as f64, "{tripped}");
+    }
+
+    #[test]
+    fn trip_latches_until_reset() {
+        let mut sspc = sspc();
+        run(&mut sspc, 5.0 * RATING, 1.0).unwrap();
+        assert!(matches!(sspc.circuit_model(), CircuitModel::Open));
+        assert_eq!(sspc.current().get::<ampere>(), 0.0);
+
+        // Commanding it closed does not clear the trip
+        sspc.command(true);
+        assert_eq!(run(&mut sspc, 0.0, 1.0), Some(TIME_STEP as f64));
+        assert_eq!(sspc.state(), SspcState::Tripped);
+
+        // A reset takes it back to the commanded state, with the integral cleared
+        sspc.command(false);
+        sspc.reset();
+        assert_eq!(sspc.state(), SspcState::Open);
+        sspc.command(true);
+        sspc.reset();
+        assert_eq!(sspc.state(), SspcState::Closed);
+        let tripped = run(&mut sspc, 2.0 * RATING, 10.0).unwrap();
+        assert!((tripped - 1.0).abs() <= 2.0 * TIME_STEP as f64, "{tripped}");
+    }
+
+    #[test]
+    fn open_sspc_does_not_integrate() {
+        let mut sspc = SolidStatePowerController::new("SSPC", RATING, false);
+        assert_eq!(run(&mut sspc, 5.0 * RATING, 5.0), None);
+        assert_eq!(sspc.state(), SspcState::Open);
+    }
+}
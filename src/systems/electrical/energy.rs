@@ -0,0 +1,379 @@
+// Energy and heat accounting.
+// Every solve is turned into a power balance: what the sources and converter outputs put into the network
+// against what the loads, converter inputs, wires, switching elements and shorts take out of it. The
+// powers of each tick are integrated into the energy each component, bus and wire has delivered, consumed
+// and turned into heat, which is what the cooling models need. A linear solve balances exactly, so a
+// tick that does not is a solver bug.
+
+use crate::systems::electrical::ElectricalComponent;
+use crate::systems::electrical::solver::{CircuitModel, NetworkSolution, conductance};
+
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+use uom::si::electrical_resistance::ohm;
+use uom::si::energy::joule;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::time::second;
+
+// Share of the generated power the balance may be off by, the rest of the slack covers solver leakage
+const BALANCE_TOLERANCE: f64 = 1e-3;
+const BALANCE_FLOOR: f64 = 0.5; // Watt
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnergyAccount {
+    // Last tick
+    pub delivered_power: Power,
+    pub consumed_power: Power,
+    pub heat_power: Power,
+    // Since the start of the simulation
+    pub delivered: Energy,
+    pub consumed: Energy,
+    pub heat: Energy,
+}
+
+impl Default for EnergyAccount {
+    fn default() -> Self {
+        EnergyAccount {
+            delivered_power: Power::new::<watt>(0.0),
+            consumed_power: Power::new::<watt>(0.0),
+            heat_power: Power::new::<watt>(0.0),
+            delivered: Energy::new::<joule>(0.0),
+            consumed: Energy::new::<joule>(0.0),
+            heat: Energy::new::<joule>(0.0),
+        }
+    }
+}
+
+impl EnergyAccount {
+    fn record(&mut self, delivered: f64, consumed: f64, heat: f64, dt: Time) {
+        self.delivered_power = Power::new::<watt>(delivered);
+        self.consumed_power = Power::new::<watt>(consumed);
+        self.heat_power = Power::new::<watt>(heat);
+        self.delivered += self.delivered_power * dt;
+        self.consumed += self.consumed_power * dt;
+        self.heat += self.heat_power * dt;
+    }
+}
+
+// Power balance of one solve
+#[derive(Clone, Copy, Debug)]
+pub struct PowerBalance {
+    // Sources and converter outputs
+    pub generated: Power,
+    // Loads, converter inputs and charging batteries
+    pub consumed: Power,
+    // Wires, switching elements and shorts
+    pub dissipated: Power,
+}
+
+impl Default for PowerBalance {
+    fn default() -> Self {
+        PowerBalance {
+            generated: Power::new::<watt>(0.0),
+            consumed: Power::new::<watt>(0.0),
+            dissipated: Power::new::<watt>(0.0),
+        }
+    }
+}
+
+impl PowerBalance {
+    pub fn imbalance(&self) -> Power {
+        self.generated - self.consumed - self.dissipated
+    }
+
+    pub fn is_conserved(&self) -> bool {
+        let tolerance = (self.generated.get::<watt>().abs() * BALANCE_TOLERANCE).max(BALANCE_FLOOR);
+        self.imbalance().get::<watt>().abs() <= tolerance
+    }
+}
+
+#[derive(Default)]
+pub struct EnergyLedger {
+    components: HashMap<NodeIndex, EnergyAccount>,
+    wires: HashMap<(NodeIndex, NodeIndex), EnergyAccount>,
+    // Heat released in shorts to ground
+    faults: EnergyAccount,
+    balance: PowerBalance,
+    // Solves that did not balance since the start of the simulation
+    imbalanced_solves: usize,
+}
+
+impl EnergyLedger {
+    // Delivered is the throughput for buses
+    pub fn component(&self, node: NodeIndex) -> Option<&EnergyAccount> {
+        self.components.get(&node)
+    }
+
+    pub fn wire(&self, from: NodeIndex, to: NodeIndex) -> Option<&EnergyAccount> {
+        self.wires.get(&(from, to))
+    }
+
+    pub fn faults(&self) -> &EnergyAccount {
+        &self.faults
+    }
+
+    pub fn balance(&self) -> PowerBalance {
+        self.balance
+    }
+
+    pub fn imbalanced_solves(&self) -> usize {
+        self.imbalanced_solves
+    }
+
+    // Heat released by a group of components and the wires between them, e.g. everything in one e-bay
+    pub fn heat_power(&self, nodes: &[NodeIndex]) -> Power {
+        let components = nodes
+            .iter()
+            .filter_map(|node| self.components.get(node))
+            .map(|account| account.heat_power);
+        let wires = self
+            .wires
+            .iter()
+            .filter(|((from, to), _)| nodes.contains(from) && nodes.contains(to))
+            .map(|(_, account)| account.heat_power);
+        components
+            .chain(wires)
+            .fold(Power::new::<watt>(0.0), |sum, power| sum + power)
+    }
+
+    pub fn total_heat_power(&self) -> Power {
+        self.components
+            .values()
+            .chain(self.wires.values())
+            .chain([&self.faults])
+            .fold(Power::new::<watt>(0.0), |sum, account| {
+                sum + account.heat_power
+            })
+    }
+
+    pub(crate) fn forget(&mut self, node: NodeIndex) {
+        self.components.remove(&node);
+        self.wires
+            .retain(|(from, to), _| *from != node && *to != node);
+    }
+
+    // Books one solve, the circuit models have to be the ones the solution came from
+    pub(crate) fn record(
+        &mut self,
+        components: &HashMap<NodeIndex, Box<dyn ElectricalComponent>>,
+        shunts: &[(NodeIndex, ElectricalResistance)],
+        solution: &NetworkSolution,
+        dt: f32,
+    ) {
+        let dt = Time::new::<second>(dt as f64);
+        let voltage = |map: &HashMap<NodeIndex, ElectricPotential>, node| {
+            map.get(&node).copied().unwrap_or_default().value
+        };
+        let mut balance = [0.0; 3]; // generated, consumed, dissipated
+
+        for (node, component) in components {
+            let input = voltage(&solution.input_voltage, *node);
+            let output = voltage(&solution.output_voltage, *node);
+            let current = solution
+                .component_current
+                .get(node)
+                .copied()
+                .unwrap_or_default()
+                .value;
+
+            // Heat in the network (counted in the balance) and inside the component (behind its terminals)
+            let (delivered, consumed, network_heat, internal_heat) = match component.circuit_model()
+            {
+                CircuitModel::Source {
+                    internal_resistance,
+                    ..
+                } => {
+                    // A charging battery takes power out of the network
+                    let power = input * current;
+                    let internal = current * current * internal_resistance.get::<ohm>();
+                    (power.max(0.0), (-power).max(0.0), 0.0, internal)
+                }
+                CircuitModel::Load { .. } => (0.0, input * current, 0.0, 0.0),
+                CircuitModel::Series { .. } | CircuitModel::Diode { .. } => {
+                    (0.0, 0.0, (input - output) * current, 0.0)
+                }
+                // A load on the feeding network and a source on the fed one
+                CircuitModel::Converter {
+                    input_resistance, ..
+                } => {
+                    let drawn = input * input * conductance(input_resistance.get::<ohm>());
+                    let delivered = output * current;
+                    (delivered, drawn, 0.0, drawn - delivered)
+                }
+                // Buses only pass power on, they are booked with what flows through them
+                CircuitModel::Junction => {
+                    self.components
+                        .entry(*node)
+                        .or_default()
+                        .record(input * current, 0.0, 0.0, dt);
+                    continue;
+                }
+                CircuitModel::Open => (0.0, 0.0, 0.0, 0.0),
+            };
+            balance[0] += delivered;
+            balance[1] += consumed;
+            balance[2] += network_heat;
+
+            // Plus the losses the network does not see at all, generator windings and bearings
+            let heat = network_heat + internal_heat + component.heat_loss().get::<watt>();
+            self.components
+                .entry(*node)
+                .or_default()
+                .record(delivered, consumed, heat, dt);
+        }
+
+        for ((from, to), current) in &solution.wire_current {
+            let drop =
+                voltage(&solution.output_voltage, *from) - voltage(&solution.input_voltage, *to);
+            let heat = drop * current.value;
+            balance[2] += heat;
+            self.wires
+                .entry((*from, *to))
+                .or_default()
+                .record(0.0, 0.0, heat, dt);
+        }
+
+        let fault_heat: f64 = shunts
+            .iter()
+            .zip(&solution.shunt_current)
+            .map(|((node, _), current)| voltage(&solution.input_voltage, *node) * current.value)
+            .sum();
+        balance[2] += fault_heat;
+        self.faults.record(0.0, 0.0, fault_heat, dt);
+
+        self.balance = PowerBalance {
+            generated: Power::new::<watt>(balance[0]),
+            consumed: Power::new::<watt>(balance[1]),
+            dissipated: Power::new::<watt>(balance[2]),
+        };
+        if !self.balance.is_conserved() {
+            self.imbalanced_solves += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::components::dc::generic_dc_component::{
+        GenericDcComponent, VoltageResponse,
+    };
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::fault::Fault;
+    use crate::systems::electrical::wire::Wire;
+    use crate::systems::electrical::{ComponentHandle, ElectricalSystem};
+    use uom::si::electric_potential::volt;
+    use uom::si::electrical_resistance::ohm;
+    use uom::si::energy::joule;
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+
+    const TICKS: usize = 50;
+    const DT: f32 = 0.1;
+
+    struct Network {
+        system: ElectricalSystem,
+        battery: ComponentHandle<Battery>,
+        bus: ComponentHandle<Bus>,
+        load: ComponentHandle<GenericDcComponent>,
+    }
+
+    // Battery -> bus -> 280W load, both feeders 50 mOhm
+    fn network() -> Network {
+        let mut system = ElectricalSystem::new();
+        let battery = system
+            .add_component("Battery", Battery::new("Battery", 24.0, 44.0, 0.02, 1.0))
+            .unwrap();
+        let bus = system
+            .add_component(
+                "DC Bus",
+                Bus {
+                    voltage: ElectricPotential::new::<volt>(0.0),
+                    power: Power::new::<watt>(0.0),
+                },
+            )
+            .unwrap();
+        let mut load = GenericDcComponent::new(
+            "Load",
+            28.0,
+            280.0,
+            18.0,
+            32.0,
+            VoltageResponse::Linear,
+            1.0,
+        );
+        load.set_power_state(true);
+        let load = system.add_component("Load", load).unwrap();
+        system.connect_with_wire(battery, bus, Wire::fixed(0.05));
+        system.connect_with_wire(bus, load, Wire::fixed(0.05));
+        Network {
+            system,
+            battery,
+            bus,
+            load,
+        }
+    }
+
+    fn joules(energy: Energy) -> f64 {
+        energy.get::<joule>()
+    }
+
+    #[test]
+    fn energy_delivered_is_consumed_or_heat() {
+        let Network {
+            mut system,
+            battery,
+            bus,
+            load,
+        } = network();
+        for _ in 0..TICKS {
+            system.update_system(DT);
+            assert!(system.energy().balance().is_conserved());
+        }
+        assert_eq!(system.energy().imbalanced_solves(), 0);
+
+        let ledger = system.energy();
+        let delivered = joules(ledger.component(battery.node()).unwrap().delivered);
+        let consumed = joules(ledger.component(load.node()).unwrap().consumed);
+        let feeder_heat = joules(ledger.wire(battery.node(), bus.node()).unwrap().heat);
+        let branch_heat = joules(ledger.wire(bus.node(), load.node()).unwrap().heat);
+        assert!(delivered > 0.0 && consumed > 0.0 && feeder_heat > 0.0);
+        assert!((delivered - consumed - feeder_heat - branch_heat).abs() < 1e-6 * delivered);
+
+        // The bus passes on what reached it
+        let throughput = joules(ledger.component(bus.node()).unwrap().delivered);
+        assert!((throughput - consumed - branch_heat).abs() < 1e-6 * delivered);
+    }
+
+    #[test]
+    fn heat_of_a_short_is_booked_to_the_faults() {
+        let Network {
+            mut system,
+            battery,
+            bus,
+            ..
+        } = network();
+        system.update_system(DT);
+        system
+            .inject_fault(Fault::ShortToGround {
+                node: bus.node(),
+                resistance: ElectricalResistance::new::<ohm>(0.1),
+            })
+            .unwrap();
+        system.update_system(DT);
+
+        let ledger = system.energy();
+        assert!(ledger.balance().is_conserved());
+        let fault_heat = ledger.faults().heat_power.get::<watt>();
+        assert!(fault_heat > 0.0);
+        // Nothing but the fault, the load and the wires to take the battery power
+        let delivered = ledger
+            .component(battery.node())
+            .unwrap()
+            .delivered_power
+            .get::<watt>();
+        assert!(delivered > fault_heat);
+        assert!((ledger.balance().generated.get::<watt>() - delivered).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,214 @@
+// Electrical failures for training scenarios.
+// Faults are injected on a live `ElectricalSystem` and are part of every solve until they are cleared.
+// A short to ground is an extra path to ground at a component, the fault current follows from the network
+// so the breakers and GCUs upstream of it see it like any other overload. Wire faults change the wire
+// instead: an open wire stops conducting, a high resistance joint adds resistance (and heat) in series and
+// an intermittent connection alternates between the two.
+
+use petgraph::graph::NodeIndex;
+use std::fmt;
+use uom::si::electric_current::ampere;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::time::second;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Fault {
+    // Path to ground on the input side of the component, a bolted short is a few milliohms
+    ShortToGround {
+        node: NodeIndex,
+        resistance: ElectricalResistance,
+    },
+    // Broken conductor or a pin backed out of its connector
+    OpenWire {
+        from: NodeIndex,
+        to: NodeIndex,
+    },
+    // Loose connection, open for `open_time` then making contact again for `closed_time`
+    Intermittent {
+        from: NodeIndex,
+        to: NodeIndex,
+        open_time: Time,
+        closed_time: Time,
+    },
+    // Corroded or loose joint, in series with the wire
+    HighResistance {
+        from: NodeIndex,
+        to: NodeIndex,
+        resistance: ElectricalResistance,
+    },
+}
+
+impl Fault {
+    pub fn short_to_ground(node: NodeIndex, resistance: f64) -> Self {
+        Fault::ShortToGround {
+            node,
+            resistance: ElectricalResistance::new::<ohm>(resistance),
+        }
+    }
+
+    pub fn open_wire(from: NodeIndex, to: NodeIndex) -> Self {
+        Fault::OpenWire { from, to }
+    }
+
+    pub fn intermittent(from: NodeIndex, to: NodeIndex, open_time: f64, closed_time: f64) -> Self {
+        Fault::Intermittent {
+            from,
+            to,
+            open_time: Time::new::<second>(open_time),
+            closed_time: Time::new::<second>(closed_time),
+        }
+    }
+
+    pub fn high_resistance(from: NodeIndex, to: NodeIndex, resistance: f64) -> Self {
+        Fault::HighResistance {
+            from,
+            to,
+            resistance: ElectricalResistance::new::<ohm>(resistance),
+        }
+    }
+
+    // The wire this fault sits on, if it is a wire fault
+    pub fn wire(&self) -> Option<(NodeIndex, NodeIndex)> {
+        match self {
+            Fault::ShortToGround { .. } => None,
+            Fault::OpenWire { from, to }
+            | Fault::Intermittent { from, to, .. }
+            | Fault::HighResistance { from, to, .. } => Some((*from, *to)),
+        }
+    }
+
+    pub(crate) fn resistance(&self) -> Option<ElectricalResistance> {
+        match self {
+            Fault::ShortToGround { resistance, .. } | Fault::HighResistance { resistance, .. } => {
+                Some(*resistance)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn involves(&self, node: NodeIndex) -> bool {
+        match self {
+            Fault::ShortToGround { node: faulted, .. } => *faulted == node,
+            _ => self
+                .wire()
+                .is_some_and(|(from, to)| from == node || to == node),
+        }
+    }
+}
+
+// Why a fault was not injected, a fault on something that is not in the network would never show up
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FaultError {
+    UnknownNode(NodeIndex),
+    // The two components are not wired together
+    UnknownWire { from: NodeIndex, to: NodeIndex },
+    NegativeResistance(ElectricalResistance),
+}
+
+impl fmt::Display for FaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultError::UnknownNode(node) => write!(f, "no component at node {}", node.index()),
+            FaultError::UnknownWire { from, to } => write!(
+                f,
+                "no wire from node {} to node {}",
+                from.index(),
+                to.index()
+            ),
+            FaultError::NegativeResistance(resistance) => {
+                write!(
+                    f,
+                    "fault resistance {:.3} Ohm is negative",
+                    resistance.value
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FaultId(pub(crate) usize);
+
+pub struct ActiveFault {
+    id: FaultId,
+    fault: Fault,
+    is_open: bool,
+    timer: Time,
+    current: ElectricCurrent,
+    power: Power,
+}
+
+impl ActiveFault {
+    pub(crate) fn new(id: FaultId, fault: Fault) -> Self {
+        ActiveFault {
+            id,
+            fault,
+            is_open: matches!(fault, Fault::OpenWire { .. }),
+            timer: Time::new::<second>(0.0),
+            current: ElectricCurrent::new::<ampere>(0.0),
+            power: Power::new::<watt>(0.0),
+        }
+    }
+
+    pub fn id(&self) -> FaultId {
+        self.id
+    }
+
+    pub fn fault(&self) -> &Fault {
+        &self.fault
+    }
+
+    // Whether the faulted wire is currently not conducting
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    // Current into the short, or through the faulted wire
+    pub fn current(&self) -> ElectricCurrent {
+        self.current
+    }
+
+    // Heat released at the fault, what makes the smoke
+    pub fn power(&self) -> Power {
+        self.power
+    }
+
+    pub(crate) fn update(&mut self, dt: f32) {
+        if let Fault::Intermittent {
+            open_time,
+            closed_time,
+            ..
+        } = self.fault
+        {
+            self.timer += Time::new::<second>(dt as f64);
+            let period = if self.is_open { open_time } else { closed_time };
+            if self.timer >= period {
+                self.is_open = !self.is_open;
+                self.timer = Time::new::<second>(0.0);
+            }
+        }
+    }
+
+    // Resistance the fault puts in series with its wire
+    pub(crate) fn series_resistance(&self) -> ElectricalResistance {
+        match self.fault {
+            _ if self.is_open => ElectricalResistance::new::<ohm>(f64::INFINITY),
+            Fault::HighResistance { resistance, .. } => resistance,
+            _ => ElectricalResistance::new::<ohm>(0.0),
+        }
+    }
+
+    pub(crate) fn set_current(&mut self, current: ElectricCurrent) {
+        self.current = current;
+        let resistance = match self.fault {
+            Fault::ShortToGround { resistance, .. } | Fault::HighResistance { resistance, .. } => {
+                resistance.get::<ohm>()
+            }
+            _ => 0.0,
+        };
+        let amps = current.get::<ampere>();
+        self.power = Power::new::<watt>(amps * amps * resistance);
+    }
+}
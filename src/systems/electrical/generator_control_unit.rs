@@ -0,0 +1,474 @@
+// Generator Control Unit.
+// Watches one generator and its line contactor and protects the network: every protection has a pickup
+// threshold and a time delay, once a protection trips the fault is latched, the line contactor is opened
+// and, for the faults that can damage the network, the field is de-excited. Latched faults are cleared
+// by cycling the GEN switch. An underspeed (engine shutting down) only drops power ready and is not latched.
+// The line contactor only closes with power ready and the BPCU permission for this source.
+
+use crate::systems::electrical::components::ac::generator::Generator;
+use crate::systems::electrical::components::ac::integrated_drive_generator::IntegratedDriveGenerator;
+use crate::systems::electrical::components::shared::contactor::Contactor;
+use crate::systems::electrical::{ComponentHandle, ElectricalComponent, ElectricalSystem};
+
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::f64::*;
+use uom::si::frequency::hertz;
+use uom::si::time::second;
+
+// The GCU and the line contactor coil run off the generator PMG, whose output goes with the shaft speed.
+// The GCU supply regulates it to this once the shaft is at rated speed.
+const PMG_COIL_VOLTAGE: f64 = 28.0; // Volt
+// Of rated speed, below the underfrequency pickup so a drive governing low still trips underfrequency
+const UNDERSPEED_RATIO: f64 = 0.9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GcuFault {
+    Overvoltage,
+    Undervoltage,
+    Overfrequency,
+    Underfrequency,
+    Overcurrent,
+    Differential,
+}
+
+impl GcuFault {
+    // Faults that take the field down with the line contactor
+    fn de_excites(&self) -> bool {
+        matches!(
+            self,
+            GcuFault::Overvoltage | GcuFault::Differential | GcuFault::Overfrequency
+        )
+    }
+}
+
+// The generator either sits directly in the network or behind its constant speed drive
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MonitoredGenerator {
+    Generator(ComponentHandle<Generator>),
+    Idg(ComponentHandle<IntegratedDriveGenerator>),
+}
+
+impl MonitoredGenerator {
+    fn generator_mut<'a>(&self, system: &'a mut ElectricalSystem) -> Option<&'a mut Generator> {
+        match *self {
+            MonitoredGenerator::Generator(generator) => system.get_mut(generator),
+            MonitoredGenerator::Idg(idg) => system.get_mut(idg).map(|idg| idg.generator_mut()),
+        }
+    }
+
+    // The engine has dropped below the range the drive can govern, a normal shutdown
+    fn is_drive_underspeed(&self, system: &ElectricalSystem) -> bool {
+        match *self {
+            MonitoredGenerator::Generator(_) => false,
+            MonitoredGenerator::Idg(idg) => system
+                .get(idg)
+                .is_some_and(|idg| idg.is_below_governing_range()),
+        }
+    }
+}
+
+impl From<ComponentHandle<Generator>> for MonitoredGenerator {
+    fn from(handle: ComponentHandle<Generator>) -> Self {
+        MonitoredGenerator::Generator(handle)
+    }
+}
+
+impl From<ComponentHandle<IntegratedDriveGenerator>> for MonitoredGenerator {
+    fn from(handle: ComponentHandle<IntegratedDriveGenerator>) -> Self {
+        MonitoredGenerator::Idg(handle)
+    }
+}
+
+struct Protection {
+    fault: GcuFault,
+    delay: Time,
+    timer: Time,
+}
+
+impl Protection {
+    fn new(fault: GcuFault, delay: f64) -> Self {
+        Protection {
+            fault,
+            delay: Time::new::<second>(delay),
+            timer: Time::new::<second>(0.0),
+        }
+    }
+
+    // Returns true once the condition held for the full delay
+    fn update(&mut self, condition: bool, dt: Time) -> bool {
+        if condition {
+            self.timer += dt;
+        } else {
+            self.timer = Time::new::<second>(0.0);
+        }
+        self.timer >= self.delay
+    }
+}
+
+pub struct GeneratorControlUnit {
+    name: String,
+    generator: MonitoredGenerator,
+    line_contactor: ComponentHandle<Contactor>,
+
+    overvoltage: ElectricPotential,
+    undervoltage: ElectricPotential,
+    overfrequency: Frequency,
+    underfrequency: Frequency,
+    overcurrent: ElectricCurrent,
+    differential_current: ElectricCurrent,
+    protections: Vec<Protection>,
+
+    generator_switch: bool,
+    bus_permission: bool,
+    latched_fault: Option<GcuFault>,
+    is_power_ready: bool,
+}
+
+impl GeneratorControlUnit {
+    pub fn new(
+        name: &str,
+        generator: impl Into<MonitoredGenerator>,
+        line_contactor: ComponentHandle<Contactor>,
+        rated_current: f64,
+    ) -> Self {
+        GeneratorControlUnit {
+            name: name.to_string(),
+            generator: generator.into(),
+            line_contactor,
+
+            overvoltage: ElectricPotential::new::<volt>(125.0),
+            undervoltage: ElectricPotential::new::<volt>(100.0),
+            overfrequency: Frequency::new::<hertz>(430.0),
+            underfrequency: Frequency::new::<hertz>(370.0),
+            overcurrent: ElectricCurrent::new::<ampere>(rated_current * 1.5),
+            differential_current: ElectricCurrent::new::<ampere>(20.0),
+            protections: vec![
+                Protection::new(GcuFault::Differential, 0.05),
+                Protection::new(GcuFault::Overvoltage, 0.5),
+                Protection::new(GcuFault::Overfrequency, 1.0),
+                Protection::new(GcuFault::Underfrequency, 1.0),
+                Protection::new(GcuFault::Overcurrent, 5.0),
+                Protection::new(GcuFault::Undervoltage, 7.0),
+            ],
+
+            generator_switch: false,
+            bus_permission: true,
+            latched_fault: None,
+            is_power_ready: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Cockpit GEN switch, cycling it OFF then ON resets a latched fault
+    pub fn set_generator_switch(&mut self, on: bool) {
+        if on && !self.generator_switch {
+            self.latched_fault = None;
+            for protection in &mut self.protections {
+                protection.timer = Time::new::<second>(0.0);
+            }
+        }
+        self.generator_switch = on;
+    }
+
+    // Source priority from the BPCU
+    pub fn set_bus_permission(&mut self, permitted: bool) {
+        self.bus_permission = permitted;
+    }
+
+    pub fn latched_fault(&self) -> Option<GcuFault> {
+        self.latched_fault
+    }
+
+    // Generator voltage and frequency are inside limits, the line contactor may close
+    pub fn is_power_ready(&self) -> bool {
+        self.is_power_ready
+    }
+
+    pub fn line_contactor(&self) -> ComponentHandle<Contactor> {
+        self.line_contactor
+    }
+
+    pub fn update(&mut self, dt: f32, system: &mut ElectricalSystem) {
+        let dt = Time::new::<second>(dt as f64);
+
+        let line_current = system
+            .get(self.line_contactor)
+            .map(|contactor| contactor.get_output_current())
+            .unwrap_or_default();
+
+        let drive_underspeed = self.generator.is_drive_underspeed(system);
+        let Some(generator) = self.generator.generator_mut(system) else {
+            return;
+        };
+
+        let voltage = generator.get_output_voltage();
+        let frequency = generator.output_frequency();
+        let current = generator.get_output_current();
+        // Speed sensing, an underspeed is a normal engine shutdown and is not latched
+        let underspeed =
+            drive_underspeed || generator.current_rpm() < generator.rated_rpm() * UNDERSPEED_RATIO;
+        let excited = generator.is_on();
+        let speed_ratio = (generator.current_rpm() / generator.rated_rpm()).value;
+        let coil_voltage =
+            ElectricPotential::new::<volt>(PMG_COIL_VOLTAGE * speed_ratio.clamp(0.0, 1.0));
+
+        if excited && self.latched_fault.is_none() {
+            let differential = (current - line_current).abs();
+            for protection in &mut self.protections {
+                let condition = match protection.fault {
+                    GcuFault::Overvoltage => voltage > self.overvoltage,
+                    // Under voltage and under frequency are inhibited while under speed
+                    GcuFault::Undervoltage => voltage < self.undervoltage && !underspeed,
+                    GcuFault::Overfrequency => frequency > self.overfrequency,
+                    GcuFault::Underfrequency => frequency < self.underfrequency && !underspeed,
+                    GcuFault::Overcurrent => current > self.overcurrent,
+                    GcuFault::Differential => differential > self.differential_current,
+                };
+                if protection.update(condition, dt) {
+                    self.latched_fault = Some(protection.fault);
+                    eprintln!("⚠️ {} TRIP: {:?}", self.name, protection.fault);
+                    break;
+                }
+            }
+        }
+
+        // Field control relay
+        let field_allowed =
+            self.generator_switch && !self.latched_fault.is_some_and(|fault| fault.de_excites());
+        if field_allowed && !generator.is_on() {
+            generator.turn_on();
+        } else if !field_allowed && generator.is_on() {
+            generator.turn_off();
+        }
+
+        // Voltage and frequency only gate the closing of the contactor, once it is in the protections
+        // and their time delays decide when it opens
+        let in_limits = voltage >= self.undervoltage
+            && voltage <= self.overvoltage
+            && frequency >= self.underfrequency
+            && frequency <= self.overfrequency;
+        self.is_power_ready = field_allowed
+            && self.latched_fault.is_none()
+            && !underspeed
+            && (self.is_power_ready || in_limits);
+
+        if let Some(contactor) = system.get_mut(self.line_contactor) {
+            contactor.set_coil_voltage(coil_voltage);
+            contactor.command(self.is_power_ready && self.bus_permission);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::angular_velocity::revolution_per_minute;
+    use uom::si::ratio::percent;
+
+    const TIME_STEP: f32 = 0.1; // Seconds
+    const RATED_VOLTAGE: f64 = 115.0; // Volt
+    const RATED_FREQUENCY: f64 = 400.0; // Hz
+    const NUM_POLES: f64 = 2.0;
+
+    struct Bench {
+        system: ElectricalSystem,
+        generator: ComponentHandle<Generator>,
+        gcu: GeneratorControlUnit,
+    }
+
+    // Generator on line with its GEN switch on
+    fn bench() -> Bench {
+        let mut system = ElectricalSystem::new();
+        let generator = Generator::new(
+            NUM_POLES,
+            40000.0,
+            RATED_VOLTAGE,
+            RATED_FREQUENCY,
+            0.95,
+            0.05,
+            0.0,
+            3,
+        );
+        let generator = system.add_component("GEN", generator).unwrap();
+        let contactor = Contactor::new("GLC", 18.0, 12.0, 0.05);
+        let contactor = system.add_component("GLC", contactor).unwrap();
+        let mut gcu = GeneratorControlUnit::new("GCU", generator, contactor, 116.0);
+        gcu.set_generator_switch(true);
+        let mut bench = Bench {
+            system,
+            generator,
+            gcu,
+        };
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 1.0);
+        assert!(bench.gcu.is_power_ready());
+        assert!(bench.is_line_contactor_commanded());
+        bench
+    }
+
+    impl Bench {
+        // The shaft turns at `frequency` and the network holds the terminals at `voltage` while the field is on
+        fn run(&mut self, voltage: f64, frequency: f64, seconds: f64) {
+            for _ in 0..(seconds / TIME_STEP as f64).round() as usize {
+                self.step(voltage, frequency);
+            }
+        }
+
+        fn step(&mut self, voltage: f64, frequency: f64) {
+            let generator = self.system.get_mut(self.generator).unwrap();
+            generator.set_mechanical_input(60000.0, frequency * 60.0 / NUM_POLES);
+            generator.update(TIME_STEP);
+            if generator.is_on() {
+                generator.set_input_voltage(ElectricPotential::new::<volt>(voltage));
+            }
+            self.gcu.update(TIME_STEP, &mut self.system);
+        }
+
+        // Seconds until a protection latches, none within 10 seconds
+        fn time_to_trip(&mut self, voltage: f64, frequency: f64) -> Option<f64> {
+            for step in 1..=100 {
+                self.step(voltage, frequency);
+                if self.gcu.latched_fault().is_some() {
+                    return Some(step as f64 * TIME_STEP as f64);
+                }
+            }
+            None
+        }
+
+        fn is_field_on(&self) -> bool {
+            self.system.get(self.generator).unwrap().is_on()
+        }
+
+        fn is_line_contactor_commanded(&self) -> bool {
+            self.system
+                .get(self.gcu.line_contactor())
+                .unwrap()
+                .is_commanded_closed()
+        }
+    }
+
+    fn assert_trip(time: Option<f64>, delay: f64) {
+        let time = time.expect("no trip");
+        assert!(
+            (time - delay).abs() <= TIME_STEP as f64 + 1e-6,
+            "tripped after {time} s instead of {delay} s"
+        );
+    }
+
+    #[test]
+    fn overvoltage_trips_after_its_delay_and_takes_the_field_down() {
+        let mut bench = bench();
+        // Shorter than the delay, the timer starts over
+        bench.run(130.0, RATED_FREQUENCY, 0.3);
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 0.1);
+        assert_eq!(bench.gcu.latched_fault(), None);
+
+        assert_trip(bench.time_to_trip(130.0, RATED_FREQUENCY), 0.5);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Overvoltage));
+        assert!(!bench.is_field_on());
+        assert!(!bench.gcu.is_power_ready());
+        assert!(!bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn undervoltage_trips_after_its_delay_and_keeps_the_field() {
+        let mut bench = bench();
+        assert_trip(bench.time_to_trip(90.0, RATED_FREQUENCY), 7.0);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Undervoltage));
+        assert!(bench.is_field_on());
+        assert!(!bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn overfrequency_trips_after_its_delay_and_takes_the_field_down() {
+        let mut bench = bench();
+        assert_trip(bench.time_to_trip(RATED_VOLTAGE, 440.0), 1.0);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Overfrequency));
+        assert!(!bench.is_field_on());
+        assert!(!bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn underfrequency_trips_after_its_delay_and_keeps_the_field() {
+        let mut bench = bench();
+        assert_trip(bench.time_to_trip(RATED_VOLTAGE, 365.0), 1.0);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Underfrequency));
+        assert!(bench.is_field_on());
+        assert!(!bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn underspeed_drops_power_ready_without_latching() {
+        let mut bench = bench();
+        assert_eq!(bench.time_to_trip(80.0, 300.0), None);
+        assert!(!bench.gcu.is_power_ready());
+        assert!(!bench.is_line_contactor_commanded());
+
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 1.0);
+        assert!(bench.gcu.is_power_ready());
+        assert!(bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn field_stays_off_until_the_gen_switch_is_cycled() {
+        let mut bench = bench();
+        bench.time_to_trip(130.0, RATED_FREQUENCY);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Overvoltage));
+
+        // Back in limits, the fault stays latched and the field off
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 5.0);
+        assert_eq!(bench.gcu.latched_fault(), Some(GcuFault::Overvoltage));
+        assert!(!bench.is_field_on());
+        assert!(!bench.is_line_contactor_commanded());
+
+        // Switching ON again without OFF first does not reset
+        bench.gcu.set_generator_switch(true);
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 1.0);
+        assert!(!bench.is_field_on());
+
+        bench.gcu.set_generator_switch(false);
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 1.0);
+        bench.gcu.set_generator_switch(true);
+        bench.run(RATED_VOLTAGE, RATED_FREQUENCY, 1.0);
+        assert_eq!(bench.gcu.latched_fault(), None);
+        assert!(bench.is_field_on());
+        assert!(bench.gcu.is_power_ready());
+        assert!(bench.is_line_contactor_commanded());
+    }
+
+    #[test]
+    fn idg_below_its_governing_range_is_an_underspeed() {
+        let mut system = ElectricalSystem::new();
+        let generator = Generator::new(
+            NUM_POLES,
+            90000.0,
+            RATED_VOLTAGE,
+            RATED_FREQUENCY,
+            0.95,
+            0.05,
+            0.0,
+            3,
+        );
+        let mut idg = IntegratedDriveGenerator::new("IDG", generator, 56.0);
+        idg.set_engine_n2(Ratio::new::<percent>(50.0));
+        let idg = system.add_component("IDG", idg).unwrap();
+        let contactor = system
+            .add_component("GLC", Contactor::new("GLC", 18.0, 12.0, 0.05))
+            .unwrap();
+        let mut gcu = GeneratorControlUnit::new("GCU", idg, contactor, 261.0);
+        gcu.set_generator_switch(true);
+
+        // The frequency follows N2 down past the underfrequency pickup, nothing latches
+        for _ in 0..100 {
+            system.get_mut(idg).unwrap().update(TIME_STEP);
+            gcu.update(TIME_STEP, &mut system);
+        }
+        let rpm = system.get(idg).unwrap().output_rpm();
+        assert!(rpm.get::<revolution_per_minute>() < 370.0 * 60.0 / NUM_POLES);
+        assert_eq!(gcu.latched_fault(), None);
+        assert!(!gcu.is_power_ready());
+    }
+}
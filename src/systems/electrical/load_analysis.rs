@@ -0,0 +1,584 @@
+// Electrical load analysis (ELA).
+// Flies the aircraft through the phases of a data file without the GUI and reads the network once each
+// phase has settled: the load on every bus, source and converter in W, VA and A, the margin left to the
+// rating of the sources and converters, and the total the sources deliver in each phase. Converters are
+// fed from the sources so they stay out of the total. The report ends with the phase that loads each
+// bus, source and converter most.
+// The network is the single line equivalent and only solves real power, the apparent power comes from the
+// power factor of the loads.
+
+use crate::systems::E170Systems;
+use crate::systems::electrical::ElectricalSystem;
+use crate::systems::electrical::components::ac::air_driven_generator::AirDrivenGenerator;
+use crate::systems::electrical::components::ac::generator::Generator;
+use crate::systems::electrical::components::ac::generic_ac_component::GenericAcComponent;
+use crate::systems::electrical::components::ac::integrated_drive_generator::IntegratedDriveGenerator;
+use crate::systems::electrical::components::ac::static_inverter::StaticInverter;
+use crate::systems::electrical::components::dc::battery::Battery;
+use crate::systems::electrical::components::dc::generic_dc_component::GenericDcComponent;
+use crate::systems::electrical::components::dc::transformer_rectifier_unit::TransformerRectifierUnit;
+use crate::systems::electrical::components::shared::bus::Bus;
+use crate::systems::electrical::components::shared::motor::Motor;
+
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::f64::*;
+use uom::si::power::watt;
+
+const TIME_STEP: f32 = 0.1; // Seconds
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlightPhase {
+    pub name: String,
+    pub duration: f64, // Seconds, long enough for the network to settle
+    #[serde(default)]
+    pub engine_n2: [f64; 2], // Percent
+    #[serde(default = "generators_on")]
+    pub generators: [bool; 2],
+    #[serde(default)]
+    pub apu: bool,
+    #[serde(default)]
+    pub ground_power: bool,
+    #[serde(default)]
+    pub airspeed: f64, // Knots
+    #[serde(default)]
+    pub loads_on: Vec<String>,
+    #[serde(default)]
+    pub loads_off: Vec<String>,
+}
+
+fn generators_on() -> [bool; 2] {
+    [true, true]
+}
+
+#[derive(Deserialize)]
+struct PhaseFile {
+    #[serde(default)]
+    phase: Vec<FlightPhase>,
+}
+
+#[derive(Debug)]
+pub enum LoadAnalysisError {
+    Io { path: String, message: String },
+    Syntax { message: String },
+    NoPhases,
+    UnknownLoad { phase: String, load: String },
+}
+
+impl fmt::Display for LoadAnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadAnalysisError::Io { path, message } => write!(f, "cannot read {path}: {message}"),
+            LoadAnalysisError::Syntax { message } => write!(f, "invalid flight phases: {message}"),
+            LoadAnalysisError::NoPhases => write!(f, "no flight phases to analyse"),
+            LoadAnalysisError::UnknownLoad { phase, load } => {
+                write!(f, "phase \"{phase}\" switches unknown load \"{load}\"")
+            }
+        }
+    }
+}
+
+pub fn load_phases(path: impl AsRef<Path>) -> Result<Vec<FlightPhase>, Vec<LoadAnalysisError>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|error| {
+        vec![LoadAnalysisError::Io {
+            path: path.display().to_string(),
+            message: error.to_string(),
+        }]
+    })?;
+    parse_phases(&source)
+}
+
+pub fn parse_phases(source: &str) -> Result<Vec<FlightPhase>, Vec<LoadAnalysisError>> {
+    let file: PhaseFile = toml::from_str(source).map_err(|error| {
+        vec![LoadAnalysisError::Syntax {
+            message: error.to_string(),
+        }]
+    })?;
+    if file.phase.is_empty() {
+        return Err(vec![LoadAnalysisError::NoPhases]);
+    }
+    Ok(file.phase)
+}
+
+pub struct LoadReading {
+    pub name: String,
+    pub voltage: ElectricPotential,
+    pub current: ElectricCurrent,
+    pub power: Power,
+    pub apparent_power: Power, // VA
+    // Sources and converters, a battery at its one hour rate. Ground power has none.
+    pub rated_power: Option<Power>,
+}
+
+impl LoadReading {
+    // Share of the rated power still available, negative when overloaded
+    pub fn margin(&self) -> Option<f64> {
+        let rated_power = self.rated_power?;
+        Some(1.0 - (self.power / rated_power).value)
+    }
+}
+
+pub struct PhaseLoads {
+    pub phase: String,
+    pub buses: Vec<LoadReading>,
+    pub sources: Vec<LoadReading>,
+    pub converters: Vec<LoadReading>,
+}
+
+impl PhaseLoads {
+    // Delivered by the sources in this phase
+    pub fn total_power(&self) -> Power {
+        self.sources.iter().map(|reading| reading.power).sum()
+    }
+
+    pub fn total_apparent_power(&self) -> Power {
+        self.sources
+            .iter()
+            .map(|reading| reading.apparent_power)
+            .sum()
+    }
+}
+
+pub struct LoadAnalysis {
+    pub phases: Vec<PhaseLoads>,
+}
+
+impl LoadAnalysis {
+    // Runs the phases in order on one aircraft, each phase starts from where the last one left off
+    pub fn run(
+        systems: &mut E170Systems,
+        phases: &[FlightPhase],
+    ) -> Result<Self, Vec<LoadAnalysisError>> {
+        let errors: Vec<_> = phases
+            .iter()
+            .flat_map(|phase| {
+                phase
+                    .loads_on
+                    .iter()
+                    .chain(&phase.loads_off)
+                    .filter(|load| {
+                        let system = systems.electrical_system();
+                        system.handle::<GenericDcComponent>(load).is_none()
+                            && system.handle::<GenericAcComponent>(load).is_none()
+                            && system.handle::<Motor>(load).is_none()
+                    })
+                    .map(|load| LoadAnalysisError::UnknownLoad {
+                        phase: phase.name.clone(),
+                        load: load.clone(),
+                    })
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut results = Vec::new();
+        for phase in phases {
+            for (engine, (n2, generator)) in
+                phase.engine_n2.iter().zip(phase.generators).enumerate()
+            {
+                systems.set_engine_n2(engine + 1, *n2);
+                systems.set_generator_switch(engine + 1, generator);
+            }
+            systems.set_apu_running(phase.apu);
+            systems.set_ground_power_connected(phase.ground_power);
+            systems.set_airspeed(phase.airspeed);
+            for load in &phase.loads_on {
+                systems.set_load_power(load, true);
+            }
+            for load in &phase.loads_off {
+                systems.set_load_power(load, false);
+            }
+
+            let mut elapsed = 0.0;
+            while elapsed < phase.duration {
+                systems.update(TIME_STEP);
+                elapsed += TIME_STEP as f64;
+            }
+
+            let system = systems.electrical_system();
+            results.push(PhaseLoads {
+                phase: phase.name.clone(),
+                buses: system.bus_loads(),
+                sources: system.source_loads(),
+                converters: system.converter_loads(),
+            });
+        }
+        Ok(LoadAnalysis { phases: results })
+    }
+
+    // Phase with the highest load on `name`, for a rated source or converter the one with the smallest
+    // margin, its rating can change from phase to phase
+    pub fn worst_case(&self, name: &str) -> Option<(&str, &LoadReading)> {
+        self.phases
+            .iter()
+            .filter_map(|phase| {
+                phase
+                    .buses
+                    .iter()
+                    .chain(&phase.sources)
+                    .chain(&phase.converters)
+                    .find(|reading| reading.name == name)
+                    .map(|reading| (phase.phase.as_str(), reading))
+            })
+            .reduce(|worst, next| {
+                let is_worse = match (next.1.margin(), worst.1.margin()) {
+                    (Some(next_margin), Some(worst_margin)) => next_margin < worst_margin,
+                    _ => next.1.power > worst.1.power,
+                };
+                if is_worse { next } else { worst }
+            })
+    }
+}
+
+impl fmt::Display for LoadAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ELECTRICAL LOAD ANALYSIS")?;
+        for phase in &self.phases {
+            writeln!(f, "\n== {} ==", phase.phase)?;
+            write_table(f, "Bus", &phase.buses)?;
+            write_table(f, "Source", &phase.sources)?;
+            writeln!(
+                f,
+                "{:<20} {:>8} {:>8} {:>9.0} {:>9.0}",
+                "Total",
+                "",
+                "",
+                phase.total_power().get::<watt>(),
+                phase.total_apparent_power().get::<watt>()
+            )?;
+            write_table(f, "Converter", &phase.converters)?;
+        }
+
+        writeln!(f, "\n== Worst case ==")?;
+        let Some(first) = self.phases.first() else {
+            return Ok(());
+        };
+        for reading in first
+            .buses
+            .iter()
+            .chain(&first.sources)
+            .chain(&first.converters)
+        {
+            let Some((phase, worst)) = self.worst_case(&reading.name) else {
+                continue;
+            };
+            write!(
+                f,
+                "{:<20} {:>9.0} W {:>9.0} VA  in {phase}",
+                worst.name,
+                worst.power.get::<watt>(),
+                worst.apparent_power.get::<watt>()
+            )?;
+            if let Some(margin) = worst.margin() {
+                write!(f, ", {:.1} % margin", margin * 100.0)?;
+                if margin < 0.0 {
+                    write!(f, " ⚠️ OVERLOADED")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_table(f: &mut fmt::Formatter<'_>, title: &str, readings: &[LoadReading]) -> fmt::Result {
+    writeln!(
+        f,
+        "{title:<20} {:>8} {:>8} {:>9} {:>9} {:>9} {:>8}",
+        "V", "A", "W", "VA", "Rated W", "Margin"
+    )?;
+    for reading in readings {
+        let (rated, margin) = match (reading.rated_power, reading.margin()) {
+            (Some(rated), Some(margin)) => (
+                format!("{:.0}", rated.get::<watt>()),
+                format!("{:.1} %", margin * 100.0),
+            ),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+        writeln!(
+            f,
+            "{:<20} {:>8.1} {:>8.1} {:>9.0} {:>9.0} {rated:>9} {margin:>8}",
+            reading.name,
+            reading.voltage.get::<volt>(),
+            reading.current.get::<ampere>(),
+            reading.power.get::<watt>(),
+            reading.apparent_power.get::<watt>(),
+        )?;
+    }
+    Ok(())
+}
+
+impl ElectricalSystem {
+    pub fn bus_loads(&self) -> Vec<LoadReading> {
+        self.sorted_nodes()
+            .into_iter()
+            .filter(|node| self.handle_at::<Bus>(*node).is_some())
+            .map(|node| self.load_reading(node, false))
+            .collect()
+    }
+
+    pub fn source_loads(&self) -> Vec<LoadReading> {
+        self.sorted_nodes()
+            .into_iter()
+            .filter(|node| {
+                self.components
+                    .get(node)
+                    .is_some_and(|component| component.is_source())
+                    && !self.is_converter(*node)
+            })
+            .map(|node| self.load_reading(node, true))
+            .collect()
+    }
+
+    pub fn converter_loads(&self) -> Vec<LoadReading> {
+        self.sorted_nodes()
+            .into_iter()
+            .filter(|node| self.is_converter(*node))
+            .map(|node| self.load_reading(node, true))
+            .collect()
+    }
+
+    fn is_converter(&self, node: NodeIndex) -> bool {
+        self.handle_at::<TransformerRectifierUnit>(node).is_some()
+            || self.handle_at::<StaticInverter>(node).is_some()
+    }
+
+    fn sorted_nodes(&self) -> Vec<NodeIndex> {
+        let mut nodes: Vec<_> = self.component_nodes().collect();
+        nodes.sort();
+        nodes
+    }
+
+    // What `node` delivers to the components downstream of it
+    fn load_reading(&self, node: NodeIndex, is_source: bool) -> LoadReading {
+        // Converters sit with their input on the node, what they deliver is at their output
+        let voltage = self
+            .components
+            .get(&node)
+            .map(|component| component.get_output_voltage())
+            .unwrap_or_default();
+        let current = self
+            .graph
+            .neighbors_directed(node, Direction::Outgoing)
+            .filter_map(|next| self.get_current(node, next))
+            .fold(ElectricCurrent::new::<ampere>(0.0), |sum, current| {
+                sum + current
+            })
+            .max(ElectricCurrent::new::<ampere>(0.0));
+        let power = voltage * current;
+        // Only AC carries reactive power, on DC the VA are the W
+        let is_ac = self
+            .get_frequency(node)
+            .is_some_and(|frequency| frequency.value > 0.0);
+        let power_factor = if is_ac {
+            self.power_factor_downstream(node, is_source)
+        } else {
+            1.0
+        };
+
+        LoadReading {
+            name: self.component_name(node).unwrap_or("?").to_string(),
+            voltage,
+            current,
+            power,
+            apparent_power: power / power_factor,
+            rated_power: self.rated_power(node),
+        }
+    }
+
+    // Power factor of the loads fed from `node`. A bus is only charged with the loads it feeds itself,
+    // a source with everything up to the next converter, where the reactive power stops.
+    fn power_factor_downstream(&self, node: NodeIndex, through_buses: bool) -> f64 {
+        let mut visited = vec![node];
+        let mut pending = vec![node];
+        let (mut real, mut apparent) = (0.0, 0.0);
+        while let Some(current) = pending.pop() {
+            for next in self.graph.neighbors_directed(current, Direction::Outgoing) {
+                if visited.contains(&next) {
+                    continue;
+                }
+                visited.push(next);
+
+                // The power factor of a DC load is its efficiency, it draws no reactive power
+                if let Some(load) = self
+                    .handle_at::<GenericDcComponent>(next)
+                    .and_then(|load| self.get(load))
+                {
+                    let power = load.get_actual_power().get::<watt>();
+                    real += power;
+                    apparent += power;
+                    continue;
+                }
+                if let Some(load) = self
+                    .handle_at::<GenericAcComponent>(next)
+                    .and_then(|load| self.get(load))
+                {
+                    real += load.real_power().get::<watt>();
+                    apparent += load.apparent_power().get::<watt>();
+                    continue;
+                }
+                if let Some(motor) = self
+                    .handle_at::<Motor>(next)
+                    .and_then(|motor| self.get(motor))
+                {
+                    real += motor.real_power().get::<watt>();
+                    apparent += motor.apparent_power().get::<watt>();
+                    continue;
+                }
+                let Some(component) = self.components.get(&next) else {
+                    continue;
+                };
+                let stops = component.is_source()
+                    || (!through_buses && self.handle_at::<Bus>(next).is_some());
+                if !stops {
+                    pending.push(next);
+                }
+            }
+        }
+
+        if real > 0.0 { real / apparent } else { 1.0 }
+    }
+
+    fn rated_power(&self, node: NodeIndex) -> Option<Power> {
+        if let Some(idg) = self.handle_at::<IntegratedDriveGenerator>(node) {
+            return Some(self.get(idg)?.generator().rated_power());
+        }
+        if let Some(generator) = self.handle_at::<Generator>(node) {
+            return Some(self.get(generator)?.rated_power());
+        }
+        if let Some(tru) = self.handle_at::<TransformerRectifierUnit>(node) {
+            return Some(self.get(tru)?.converter().rated_power());
+        }
+        if let Some(inverter) = self.handle_at::<StaticInverter>(node) {
+            return Some(self.get(inverter)?.converter().rated_power());
+        }
+        if let Some(battery) = self.handle_at::<Battery>(node) {
+            return Some(self.get(battery)?.rated_power());
+        }
+        Some(
+            self.get(self.handle_at::<AirDrivenGenerator>(node)?)?
+                .rated_power(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on_ground_power(name: &str) -> FlightPhase {
+        FlightPhase {
+            name: name.to_string(),
+            duration: 20.0,
+            engine_n2: [0.0, 0.0],
+            generators: [false, false],
+            apu: false,
+            ground_power: true,
+            airspeed: 0.0,
+            loads_on: vec!["Galley".to_string()],
+            loads_off: Vec::new(),
+        }
+    }
+
+    fn reading<'a>(readings: &'a [LoadReading], name: &str) -> &'a LoadReading {
+        readings
+            .iter()
+            .find(|reading| reading.name == name)
+            .unwrap_or_else(|| panic!("no reading for {name}"))
+    }
+
+    #[test]
+    fn phase_total_is_what_the_sources_deliver() {
+        let mut systems = E170Systems::new().unwrap();
+        let with_galley = on_ground_power("galley on");
+        let mut without_galley = on_ground_power("galley off");
+        without_galley.loads_on.clear();
+        without_galley.loads_off.push("Galley".to_string());
+
+        let analysis = LoadAnalysis::run(&mut systems, &[with_galley]).unwrap();
+        let galley = systems
+            .electrical_system()
+            .handle::<GenericAcComponent>("Galley")
+            .and_then(|galley| systems.electrical_system().get(galley))
+            .unwrap()
+            .real_power();
+        let analysis_off = LoadAnalysis::run(&mut systems, &[without_galley]).unwrap();
+        let (on, off) = (&analysis.phases[0], &analysis_off.phases[0]);
+
+        // The ground power cart carries everything, the TRUs it feeds are not counted again
+        let gpu = reading(&on.sources, "GPU");
+        assert_eq!(on.total_power(), gpu.power);
+        assert_eq!(on.total_apparent_power(), gpu.apparent_power);
+        assert!(reading(&on.converters, "TRU 1").power.get::<watt>() > 0.0);
+        assert!(on.sources.iter().all(|reading| reading.name != "TRU 1"));
+
+        let shed = (on.total_power() - off.total_power()).get::<watt>();
+        let galley = galley.get::<watt>();
+        assert!(galley > 4000.0, "{galley}");
+        assert!(
+            (shed - galley).abs() < 0.05 * galley,
+            "{shed} W less for a {galley} W galley"
+        );
+    }
+
+    #[test]
+    fn converters_and_batteries_have_a_rating() {
+        let mut systems = E170Systems::new().unwrap();
+        let analysis = LoadAnalysis::run(&mut systems, &[on_ground_power("ground")]).unwrap();
+        let phase = &analysis.phases[0];
+
+        // 28 V at the 100 A current limit, 20 cells of 1.2 V at 44 A for the hour
+        let tru = reading(&phase.converters, "TRU 1");
+        assert_eq!(tru.rated_power, Some(Power::new::<watt>(2800.0)));
+        assert!((tru.margin().unwrap() - (1.0 - tru.power.get::<watt>() / 2800.0)).abs() < 1e-9);
+        let battery = reading(&phase.sources, "Battery 1");
+        assert!((battery.rated_power.unwrap().get::<watt>() - 1056.0).abs() < 1e-9);
+        assert!(reading(&phase.sources, "GPU").rated_power.is_none());
+    }
+
+    #[test]
+    fn worst_case_of_a_source_is_its_smallest_margin() {
+        let source = |power: f64, rated_power: Option<f64>| LoadReading {
+            name: "AC Source".to_string(),
+            voltage: ElectricPotential::new::<volt>(115.0),
+            current: ElectricCurrent::new::<ampere>(power / 115.0),
+            power: Power::new::<watt>(power),
+            apparent_power: Power::new::<watt>(power),
+            rated_power: rated_power.map(Power::new::<watt>),
+        };
+        let phase = |name: &str, reading: LoadReading| PhaseLoads {
+            phase: name.to_string(),
+            buses: Vec::new(),
+            sources: vec![reading],
+            converters: Vec::new(),
+        };
+
+        // More power on the IDG, but the ADG has less to spare
+        let analysis = LoadAnalysis {
+            phases: vec![
+                phase("cruise", source(20000.0, Some(40000.0))),
+                phase("emergency", source(12000.0, Some(15000.0))),
+            ],
+        };
+        let (worst, reading) = analysis.worst_case("AC Source").unwrap();
+        assert_eq!(worst, "emergency");
+        assert!((reading.margin().unwrap() - 0.2).abs() < 1e-9);
+
+        // Without a rating the highest load is the worst
+        let analysis = LoadAnalysis {
+            phases: vec![
+                phase("taxi", source(8000.0, None)),
+                phase("boarding", source(5000.0, None)),
+            ],
+        };
+        assert_eq!(analysis.worst_case("AC Source").unwrap().0, "taxi");
+    }
+}
@@ -1,14 +1,31 @@
+pub mod breaker_coordination;
+pub mod bus_power_control_unit;
+pub mod circuit_breaker_database;
 pub mod components;
+pub mod energy;
+pub mod fault;
+pub mod generator_control_unit;
+pub mod load_analysis;
+pub mod network_export;
+pub mod network_loader;
+pub mod secondary_power_distribution;
+pub mod solver;
+pub mod wire;
 
-use petgraph::algo::toposort;
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::IntoNeighbors;
+use energy::EnergyLedger;
+use fault::{ActiveFault, Fault, FaultError, FaultId};
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use petgraph::stable_graph::StableDiGraph;
+use petgraph::visit::EdgeRef;
+use solver::{CircuitModel, NetworkSolution, NodalNetwork};
 use std::any::Any;
 use std::collections::HashMap;
-use std::path::Component;
+use std::fmt;
+use std::marker::PhantomData;
 use uom::si::electric_current::ampere;
-use uom::si::electrical_resistance::ohm;
 use uom::si::f64::*;
+use wire::Wire;
 
 pub trait ElectricalComponent: Any {
     fn as_any(&self) -> &dyn Any;
@@ -30,6 +47,28 @@ pub trait ElectricalComponent: Any {
         self.get_output_current()
     }
     fn set_input_current(&mut self, current: ElectricCurrent);
+    // Voltage solved on the output side of two terminal components
+    fn set_output_voltage(&mut self, _voltage: ElectricPotential) {}
+    // AC sources report the frequency they feed, the network passes it on to everything they power
+    fn get_output_frequency(&self) -> Option<Frequency> {
+        None
+    }
+    fn set_input_frequency(&mut self, _frequency: Frequency) {}
+    // How the component looks to the network solver for the next solve
+    fn circuit_model(&self) -> CircuitModel;
+    // Short type name for reports and exports
+    fn component_type(&self) -> &'static str {
+        let path = std::any::type_name::<Self>();
+        path.rsplit("::").next().unwrap_or(path)
+    }
+    // Heat lost inside the component that the network does not see (mechanical and magnetic losses)
+    fn heat_loss(&self) -> Power {
+        Power::default()
+    }
+    // Feeds the network, converters count with their output side
+    fn is_source(&self) -> bool {
+        false
+    }
 }
 
 pub trait ElectricalComponentExt {
@@ -47,117 +86,862 @@ impl ElectricalComponentExt for Box<dyn ElectricalComponent> {
     }
 }
 
+// Typed reference to a component of the network. Resolving it checks the type again, so a handle can
+// only ever give back the component type it was created for, and a handle to a removed component
+// resolves to nothing.
+pub struct ComponentHandle<T> {
+    node: NodeIndex,
+    generation: u64,
+    component_type: PhantomData<fn() -> T>,
+}
+
+impl<T> ComponentHandle<T> {
+    fn new(node: NodeIndex, generation: u64) -> Self {
+        ComponentHandle {
+            node,
+            generation,
+            component_type: PhantomData,
+        }
+    }
+
+    pub fn node(&self) -> NodeIndex {
+        self.node
+    }
+}
+
+impl<T> Clone for ComponentHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ComponentHandle<T> {}
+
+impl<T> PartialEq for ComponentHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node && self.generation == other.generation
+    }
+}
+
+impl<T> std::fmt::Debug for ComponentHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ComponentHandle({}, generation {})",
+            self.node.index(),
+            self.generation
+        )
+    }
+}
+
+// A component the network is asked about or edited at, by its bare index or by a handle. Either only
+// resolves while its component is in the network. The index of a removed component is never handed out
+// again, so a stale one is refused rather than taken for a component added since.
+pub trait ComponentRef {
+    fn resolve(&self, system: &ElectricalSystem) -> Option<NodeIndex>;
+}
+
+impl ComponentRef for NodeIndex {
+    fn resolve(&self, system: &ElectricalSystem) -> Option<NodeIndex> {
+        system.components.contains_key(self).then_some(*self)
+    }
+}
+
+impl<T> ComponentRef for ComponentHandle<T> {
+    fn resolve(&self, system: &ElectricalSystem) -> Option<NodeIndex> {
+        system.is_current(*self).then_some(self.node)
+    }
+}
+
+// Component names are unique, `handle` and `node` find components by them
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DuplicateName(pub String);
+
+impl fmt::Display for DuplicateName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "component name \"{}\" is already taken", self.0)
+    }
+}
+
 pub struct ElectricalSystem {
-    pub(crate) graph: DiGraph<String, ()>,
-    pub(crate) components: HashMap<NodeIndex, Box<dyn ElectricalComponent>>,
+    // Stable so removing a wire leaves the indices untouched, removed components stay as unwired nodes
+    graph: StableDiGraph<String, ()>,
+    components: HashMap<NodeIndex, Box<dyn ElectricalComponent>>,
+    // Which component added so far sits at each node, see `ComponentHandle`
+    generations: HashMap<NodeIndex, u64>,
+    next_generation: u64,
+    names: HashMap<String, NodeIndex>,
     node_voltage: HashMap<NodeIndex, ElectricPotential>,
-    pub(crate) edge_current: HashMap<(NodeIndex, NodeIndex), ElectricCurrent>,
-    wire_resistance: HashMap<(NodeIndex, NodeIndex), ElectricalResistance>,
+    node_frequency: HashMap<NodeIndex, Frequency>,
+    edge_current: HashMap<(NodeIndex, NodeIndex), ElectricCurrent>,
+    wires: HashMap<(NodeIndex, NodeIndex), Wire>,
+    faults: Vec<ActiveFault>,
+    next_fault_id: usize,
+    energy: EnergyLedger,
 }
 
 impl ElectricalSystem {
     pub fn new() -> Self {
         ElectricalSystem {
-            graph: DiGraph::new(),
+            graph: StableDiGraph::new(),
             components: HashMap::new(),
+            generations: HashMap::new(),
+            next_generation: 0,
+            names: HashMap::new(),
             node_voltage: HashMap::new(),
+            node_frequency: HashMap::new(),
             edge_current: HashMap::new(),
-            wire_resistance: HashMap::new(),
+            wires: HashMap::new(),
+            faults: Vec::new(),
+            next_fault_id: 0,
+            energy: EnergyLedger::default(),
         }
     }
+    // Refused if the name is taken, the component already there keeps it
     pub fn add_component<C: ElectricalComponent + 'static>(
         &mut self,
         name: &str,
         component: C,
-    ) -> NodeIndex {
+    ) -> Result<ComponentHandle<C>, DuplicateName> {
+        if self.names.contains_key(name) {
+            return Err(DuplicateName(name.to_string()));
+        }
         let node = self.graph.add_node(name.to_string());
         self.components.insert(node, Box::new(component));
-        node
+        self.names.insert(name.to_string(), node);
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        self.generations.insert(node, generation);
+        Ok(ComponentHandle::new(node, generation))
+    }
+
+    fn is_current<T>(&self, handle: ComponentHandle<T>) -> bool {
+        self.generations.get(&handle.node) == Some(&handle.generation)
+    }
+
+    pub fn get<T: Any>(&self, handle: ComponentHandle<T>) -> Option<&T> {
+        if !self.is_current(handle) {
+            return None;
+        }
+        self.components.get(&handle.node)?.downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: Any>(&mut self, handle: ComponentHandle<T>) -> Option<&mut T> {
+        if !self.is_current(handle) {
+            return None;
+        }
+        self.components.get_mut(&handle.node)?.downcast_mut::<T>()
+    }
+
+    // Handle to the named component, only if it is a `T`
+    pub fn handle<T: Any>(&self, name: &str) -> Option<ComponentHandle<T>> {
+        self.handle_at(*self.names.get(name)?)
+    }
+
+    // Handle to the component at `node`, only if it is a `T`
+    pub fn handle_at<T: Any>(&self, node: NodeIndex) -> Option<ComponentHandle<T>> {
+        self.components
+            .get(&node)?
+            .downcast_ref::<T>()
+            .map(|_| ComponentHandle::new(node, self.generations[&node]))
+    }
+
+    pub fn node(&self, name: &str) -> Option<NodeIndex> {
+        self.names.get(name).copied()
+    }
+
+    pub fn component_name(&self, node: NodeIndex) -> Option<&str> {
+        if !self.components.contains_key(&node) {
+            return None;
+        }
+        self.graph.node_weight(node).map(String::as_str)
     }
 
-    pub fn connect_with_wire(&mut self, from: NodeIndex, to: NodeIndex, resistance: f64) {
-        self.graph.add_edge(from, to, ());
+    // Two components are joined by one wire at most, connecting them again in either direction replaces the
+    // wire between them. Refused if either end is not in the network or both ends are the same component.
+    pub fn connect_with_wire(
+        &mut self,
+        from: impl ComponentRef,
+        to: impl ComponentRef,
+        wire: Wire,
+    ) -> bool {
+        let (Some(from), Some(to)) = (from.resolve(self), to.resolve(self)) else {
+            return false;
+        };
+        if from == to {
+            return false;
+        }
+        if self.wires.contains_key(&(to, from)) {
+            self.disconnect(to, from);
+        }
+        if self.graph.find_edge(from, to).is_none() {
+            self.graph.add_edge(from, to, ());
+        }
         self.edge_current
             .insert((from, to), ElectricCurrent::new::<ampere>(0.0));
-        self.wire_resistance
-            .insert((from, to), ElectricalResistance::new::<ohm>(resistance));
+        self.wires.insert((from, to), wire);
+        true
+    }
+
+    pub fn connect_no_resistance(
+        &mut self,
+        from: impl ComponentRef,
+        to: impl ComponentRef,
+    ) -> bool {
+        self.connect_with_wire(from, to, Wire::fixed(0.001))
+    }
+
+    // Takes the component out of the network together with every wire connected to it. The node stays
+    // in the graph unwired, so its index is not handed to the next component added.
+    pub fn remove_component(
+        &mut self,
+        node: impl ComponentRef,
+    ) -> Option<Box<dyn ElectricalComponent>> {
+        let node = node.resolve(self)?;
+        let edges: Vec<_> = self
+            .graph
+            .edges_directed(node, Direction::Outgoing)
+            .chain(self.graph.edges_directed(node, Direction::Incoming))
+            .map(|edge| edge.id())
+            .collect();
+        for edge in edges {
+            self.graph.remove_edge(edge);
+        }
+        if let Some(name) = self.graph.node_weight(node)
+            && self.names.get(name) == Some(&node)
+        {
+            self.names.remove(name);
+        }
+        self.node_voltage.remove(&node);
+        self.node_frequency.remove(&node);
+        self.edge_current
+            .retain(|(from, to), _| *from != node && *to != node);
+        self.wires
+            .retain(|(from, to), _| *from != node && *to != node);
+        self.faults.retain(|active| !active.fault().involves(node));
+        self.energy.forget(node);
+        self.generations.remove(&node);
+        self.components.remove(&node)
+    }
+
+    // Cuts the wire between two components, returns false if they were not connected
+    pub fn disconnect(&mut self, from: impl ComponentRef, to: impl ComponentRef) -> bool {
+        let (Some(from), Some(to)) = (from.resolve(self), to.resolve(self)) else {
+            return false;
+        };
+        let mut removed = false;
+        while let Some(edge) = self.graph.find_edge(from, to) {
+            self.graph.remove_edge(edge);
+            removed = true;
+        }
+        self.edge_current.remove(&(from, to));
+        self.wires.remove(&(from, to));
+        self.faults
+            .retain(|active| active.fault().wire() != Some((from, to)));
+        removed
+    }
+
+    // Moves an existing wire to new end points, the wire keeps its gauge, length and temperature. Refused if
+    // the new end points are already wired together or are the same component.
+    pub fn rewire(
+        &mut self,
+        from: impl ComponentRef,
+        to: impl ComponentRef,
+        new_from: impl ComponentRef,
+        new_to: impl ComponentRef,
+    ) -> bool {
+        let (Some(from), Some(to)) = (from.resolve(self), to.resolve(self)) else {
+            return false;
+        };
+        let (Some(new_from), Some(new_to)) = (new_from.resolve(self), new_to.resolve(self)) else {
+            return false;
+        };
+        if new_from == new_to
+            || self.graph.find_edge(new_from, new_to).is_some()
+            || ((new_to, new_from) != (from, to) && self.graph.find_edge(new_to, new_from).is_some())
+        {
+            return false;
+        }
+        let Some(wire) = self.wires.get(&(from, to)).cloned() else {
+            return false;
+        };
+        self.disconnect(from, to);
+        self.connect_with_wire(new_from, new_to, wire);
+        true
+    }
+
+    // Refused if the faulted component or wire is not in the network
+    pub fn inject_fault(&mut self, fault: Fault) -> Result<FaultId, FaultError> {
+        if let Fault::ShortToGround { node, .. } = fault
+            && !self.components.contains_key(&node)
+        {
+            return Err(FaultError::UnknownNode(node));
+        }
+        if let Some((from, to)) = fault.wire()
+            && !self.wires.contains_key(&(from, to))
+        {
+            return Err(FaultError::UnknownWire { from, to });
+        }
+        if let Some(resistance) = fault.resistance()
+            && resistance.value < 0.0
+        {
+            return Err(FaultError::NegativeResistance(resistance));
+        }
+        let id = FaultId(self.next_fault_id);
+        self.next_fault_id += 1;
+        self.faults.push(ActiveFault::new(id, fault));
+        Ok(id)
+    }
+
+    pub fn clear_fault(&mut self, id: FaultId) -> bool {
+        let count = self.faults.len();
+        self.faults.retain(|active| active.id() != id);
+        self.faults.len() != count
+    }
+
+    pub fn clear_faults(&mut self) {
+        self.faults.clear();
+    }
+
+    pub fn faults(&self) -> &[ActiveFault] {
+        &self.faults
+    }
+
+    pub fn fault(&self, id: FaultId) -> Option<&ActiveFault> {
+        self.faults.iter().find(|active| active.id() == id)
+    }
+
+    pub fn describe_fault(&self, fault: &Fault) -> String {
+        let name = |node| self.component_name(node).unwrap_or("?");
+        match fault {
+            Fault::ShortToGround { node, resistance } => {
+                format!(
+                    "{} shorted to ground ({:.3} Ohm)",
+                    name(*node),
+                    resistance.value
+                )
+            }
+            Fault::OpenWire { from, to } => format!("{} → {} open", name(*from), name(*to)),
+            Fault::Intermittent { from, to, .. } => {
+                format!("{} → {} intermittent", name(*from), name(*to))
+            }
+            Fault::HighResistance {
+                from,
+                to,
+                resistance,
+            } => format!(
+                "{} → {} high resistance ({:.3} Ohm)",
+                name(*from),
+                name(*to),
+                resistance.value
+            ),
+        }
+    }
+
+    pub fn component_nodes(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph
+            .node_indices()
+            .filter(|node| self.components.contains_key(node))
     }
 
-    pub fn connect_no_resistance(&mut self, from: NodeIndex, to: NodeIndex) {
-        self.connect_with_wire(from, to, 0.001);
+    pub fn wires(&self) -> impl Iterator<Item = (NodeIndex, NodeIndex, &Wire)> + '_ {
+        self.graph.edge_indices().filter_map(|edge| {
+            let (from, to) = self.graph.edge_endpoints(edge)?;
+            let wire = self.wires.get(&(from, to))?;
+            Some((from, to, wire))
+        })
     }
 
-    pub fn calculate_current_flow(&mut self) {
+    pub fn wire(&self, from: impl ComponentRef, to: impl ComponentRef) -> Option<&Wire> {
+        self.wires.get(&(from.resolve(self)?, to.resolve(self)?))
+    }
+
+    pub fn wire_mut(
+        &mut self,
+        from: impl ComponentRef,
+        to: impl ComponentRef,
+    ) -> Option<&mut Wire> {
+        let wire = (from.resolve(self)?, to.resolve(self)?);
+        self.wires.get_mut(&wire)
+    }
+
+    pub fn solve_network(&self) -> NetworkSolution {
+        self.nodal_network(None).solve()
+    }
+
+    // Current the component at `node` would carry if it were `model` instead, e.g. a breaker about to be
+    // pushed in. The network itself is left as it is.
+    pub fn prospective_current(
+        &self,
+        node: impl ComponentRef,
+        model: CircuitModel,
+    ) -> ElectricCurrent {
+        let Some(node) = node.resolve(self) else {
+            return ElectricCurrent::default();
+        };
+        self.nodal_network(Some((node, model)))
+            .solve()
+            .component_current
+            .get(&node)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn nodal_network(&self, mut replaced: Option<(NodeIndex, CircuitModel)>) -> NodalNetwork {
+        let mut network = NodalNetwork::default();
+        for node in self.graph.node_indices() {
+            if let Some(component) = self.components.get(&node) {
+                let model = match replaced.take_if(|(replaced, _)| *replaced == node) {
+                    Some((_, model)) => model,
+                    None => component.circuit_model(),
+                };
+                network.add_component(node, model);
+            }
+        }
         for edge in self.graph.edge_indices() {
-            if let Some((from, to)) = self.graph.edge_endpoints(edge) {
-                if let (Some(from_comp), Some(to_comp)) =
-                    (self.components.get(&from), self.components.get(&to))
-                {
-                    let v_from = from_comp.get_output_voltage();
-                    let v_to = to_comp.get_output_voltage();
-                    let voltage_diff = v_from - v_to;
-
-                    if let Some(resistance) = self.wire_resistance.get(&(from, to)) {
-                        let current = if resistance.value > 0.0 {
-                            ElectricCurrent::new::<ampere>(voltage_diff.value / resistance.value)
-                        } else {
-                            ElectricCurrent::new::<ampere>(0.0)
-                        };
-
-                        self.edge_current.insert((from, to), current);
-                    }
-                }
+            if let Some((from, to)) = self.graph.edge_endpoints(edge)
+                && let Some(wire) = self.wires.get(&(from, to))
+            {
+                let fault_resistance: ElectricalResistance = self
+                    .faults
+                    .iter()
+                    .filter(|active| active.fault().wire() == Some((from, to)))
+                    .map(ActiveFault::series_resistance)
+                    .sum();
+                network.add_wire(from, to, wire.resistance() + fault_resistance);
+            }
+        }
+        // Shunts go in the order of the faults, `update_system` reads their current back the same way
+        for active in &self.faults {
+            if let Fault::ShortToGround { node, resistance } = active.fault() {
+                network.add_shunt(*node, *resistance);
             }
         }
+        network
     }
 
     pub fn update_system(&mut self, dt: f32) {
-        if let Ok(sorted_nodes) = toposort(&self.graph, None) {
-            for node in &sorted_nodes {
-                if let Some(component) = self.components.get_mut(node) {
-                    component.update(dt);
-                    let output_voltage = component.get_output_voltage();
-                    self.node_voltage.insert(*node, output_voltage);
-                }
+        for node in self.graph.node_indices() {
+            if let Some(component) = self.components.get_mut(&node) {
+                component.update(dt);
+            }
+        }
+
+        for active in &mut self.faults {
+            active.update(dt);
+        }
+
+        let solution = self.solve_network();
+        let shunts: Vec<_> = self
+            .faults
+            .iter()
+            .filter_map(|active| match active.fault() {
+                Fault::ShortToGround { node, resistance } => Some((*node, *resistance)),
+                _ => None,
+            })
+            .collect();
+        let was_conserved = self.energy.balance().is_conserved();
+        self.energy.record(&self.components, &shunts, &solution, dt);
+        // Only when the balance breaks, the ledger counts the solves it stays off for
+        let balance = self.energy.balance();
+        if was_conserved && !balance.is_conserved() {
+            eprintln!(
+                "⚠️ ENERGY BALANCE off by {:.1} W ({:.1} W generated, {:.1} W consumed, {:.1} W dissipated)",
+                balance.imbalance().value,
+                balance.generated.value,
+                balance.consumed.value,
+                balance.dissipated.value
+            );
+        }
+
+        for (node, component) in self.components.iter_mut() {
+            let voltage = solution
+                .input_voltage
+                .get(node)
+                .copied()
+                .unwrap_or_default();
+            let current = solution
+                .component_current
+                .get(node)
+                .copied()
+                .unwrap_or_default();
+
+            component.set_input_voltage(voltage);
+            if let Some(output_voltage) = solution.output_voltage.get(node) {
+                component.set_output_voltage(*output_voltage);
             }
+            component.set_input_current(current);
+            component.set_input_power(voltage * current);
+            self.node_voltage.insert(*node, voltage);
+        }
+
+        self.propagate_frequency();
+
+        let mut shunt_current = solution.shunt_current.iter();
+        for active in &mut self.faults {
+            let current = match active.fault().wire() {
+                Some(wire) => solution.wire_current.get(&wire).copied(),
+                None => shunt_current.next().copied(),
+            };
+            active.set_current(current.unwrap_or_default());
+        }
+
+        self.edge_current.extend(solution.wire_current);
+        for (edge, wire) in self.wires.iter_mut() {
+            let current = self.edge_current.get(edge).copied().unwrap_or_default();
+            wire.update(dt, current);
+        }
+    }
 
-            self.calculate_current_flow();
+    pub fn energy(&self) -> &EnergyLedger {
+        &self.energy
+    }
 
-            for node in sorted_nodes {
-                if let Some(component) = self.components.get_mut(&node) {
-                    let output_voltage = component.get_output_voltage();
-                    let output_power = component.get_output_power();
+    pub fn get_frequency(&self, node: impl ComponentRef) -> Option<Frequency> {
+        self.node_frequency.get(&node.resolve(self)?).copied()
+    }
 
-                    for neighbor in self.graph.neighbors(node) {
-                        if let Some(neighbor_component) = self.components.get_mut(&neighbor) {
-                            neighbor_component.set_input_voltage(output_voltage);
-                            neighbor_component.set_input_power(output_power);
+    // Walks from every running AC source down through everything that conducts. Converters take the
+    // frequency in but do not pass it on, an inverter reports its own.
+    fn propagate_frequency(&mut self) {
+        self.node_frequency.clear();
+        let mut pending: Vec<(NodeIndex, Frequency)> = self
+            .components
+            .iter()
+            .filter_map(|(node, component)| {
+                let frequency = component.get_output_frequency()?;
+                let running = frequency.value > 0.0
+                    && !matches!(component.circuit_model(), CircuitModel::Open);
+                running.then_some((*node, frequency))
+            })
+            .collect();
+        for (node, frequency) in &pending {
+            self.node_frequency.insert(*node, *frequency);
+        }
 
-                            if let Some(current) = self.edge_current.get(&(node, neighbor)) {
-                                neighbor_component.set_input_current(*current);
-                            }
-                        }
-                    }
+        while let Some((node, frequency)) = pending.pop() {
+            let next: Vec<_> = self
+                .graph
+                .neighbors_directed(node, petgraph::Direction::Outgoing)
+                .collect();
+            for next in next {
+                if self.node_frequency.contains_key(&next) {
+                    continue;
+                }
+                self.node_frequency.insert(next, frequency);
+                let passes_on = self.components.get(&next).is_some_and(|component| {
+                    matches!(
+                        component.circuit_model(),
+                        CircuitModel::Junction | CircuitModel::Series { .. }
+                    )
+                });
+                if passes_on {
+                    pending.push((next, frequency));
                 }
             }
         }
+
+        for (node, component) in self.components.iter_mut() {
+            let frequency = self.node_frequency.get(node).copied().unwrap_or_default();
+            component.set_input_frequency(frequency);
+        }
+    }
+
+    pub fn get_voltage(&self, node: impl ComponentRef) -> Option<ElectricPotential> {
+        self.node_voltage.get(&node.resolve(self)?).copied()
     }
 
-    pub fn get_current(&self, from: NodeIndex, to: NodeIndex) -> Option<ElectricCurrent> {
-        self.edge_current.get(&(from, to)).copied()
+    pub fn get_current(
+        &self,
+        from: impl ComponentRef,
+        to: impl ComponentRef,
+    ) -> Option<ElectricCurrent> {
+        self.edge_current
+            .get(&(from.resolve(self)?, to.resolve(self)?))
+            .copied()
     }
 
+    // Wires heated over their rating by the current they carry as (from, to, current, ampacity)
     pub fn check_overcurrent(
         &self,
-        max_current: f64,
-    ) -> Vec<(NodeIndex, NodeIndex, ElectricCurrent)> {
+    ) -> Vec<(NodeIndex, NodeIndex, ElectricCurrent, ElectricCurrent)> {
         let mut overcurrents = Vec::new();
 
-        for ((from, to), current) in &self.edge_current {
-            if current.value > max_current {
-                overcurrents.push((*from, *to, *current));
+        for ((from, to), wire) in &self.wires {
+            if let Some(ampacity) = wire.ampacity()
+                && wire.is_overcurrent()
+            {
+                overcurrents.push((*from, *to, wire.current(), ampacity));
             }
         }
         overcurrents
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::dc::battery::Battery;
+    use components::shared::bus::Bus;
+    use components::shared::circuit_breaker::{CircuitBreaker, TripBand};
+    use uom::si::electric_potential::volt;
+    use uom::si::power::watt;
+
+    fn bus() -> Bus {
+        Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        }
+    }
+
+    #[test]
+    fn duplicate_name_is_refused() {
+        let mut system = ElectricalSystem::new();
+        let first = system.add_component("DC Bus 1", bus()).unwrap();
+
+        assert_eq!(
+            system.add_component("DC Bus 1", bus()),
+            Err(DuplicateName("DC Bus 1".to_string()))
+        );
+        assert_eq!(system.handle::<Bus>("DC Bus 1"), Some(first));
+        assert_eq!(system.component_nodes().count(), 1);
+    }
+
+    #[test]
+    fn handle_to_a_removed_component_resolves_to_nothing() {
+        let mut system = ElectricalSystem::new();
+        let removed = system.add_component("DC Bus 1", bus()).unwrap();
+        assert!(system.remove_component(removed).is_some());
+        assert!(system.get(removed).is_none());
+        assert!(system.handle::<Bus>("DC Bus 1").is_none());
+
+        // The next component gets an index of its own, the old handle must not find it
+        let added = system.add_component("DC Bus 2", bus()).unwrap();
+        assert_ne!(added.node(), removed.node());
+        assert!(system.get(removed).is_none());
+        assert!(system.get_mut(removed).is_none());
+        assert!(system.get(added).is_some());
+    }
+
+    #[test]
+    fn stale_handle_cannot_edit_a_component_added_since() {
+        let mut system = ElectricalSystem::new();
+        let bus_1 = system.add_component("DC Bus 1", bus()).unwrap();
+        let removed = system.add_component("DC Bus 2", bus()).unwrap();
+        system.remove_component(removed).unwrap();
+        let added = system.add_component("DC Bus 3", bus()).unwrap();
+        system.connect_with_wire(bus_1, added, Wire::fixed(0.01));
+
+        assert!(system.remove_component(removed).is_none());
+        assert!(!system.disconnect(bus_1, removed));
+        assert!(!system.connect_with_wire(removed, bus_1, Wire::fixed(0.01)));
+        assert!(!system.rewire(bus_1, removed, added, bus_1));
+        assert!(system.wire(bus_1, removed).is_none());
+        assert!(system.get_current(bus_1, removed).is_none());
+
+        // Nor can its bare index
+        let stale = removed.node();
+        assert!(system.remove_component(stale).is_none());
+        assert!(!system.disconnect(bus_1, stale));
+        assert!(!system.connect_with_wire(stale, bus_1, Wire::fixed(0.01)));
+        assert!(!system.rewire(bus_1, added, bus_1, stale));
+        assert!(system.get_voltage(stale).is_none());
+        assert!(system.component_name(stale).is_none());
+        assert_eq!(system.component_nodes().count(), 2);
+
+        // The component that took the index is untouched
+        assert!(system.get(added).is_some());
+        assert!(system.wire(bus_1, added).is_some());
+        assert!(system.wire(added, bus_1).is_none());
+        assert!(system.remove_component(added).is_some());
+    }
+
+    #[test]
+    fn connecting_to_a_removed_component_is_refused() {
+        let mut system = ElectricalSystem::new();
+        let bus_1 = system.add_component("DC Bus 1", bus()).unwrap();
+        let removed = system.add_component("DC Bus 2", bus()).unwrap();
+        let node = removed.node();
+        system.remove_component(removed).unwrap();
+
+        assert!(!system.connect_with_wire(bus_1, node, Wire::fixed(0.01)));
+        assert!(!system.connect_no_resistance(node, bus_1));
+        assert!(!system.connect_with_wire(bus_1, removed, Wire::fixed(0.01)));
+        assert!(system.wire(bus_1, node).is_none());
+        // The network still solves
+        system.update_system(0.1);
+    }
+
+    #[test]
+    fn wire_back_to_the_same_component_is_refused() {
+        let mut system = ElectricalSystem::new();
+        let bus_1 = system.add_component("DC Bus 1", bus()).unwrap();
+
+        assert!(!system.connect_with_wire(bus_1, bus_1, Wire::fixed(0.01)));
+        assert!(system.wire(bus_1, bus_1).is_none());
+
+        // Nor can an existing wire be moved onto one component
+        let bus_2 = system.add_component("DC Bus 2", bus()).unwrap();
+        system.connect_with_wire(bus_1, bus_2, Wire::fixed(0.01));
+        assert!(!system.rewire(bus_1, bus_2, bus_2, bus_2));
+        assert!(system.wire(bus_1, bus_2).is_some());
+    }
+
+    #[test]
+    fn wiring_the_other_way_replaces_the_wire() {
+        let mut system = ElectricalSystem::new();
+        let a = system.add_component("A", bus()).unwrap();
+        let b = system.add_component("B", bus()).unwrap();
+        let c = system.add_component("C", bus()).unwrap();
+        system.connect_with_wire(a, b, Wire::fixed(0.01));
+
+        assert!(system.connect_with_wire(b, a, Wire::fixed(0.02)));
+        assert!(system.wire(a, b).is_none());
+        assert_eq!(system.wire(b, a).unwrap().resistance().value, 0.02);
+        assert_eq!(system.wires().count(), 1);
+        assert_eq!(system.graph.edge_count(), 1);
+
+        // Nor can a wire be moved onto end points wired the other way
+        system.connect_with_wire(c, a, Wire::fixed(0.03));
+        assert!(!system.rewire(b, a, a, c));
+        assert!(system.wire(b, a).is_some());
+        // A wire can still be turned round
+        assert!(system.rewire(b, a, a, b));
+        assert_eq!(system.wires().count(), 2);
+    }
+
+    #[test]
+    fn disconnect_cuts_only_the_given_wire() {
+        let mut system = ElectricalSystem::new();
+        let a = system.add_component("A", bus()).unwrap();
+        let b = system.add_component("B", bus()).unwrap();
+        let c = system.add_component("C", bus()).unwrap();
+        system.connect_with_wire(a, b, Wire::fixed(0.01));
+        system.connect_with_wire(b, c, Wire::fixed(0.02));
+
+        assert!(system.disconnect(a, b));
+        assert!(system.wire(a, b).is_none());
+        assert!(system.get_current(a, b).is_none());
+        assert!(system.wire(b, c).is_some());
+        // Nothing left to cut
+        assert!(!system.disconnect(a, b));
+    }
+
+    #[test]
+    fn rewire_moves_the_wire_and_keeps_it() {
+        let mut system = ElectricalSystem::new();
+        let a = system.add_component("A", bus()).unwrap();
+        let b = system.add_component("B", bus()).unwrap();
+        let c = system.add_component("C", bus()).unwrap();
+        system.connect_with_wire(a, b, Wire::fixed(0.05));
+
+        assert!(system.rewire(a, b, a, c));
+        assert!(system.wire(a, b).is_none());
+        assert_eq!(system.wire(a, c).unwrap().resistance().value, 0.05);
+
+        // Refused onto end points that are already wired, and for a wire that does not exist
+        system.connect_with_wire(b, c, Wire::fixed(0.01));
+        assert!(!system.rewire(a, c, b, c));
+        assert!(system.wire(a, c).is_some());
+        assert!(!system.rewire(a, b, b, a));
+    }
+
+    #[test]
+    fn fault_on_something_not_in_the_network_is_refused() {
+        let mut system = ElectricalSystem::new();
+        let bus_1 = system.add_component("DC Bus 1", bus()).unwrap().node();
+        let bus_2 = system.add_component("DC Bus 2", bus()).unwrap().node();
+        let removed = system.add_component("DC Bus 3", bus()).unwrap().node();
+        system.remove_component(removed);
+
+        assert_eq!(
+            system.inject_fault(Fault::short_to_ground(removed, 0.001)),
+            Err(FaultError::UnknownNode(removed))
+        );
+        assert_eq!(
+            system.inject_fault(Fault::open_wire(bus_1, bus_2)),
+            Err(FaultError::UnknownWire {
+                from: bus_1,
+                to: bus_2
+            })
+        );
+        system.connect_no_resistance(bus_1, bus_2);
+        // Wires run one way, the fault has to name them the same way round
+        assert!(system.inject_fault(Fault::open_wire(bus_2, bus_1)).is_err());
+        assert!(matches!(
+            system.inject_fault(Fault::high_resistance(bus_1, bus_2, -0.5)),
+            Err(FaultError::NegativeResistance(_))
+        ));
+        assert!(system.inject_fault(Fault::open_wire(bus_1, bus_2)).is_ok());
+        assert_eq!(system.faults().len(), 1);
+    }
+
+    #[test]
+    fn short_downstream_of_a_breaker_trips_it() {
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("BATT", 24.0, 44.0, 0.02, 0.9);
+        let battery = system.add_component("BATT", battery).unwrap();
+        let breaker = CircuitBreaker::new("CB", 10.0, TripBand::Fast, false, 0.0);
+        let breaker = system.add_component("CB", breaker).unwrap();
+        let bus = system.add_component("BUS", bus()).unwrap();
+        system.connect_no_resistance(battery, breaker);
+        system.connect_with_wire(breaker, bus, Wire::fixed(0.01));
+
+        let short = system
+            .inject_fault(Fault::short_to_ground(bus.node(), 0.05))
+            .unwrap();
+        system.update_system(0.01);
+        // Limited by the battery, the wire and the short
+        let fault_current = system.fault(short).unwrap().current().get::<ampere>();
+        assert!(fault_current > 200.0, "{fault_current}");
+
+        let mut elapsed = 0.0;
+        while !system.get(breaker).unwrap().is_tripped() {
+            system.update_system(0.01);
+            elapsed += 0.01;
+            assert!(elapsed < 1.0, "still closed after {elapsed:.2} s");
+        }
+        system.update_system(0.01);
+        assert_eq!(system.fault(short).unwrap().current().get::<ampere>(), 0.0);
+        assert!(system.get_voltage(bus).unwrap().get::<volt>() < 0.1);
+    }
+
+    #[test]
+    fn intermittent_fault_makes_and_breaks_the_wire() {
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("BATT", 24.0, 44.0, 0.02, 0.9);
+        let battery = system.add_component("BATT", battery).unwrap();
+        let bus = system.add_component("BUS", bus()).unwrap();
+        system.connect_no_resistance(battery, bus);
+
+        // Makes contact for 1 s, then open for 0.5 s
+        let fault = system
+            .inject_fault(Fault::intermittent(battery.node(), bus.node(), 0.5, 1.0))
+            .unwrap();
+        let mut open_ticks = 0;
+        for tick in 1..=30 {
+            system.update_system(0.1);
+            let is_open = system.fault(fault).unwrap().is_open();
+            let voltage = system.get_voltage(bus).unwrap().get::<volt>();
+            if is_open {
+                open_ticks += 1;
+                assert!(voltage < 0.1, "{voltage} V on the bus with the wire open");
+            } else {
+                assert!(voltage > 20.0, "{voltage} V on the bus with the wire made");
+            }
+            // Opens after 1 s of contact and makes again 0.5 s later
+            if tick == 9 || tick == 15 {
+                assert!(!is_open, "open at tick {tick}");
+            }
+            if tick == 10 || tick == 14 {
+                assert!(is_open, "made at tick {tick}");
+            }
+        }
+        // Two open periods of 5 ticks in 3 s
+        assert_eq!(open_ticks, 10);
+
+        assert!(system.clear_fault(fault));
+        system.update_system(0.1);
+        assert!(system.get_voltage(bus).unwrap().get::<volt>() > 20.0);
+    }
+}
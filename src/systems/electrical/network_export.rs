@@ -0,0 +1,381 @@
+// Snapshot of the live network for wiring diagrams and for diffing two runs.
+// The snapshot lists every component with its type, voltage, current and switching state and every wire
+// with its current, it renders as Graphviz DOT or as JSON. Both come out in node order so two snapshots
+// of the same network line up. A snapshot saved as JSON reads back in, `diff` then lists what changed
+// between two runs, matching components and wires by name.
+
+use crate::systems::electrical::ElectricalSystem;
+use crate::systems::electrical::components::shared::circuit_breaker::CircuitBreaker;
+use crate::systems::electrical::components::shared::contactor::Contactor;
+use crate::systems::electrical::components::shared::solid_state_power_controller::SolidStatePowerController;
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+// Differences smaller than these are the same state in two runs
+const VOLTAGE_TOLERANCE: f64 = 0.1; // Volt
+const CURRENT_TOLERANCE: f64 = 0.1; // Ampere
+
+#[derive(Serialize, Deserialize)]
+pub struct NetworkSnapshot {
+    pub components: Vec<ComponentSnapshot>,
+    pub wires: Vec<WireSnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComponentSnapshot {
+    pub index: usize,
+    pub name: String,
+    pub component_type: String,
+    pub voltage: f64, // Volt
+    pub current: f64, // Ampere
+    // Breakers, SSPCs and contactors only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WireSnapshot {
+    pub from: String,
+    pub to: String,
+    // Node indices of `from` and `to`, what the DOT edges are drawn between
+    pub from_index: usize,
+    pub to_index: usize,
+    pub resistance: f64, // Ohm
+    pub current: f64,    // Ampere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ampacity: Option<f64>, // Ampere
+    pub temperature: f64, // °C
+}
+
+impl ElectricalSystem {
+    pub fn snapshot(&self) -> NetworkSnapshot {
+        let mut nodes: Vec<_> = self.component_nodes().collect();
+        nodes.sort();
+
+        let components = nodes
+            .iter()
+            .filter_map(|node| {
+                let component = self.components.get(node)?;
+                Some(ComponentSnapshot {
+                    index: node.index(),
+                    name: self.component_name(*node)?.to_string(),
+                    component_type: component.component_type().to_string(),
+                    voltage: self.get_voltage(*node).unwrap_or_default().get::<volt>(),
+                    current: component.get_input_current().get::<ampere>(),
+                    state: self.switching_state(*node),
+                })
+            })
+            .collect();
+
+        let mut wires: Vec<_> = self.wires().collect();
+        wires.sort_by_key(|(from, to, _)| (*from, *to));
+        let wires = wires
+            .into_iter()
+            .map(|(from, to, wire)| WireSnapshot {
+                from: self.component_name(from).unwrap_or("?").to_string(),
+                to: self.component_name(to).unwrap_or("?").to_string(),
+                from_index: from.index(),
+                to_index: to.index(),
+                resistance: wire.resistance().get::<ohm>(),
+                current: self
+                    .get_current(from, to)
+                    .unwrap_or_default()
+                    .get::<ampere>(),
+                ampacity: wire.ampacity().map(|ampacity| ampacity.get::<ampere>()),
+                temperature: wire.temperature().get::<degree_celsius>(),
+            })
+            .collect();
+
+        NetworkSnapshot { components, wires }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.snapshot()).unwrap_or_default()
+    }
+
+    pub fn to_dot(&self) -> String {
+        self.snapshot().to_dot()
+    }
+
+    fn switching_state(&self, node: petgraph::graph::NodeIndex) -> Option<String> {
+        if let Some(breaker) = self.handle_at::<CircuitBreaker>(node) {
+            return Some(format!("{:?}", self.get(breaker)?.state()));
+        }
+        if let Some(sspc) = self.handle_at::<SolidStatePowerController>(node) {
+            return Some(format!("{:?}", self.get(sspc)?.state()));
+        }
+        let contactor = self.get(self.handle_at::<Contactor>(node)?)?;
+        Some(
+            if contactor.is_closed() {
+                "Closed"
+            } else {
+                "Open"
+            }
+            .to_string(),
+        )
+    }
+}
+
+impl NetworkSnapshot {
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph electrical {\n    rankdir=LR;\n    node [shape=box];\n");
+        for component in &self.components {
+            let state = component
+                .state
+                .as_ref()
+                .map(|state| format!("\\n{state}"))
+                .unwrap_or_default();
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}\\n{}\\n{:.1} V {:.1} A{}\"];",
+                component.index,
+                escape(&component.name),
+                component.component_type,
+                component.voltage,
+                component.current,
+                state
+            );
+        }
+        for wire in &self.wires {
+            // Wires over their ampacity stand out in red
+            let colour = match wire.ampacity {
+                Some(ampacity) if wire.current.abs() > ampacity => ", color=red",
+                _ => "",
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} -> n{} [label=\"{:.2} A\"{colour}];",
+                wire.from_index, wire.to_index, wire.current
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    // What changed from this snapshot to `after`
+    pub fn diff(&self, after: &NetworkSnapshot) -> NetworkDiff {
+        let mut changes = Vec::new();
+        for before in &self.components {
+            let Some(now) = after
+                .components
+                .iter()
+                .find(|component| component.name == before.name)
+            else {
+                changes.push(format!("- {} ({})", before.name, before.component_type));
+                continue;
+            };
+            if before.state != now.state {
+                changes.push(format!(
+                    "  {}: {} → {}",
+                    before.name,
+                    before.state.as_deref().unwrap_or("-"),
+                    now.state.as_deref().unwrap_or("-")
+                ));
+            }
+            if (before.voltage - now.voltage).abs() > VOLTAGE_TOLERANCE {
+                changes.push(format!(
+                    "  {}: {:.1} V → {:.1} V",
+                    before.name, before.voltage, now.voltage
+                ));
+            }
+            if (before.current - now.current).abs() > CURRENT_TOLERANCE {
+                changes.push(format!(
+                    "  {}: {:.1} A → {:.1} A",
+                    before.name, before.current, now.current
+                ));
+            }
+        }
+        for now in &after.components {
+            if !self
+                .components
+                .iter()
+                .any(|component| component.name == now.name)
+            {
+                changes.push(format!("+ {} ({})", now.name, now.component_type));
+            }
+        }
+
+        let same_wire = |a: &WireSnapshot, b: &WireSnapshot| a.from == b.from && a.to == b.to;
+        for before in &self.wires {
+            match after.wires.iter().find(|now| same_wire(before, now)) {
+                None => changes.push(format!("- {} → {}", before.from, before.to)),
+                Some(now) if (before.current - now.current).abs() > CURRENT_TOLERANCE => changes
+                    .push(format!(
+                        "  {} → {}: {:.2} A → {:.2} A",
+                        before.from, before.to, before.current, now.current
+                    )),
+                Some(_) => {}
+            }
+        }
+        for now in &after.wires {
+            if !self.wires.iter().any(|before| same_wire(before, now)) {
+                changes.push(format!("+ {} → {}", now.from, now.to));
+            }
+        }
+
+        NetworkDiff { changes }
+    }
+}
+
+pub struct NetworkDiff {
+    // One line per change, `-` removed, `+` added, the rest changed
+    pub changes: Vec<String>,
+}
+
+impl NetworkDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for NetworkDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "NETWORK DIFF")?;
+        if self.changes.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::wire::Wire;
+    use petgraph::graph::NodeIndex;
+    use uom::si::f64::*;
+    use uom::si::power::watt;
+
+    fn bus() -> Bus {
+        Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        }
+    }
+
+    // Battery through a contactor to a bus, with a spare bus added and removed first so the node
+    // indices have a hole in them
+    fn network() -> (ElectricalSystem, NodeIndex) {
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("BATT", 24.0, 44.0, 0.02, 0.9);
+        let battery = system.add_component("BATT", battery).unwrap();
+        let spare = system.add_component("SPARE", bus()).unwrap();
+        let contactor = Contactor::new("BATT \"K1\"", 18.0, 12.0, 0.0);
+        let contactor = system.add_component("BATT \"K1\"", contactor).unwrap();
+        let bus = system.add_component("DC BUS", bus()).unwrap();
+        system.connect_no_resistance(battery, contactor);
+        system.connect_with_wire(contactor, bus, Wire::fixed(0.01));
+        system.remove_component(spare);
+        let coil = system.get_mut(contactor).unwrap();
+        coil.set_coil_voltage(ElectricPotential::new::<volt>(28.0));
+        coil.command(true);
+        system.update_system(0.1);
+        system.update_system(0.1);
+        (system, contactor.node())
+    }
+
+    #[test]
+    fn dot_edges_run_between_node_indices() {
+        let (system, contactor) = network();
+        let dot = system.to_dot();
+
+        assert!(dot.starts_with("digraph electrical {"));
+        assert!(dot.ends_with("}\n"));
+        // Index 1 went with the removed bus
+        assert!(dot.contains("    n0 [label=\"BATT\\nBattery\\n"));
+        assert!(!dot.contains("    n1 "));
+        assert!(dot.contains("    n2 [label=\"BATT \\\"K1\\\"\\nContactor\\n"));
+        assert!(dot.contains("\\nClosed\"];"));
+        assert_eq!(contactor.index(), 2);
+        assert!(dot.contains("    n0 -> n2 [label="));
+        assert!(dot.contains("    n2 -> n3 [label="));
+        assert_eq!(dot.matches(" -> ").count(), 2);
+    }
+
+    #[test]
+    fn json_reads_back_the_same_snapshot() {
+        let (system, _) = network();
+        let snapshot = NetworkSnapshot::from_json(&system.to_json()).unwrap();
+
+        let names: Vec<_> = snapshot
+            .components
+            .iter()
+            .map(|component| (component.index, component.name.as_str()))
+            .collect();
+        assert_eq!(names, [(0, "BATT"), (2, "BATT \"K1\""), (3, "DC BUS")]);
+        assert_eq!(snapshot.components[1].state.as_deref(), Some("Closed"));
+        assert_eq!(snapshot.components[2].state, None);
+        assert!(snapshot.components[2].voltage > 20.0);
+
+        let wire = &snapshot.wires[1];
+        assert_eq!((wire.from_index, wire.to_index), (2, 3));
+        assert_eq!(
+            (wire.from.as_str(), wire.to.as_str()),
+            ("BATT \"K1\"", "DC BUS")
+        );
+        assert_eq!(wire.resistance, 0.01);
+        assert!(snapshot.diff(&system.snapshot()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_what_changed_between_runs() {
+        let (mut system, contactor) = network();
+        let before = system.snapshot();
+
+        let contactor = system.handle_at::<Contactor>(contactor).unwrap();
+        system.get_mut(contactor).unwrap().command(false);
+        let dc_bus = system.node("DC BUS").unwrap();
+        let light = system.add_component("LIGHT BUS", bus()).unwrap();
+        system.connect_no_resistance(dc_bus, light);
+        for _ in 0..5 {
+            system.update_system(0.1);
+        }
+        let diff = before.diff(&system.snapshot());
+
+        assert!(
+            diff.changes
+                .contains(&"  BATT \"K1\": Closed → Open".to_string()),
+            "{diff}"
+        );
+        assert!(
+            diff.changes
+                .iter()
+                .any(|change| change.starts_with("  DC BUS: ") && change.ends_with("→ 0.0 V")),
+            "{diff}"
+        );
+        assert!(
+            diff.changes.contains(&"+ LIGHT BUS (Bus)".to_string()),
+            "{diff}"
+        );
+        assert!(
+            diff.changes.contains(&"+ DC BUS → LIGHT BUS".to_string()),
+            "{diff}"
+        );
+        assert!(
+            system
+                .snapshot()
+                .diff(&before)
+                .changes
+                .contains(&"- LIGHT BUS (Bus)".to_string())
+        );
+    }
+}
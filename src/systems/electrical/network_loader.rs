@@ -0,0 +1,1125 @@
+// Builds an `ElectricalSystem` from a declarative network file instead of hard-coding it.
+// The file is TOML with one `[[component]]` table per component (a unique `name`, a `type` and the
+// parameters of that type) and one `[[wire]]` table per connection, described by its gauge and length
+// or by a fixed resistance. Every problem in the file is collected and reported with the entry it
+// comes from instead of stopping at the first one.
+
+use crate::systems::electrical::components::{
+    ac::air_driven_generator::AirDrivenGenerator,
+    ac::generator::Generator,
+    ac::generic_ac_component::{FrequencyResponse, GenericAcComponent, PhaseConnection},
+    ac::ground_power::GroundPower,
+    ac::integrated_drive_generator::IntegratedDriveGenerator,
+    ac::static_inverter::StaticInverter,
+    dc::battery::Battery,
+    dc::diode::Diode,
+    dc::generic_dc_component::{GenericDcComponent, VoltageResponse},
+    dc::transformer_rectifier_unit::TransformerRectifierUnit,
+    shared::bus::Bus,
+    shared::circuit_breaker::{CircuitBreaker, TripBand},
+    shared::contactor::Contactor,
+    shared::motor::{Motor, MotorSupply},
+    shared::solid_state_power_controller::SolidStatePowerController,
+};
+use crate::systems::electrical::wire::{Wire, WireMaterial};
+use crate::systems::electrical::{ElectricalComponent, ElectricalSystem};
+
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::fmt;
+use uom::si::electric_potential::volt;
+use uom::si::f64::*;
+use uom::si::power::watt;
+use uom::si::torque::newton_meter;
+
+#[derive(Debug)]
+pub enum NetworkLoadError {
+    Syntax {
+        message: String,
+    },
+    MissingName {
+        index: usize,
+    },
+    MissingType {
+        name: String,
+    },
+    UnknownComponentType {
+        name: String,
+        component_type: String,
+    },
+    InvalidParameters {
+        name: String,
+        message: String,
+    },
+    DuplicateName {
+        name: String,
+    },
+    DanglingWire {
+        from: String,
+        to: String,
+        missing: String,
+    },
+    InvalidWire {
+        from: String,
+        to: String,
+        message: String,
+    },
+    DuplicateWire {
+        from: String,
+        to: String,
+    },
+    WireToItself {
+        name: String,
+    },
+}
+
+impl fmt::Display for NetworkLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkLoadError::Syntax { message } => write!(f, "invalid network file: {message}"),
+            NetworkLoadError::MissingName { index } => {
+                write!(f, "component #{} has no name", index + 1)
+            }
+            NetworkLoadError::MissingType { name } => {
+                write!(f, "component \"{name}\" has no type")
+            }
+            NetworkLoadError::UnknownComponentType {
+                name,
+                component_type,
+            } => write!(
+                f,
+                "component \"{name}\" has unknown type \"{component_type}\""
+            ),
+            NetworkLoadError::InvalidParameters { name, message } => {
+                write!(f, "component \"{name}\": {message}")
+            }
+            NetworkLoadError::DuplicateName { name } => {
+                write!(f, "component name \"{name}\" is used more than once")
+            }
+            NetworkLoadError::DanglingWire { from, to, missing } => write!(
+                f,
+                "wire \"{from}\" -> \"{to}\" references unknown component \"{missing}\""
+            ),
+            NetworkLoadError::InvalidWire { from, to, message } => {
+                write!(f, "wire \"{from}\" -> \"{to}\": {message}")
+            }
+            NetworkLoadError::DuplicateWire { from, to } => {
+                write!(f, "wire \"{from}\" -> \"{to}\" is declared more than once")
+            }
+            NetworkLoadError::WireToItself { name } => {
+                write!(
+                    f,
+                    "wire \"{name}\" -> \"{name}\" connects a component to itself"
+                )
+            }
+        }
+    }
+}
+
+// Range checks once the parameters of a component are read, a message for the first one that fails
+trait Definition: DeserializeOwned {
+    fn check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn positive(parameter: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "{parameter} {value} must be a finite positive number"
+        ))
+    }
+}
+
+fn not_negative(parameter: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "{parameter} {value} must be finite and not negative"
+        ))
+    }
+}
+
+fn fraction(parameter: &str, value: f64) -> Result<(), String> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{parameter} {value} must be between 0 and 1"))
+    }
+}
+
+fn above(parameter: &str, value: f64, lower: &str, lower_value: f64) -> Result<(), String> {
+    if value > lower_value {
+        Ok(())
+    } else {
+        Err(format!(
+            "{parameter} {value} must be above {lower} {lower_value}"
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct NetworkFile {
+    #[serde(default)]
+    component: Vec<toml::Table>,
+    #[serde(default)]
+    wire: Vec<WireDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WireDefinition {
+    from: String,
+    to: String,
+    // AWG number and length in meter, 1/0 to 4/0 are 0 to -3
+    gauge: Option<i8>,
+    length: Option<f64>,
+    #[serde(default = "default_material")]
+    material: WireMaterial,
+    #[serde(default = "default_bundle_size")]
+    bundle_size: u32,
+    // Fixed resistance instead of a gauge, all of it left out for bus bars and short links
+    resistance: Option<f64>,
+}
+
+fn default_material() -> WireMaterial {
+    WireMaterial::Copper
+}
+
+fn default_bundle_size() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BusDefinition {}
+
+impl Definition for BusDefinition {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GeneratorDefinition {
+    num_poles: f64,
+    rated_power: f64,
+    rated_voltage: f64,
+    rated_frequency: f64,
+    efficiency: f64,
+    internal_resistance: f64,
+    // Seconds, time constant of the rotor following its drive, none when left out
+    #[serde(default)]
+    spin_up_time: f64,
+    phase_count: u8,
+}
+
+impl Definition for GeneratorDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("num_poles", self.num_poles)?;
+        positive("rated_power", self.rated_power)?;
+        positive("rated_voltage", self.rated_voltage)?;
+        positive("rated_frequency", self.rated_frequency)?;
+        positive("efficiency", self.efficiency)?;
+        fraction("efficiency", self.efficiency)?;
+        not_negative("internal_resistance", self.internal_resistance)?;
+        not_negative("spin_up_time", self.spin_up_time)?;
+        if !matches!(self.phase_count, 1 | 3) {
+            return Err(format!("phase_count {} must be 1 or 3", self.phase_count));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IntegratedDriveGeneratorDefinition {
+    underspeed_n2: f64,
+    generator: GeneratorDefinition,
+}
+
+impl Definition for IntegratedDriveGeneratorDefinition {
+    fn check(&self) -> Result<(), String> {
+        not_negative("underspeed_n2", self.underspeed_n2)?;
+        self.generator.check()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AirDrivenGeneratorDefinition {
+    rated_power: f64,
+    rated_voltage: f64,
+    internal_resistance: f64,
+    min_airspeed: f64,
+    rated_airspeed: f64,
+    deployment_time: f64,
+}
+
+impl Definition for AirDrivenGeneratorDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("rated_power", self.rated_power)?;
+        positive("rated_voltage", self.rated_voltage)?;
+        not_negative("internal_resistance", self.internal_resistance)?;
+        not_negative("min_airspeed", self.min_airspeed)?;
+        above(
+            "rated_airspeed",
+            self.rated_airspeed,
+            "min_airspeed",
+            self.min_airspeed,
+        )?;
+        not_negative("deployment_time", self.deployment_time)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroundPowerDefinition {
+    voltage: f64,
+    frequency: f64,
+    internal_resistance: f64,
+    #[serde(default)]
+    connected: bool,
+    #[serde(default)]
+    selected: bool,
+}
+
+impl Definition for GroundPowerDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("voltage", self.voltage)?;
+        positive("frequency", self.frequency)?;
+        not_negative("internal_resistance", self.internal_resistance)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConverterDefinition {
+    output_voltage: f64,
+    rated_current: f64,
+    efficiency: f64,
+    output_resistance: f64,
+}
+
+impl Definition for ConverterDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("output_voltage", self.output_voltage)?;
+        positive("rated_current", self.rated_current)?;
+        positive("efficiency", self.efficiency)?;
+        fraction("efficiency", self.efficiency)?;
+        not_negative("output_resistance", self.output_resistance)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatteryDefinition {
+    nominal_voltage: f64,
+    capacity: f64,
+    internal_resistance: f64,
+    state_of_charge: f64,
+}
+
+impl Definition for BatteryDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("nominal_voltage", self.nominal_voltage)?;
+        positive("capacity", self.capacity)?;
+        not_negative("internal_resistance", self.internal_resistance)?;
+        fraction("state_of_charge", self.state_of_charge)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContactorDefinition {
+    pull_in_voltage: f64,
+    drop_out_voltage: f64,
+    transfer_time: f64,
+}
+
+impl Definition for ContactorDefinition {
+    fn check(&self) -> Result<(), String> {
+        not_negative("drop_out_voltage", self.drop_out_voltage)?;
+        above(
+            "pull_in_voltage",
+            self.pull_in_voltage,
+            "drop_out_voltage",
+            self.drop_out_voltage,
+        )?;
+        not_negative("transfer_time", self.transfer_time)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiodeDefinition {
+    // No forward voltage for a reverse current relay
+    forward_voltage: f64,
+    forward_resistance: f64,
+}
+
+impl Definition for DiodeDefinition {
+    fn check(&self) -> Result<(), String> {
+        not_negative("forward_voltage", self.forward_voltage)?;
+        not_negative("forward_resistance", self.forward_resistance)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CircuitBreakerDefinition {
+    rating: f64,
+    #[serde(default)]
+    trip_band: TripBand,
+    #[serde(default)]
+    auto_reset: bool,
+    #[serde(default)]
+    reset_delay: f64,
+}
+
+impl Definition for CircuitBreakerDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("rating", self.rating)?;
+        not_negative("reset_delay", self.reset_delay)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SspcDefinition {
+    rating: f64,
+    #[serde(default = "default_closed")]
+    closed: bool,
+}
+
+impl Definition for SspcDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("rating", self.rating)
+    }
+}
+
+fn default_closed() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LoadDefinition {
+    nominal_voltage: f64,
+    nominal_power: f64,
+    min_voltage: f64,
+    max_voltage: f64,
+    voltage_response: VoltageResponse,
+    power_factor: f64,
+    #[serde(default)]
+    powered: bool,
+}
+
+impl Definition for LoadDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("nominal_voltage", self.nominal_voltage)?;
+        not_negative("nominal_power", self.nominal_power)?;
+        not_negative("min_voltage", self.min_voltage)?;
+        above(
+            "max_voltage",
+            self.max_voltage,
+            "min_voltage",
+            self.min_voltage,
+        )?;
+        fraction("power_factor", self.power_factor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AcLoadDefinition {
+    nominal_power: f64,
+    power_factor: f64,
+    connection: PhaseConnection,
+    frequency_response: FrequencyResponse,
+    min_voltage: f64,
+    max_voltage: f64,
+    #[serde(default)]
+    powered: bool,
+}
+
+impl Definition for AcLoadDefinition {
+    fn check(&self) -> Result<(), String> {
+        not_negative("nominal_power", self.nominal_power)?;
+        positive("power_factor", self.power_factor)?;
+        fraction("power_factor", self.power_factor)?;
+        not_negative("min_voltage", self.min_voltage)?;
+        above(
+            "max_voltage",
+            self.max_voltage,
+            "min_voltage",
+            self.min_voltage,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MotorSupplyDefinition {
+    Dc,
+    Ac,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MotorDefinition {
+    supply: MotorSupplyDefinition,
+    rated_voltage: f64,
+    rated_power: f64,
+    rated_speed: f64,
+    stall_current_ratio: f64,
+    spin_up_time: f64,
+    // AC motors only, the power factor is the one at the rated point
+    connection: Option<PhaseConnection>,
+    power_factor: Option<f64>,
+    // Newton meter the driven machine takes off the shaft until something drives it
+    #[serde(default)]
+    load_torque: f64,
+    #[serde(default)]
+    powered: bool,
+}
+
+impl Definition for MotorDefinition {
+    fn check(&self) -> Result<(), String> {
+        positive("rated_voltage", self.rated_voltage)?;
+        positive("rated_power", self.rated_power)?;
+        positive("rated_speed", self.rated_speed)?;
+        above(
+            "stall_current_ratio",
+            self.stall_current_ratio,
+            "rated current",
+            1.0,
+        )?;
+        positive("spin_up_time", self.spin_up_time)?;
+        not_negative("load_torque", self.load_torque)?;
+        if let Some(power_factor) = self.power_factor {
+            positive("power_factor", power_factor)?;
+            fraction("power_factor", power_factor)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_network(source: &str) -> Result<ElectricalSystem, Vec<NetworkLoadError>> {
+    let file: NetworkFile = toml::from_str(source).map_err(|error| {
+        vec![NetworkLoadError::Syntax {
+            message: error.to_string(),
+        }]
+    })?;
+
+    let mut system = ElectricalSystem::new();
+    let mut errors = Vec::new();
+    // Declared but refused, a wire to one of these has already been reported through the component
+    let mut failed = HashSet::new();
+
+    for (index, mut table) in file.component.into_iter().enumerate() {
+        let Some(name) = table
+            .remove("name")
+            .and_then(|name| name.as_str().map(str::to_string))
+        else {
+            errors.push(NetworkLoadError::MissingName { index });
+            continue;
+        };
+        if system.node(&name).is_some() {
+            errors.push(NetworkLoadError::DuplicateName { name });
+            continue;
+        }
+        let Some(component_type) = table
+            .remove("type")
+            .and_then(|component_type| component_type.as_str().map(str::to_string))
+        else {
+            failed.insert(name.clone());
+            errors.push(NetworkLoadError::MissingType { name });
+            continue;
+        };
+
+        if let Err(error) = add_component(&mut system, &name, &component_type, table) {
+            failed.insert(name);
+            errors.push(error);
+        }
+    }
+
+    for wire in file.wire {
+        let from = system.node(&wire.from);
+        let to = system.node(&wire.to);
+        match (from, to) {
+            (Some(from), Some(to)) if from == to => {
+                errors.push(NetworkLoadError::WireToItself { name: wire.from })
+            }
+            // The pair is one connection whichever way round it is written
+            (Some(from), Some(to))
+                if system.wire(from, to).is_some() || system.wire(to, from).is_some() =>
+            {
+                errors.push(NetworkLoadError::DuplicateWire {
+                    from: wire.from,
+                    to: wire.to,
+                })
+            }
+            (Some(from), Some(to)) => match wire_of(&wire) {
+                Ok(Some(definition)) => {
+                    system.connect_with_wire(from, to, definition);
+                }
+                Ok(None) => {
+                    system.connect_no_resistance(from, to);
+                }
+                Err(message) => errors.push(NetworkLoadError::InvalidWire {
+                    from: wire.from,
+                    to: wire.to,
+                    message,
+                }),
+            },
+            _ => {
+                let missing = [(&wire.from, from), (&wire.to, to)]
+                    .into_iter()
+                    .find(|(name, node)| node.is_none() && !failed.contains(*name))
+                    .map(|(name, _)| name.clone());
+                if let Some(missing) = missing {
+                    errors.push(NetworkLoadError::DanglingWire {
+                        from: wire.from,
+                        to: wire.to,
+                        missing,
+                    });
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(system)
+    } else {
+        Err(errors)
+    }
+}
+
+fn add_component(
+    system: &mut ElectricalSystem,
+    name: &str,
+    component_type: &str,
+    parameters: toml::Table,
+) -> Result<NodeIndex, NetworkLoadError> {
+    let node = match component_type {
+        "bus" => {
+            let _: BusDefinition = parameters_of(name, parameters)?;
+            let bus = Bus {
+                voltage: ElectricPotential::new::<volt>(0.0),
+                power: Power::new::<watt>(0.0),
+            };
+            add(system, name, bus)?
+        }
+        "generator" => {
+            let definition: GeneratorDefinition = parameters_of(name, parameters)?;
+            add(system, name, generator(&definition))?
+        }
+        "integrated_drive_generator" => {
+            let definition: IntegratedDriveGeneratorDefinition = parameters_of(name, parameters)?;
+            let idg = IntegratedDriveGenerator::new(
+                name,
+                generator(&definition.generator),
+                definition.underspeed_n2,
+            );
+            add(system, name, idg)?
+        }
+        "air_driven_generator" => {
+            let definition: AirDrivenGeneratorDefinition = parameters_of(name, parameters)?;
+            let adg = AirDrivenGenerator::new(
+                name,
+                definition.rated_power,
+                definition.rated_voltage,
+                definition.internal_resistance,
+                definition.min_airspeed,
+                definition.rated_airspeed,
+                definition.deployment_time,
+            );
+            add(system, name, adg)?
+        }
+        "ground_power" => {
+            let definition: GroundPowerDefinition = parameters_of(name, parameters)?;
+            let mut ground_power = GroundPower::new(
+                name,
+                definition.voltage,
+                definition.frequency,
+                definition.internal_resistance,
+            );
+            if definition.connected {
+                ground_power.connect();
+            }
+            ground_power.set_selected(definition.selected);
+            add(system, name, ground_power)?
+        }
+        "transformer_rectifier_unit" => {
+            let definition: ConverterDefinition = parameters_of(name, parameters)?;
+            let tru = TransformerRectifierUnit::new(
+                name,
+                definition.output_voltage,
+                definition.rated_current,
+                definition.efficiency,
+                definition.output_resistance,
+            );
+            add(system, name, tru)?
+        }
+        "static_inverter" => {
+            let definition: ConverterDefinition = parameters_of(name, parameters)?;
+            let inverter = StaticInverter::new(
+                name,
+                definition.output_voltage,
+                definition.rated_current,
+                definition.efficiency,
+                definition.output_resistance,
+            );
+            add(system, name, inverter)?
+        }
+        "battery" => {
+            let definition: BatteryDefinition = parameters_of(name, parameters)?;
+            let battery = Battery::new(
+                name,
+                definition.nominal_voltage,
+                definition.capacity,
+                definition.internal_resistance,
+                definition.state_of_charge,
+            );
+            add(system, name, battery)?
+        }
+        "contactor" => {
+            let definition: ContactorDefinition = parameters_of(name, parameters)?;
+            let contactor = Contactor::new(
+                name,
+                definition.pull_in_voltage,
+                definition.drop_out_voltage,
+                definition.transfer_time,
+            );
+            add(system, name, contactor)?
+        }
+        "diode" => {
+            let definition: DiodeDefinition = parameters_of(name, parameters)?;
+            let diode = Diode::new(
+                name,
+                definition.forward_voltage,
+                definition.forward_resistance,
+            );
+            add(system, name, diode)?
+        }
+        "circuit_breaker" => {
+            let definition: CircuitBreakerDefinition = parameters_of(name, parameters)?;
+            let breaker = CircuitBreaker::new(
+                name,
+                definition.rating,
+                definition.trip_band,
+                definition.auto_reset,
+                definition.reset_delay,
+            );
+            add(system, name, breaker)?
+        }
+        "solid_state_power_controller" => {
+            let definition: SspcDefinition = parameters_of(name, parameters)?;
+            let sspc = SolidStatePowerController::new(name, definition.rating, definition.closed);
+            add(system, name, sspc)?
+        }
+        "load" => {
+            let definition: LoadDefinition = parameters_of(name, parameters)?;
+            let mut load = GenericDcComponent::new(
+                name,
+                definition.nominal_voltage,
+                definition.nominal_power,
+                definition.min_voltage,
+                definition.max_voltage,
+                definition.voltage_response,
+                definition.power_factor,
+            );
+            load.set_power_state(definition.powered);
+            add(system, name, load)?
+        }
+        "ac_load" => {
+            let definition: AcLoadDefinition = parameters_of(name, parameters)?;
+            let mut load = GenericAcComponent::new(
+                name,
+                definition.nominal_power,
+                definition.power_factor,
+                definition.connection,
+                definition.frequency_response,
+                definition.min_voltage,
+                definition.max_voltage,
+            );
+            load.set_power_state(definition.powered);
+            add(system, name, load)?
+        }
+        "motor" => {
+            let definition: MotorDefinition = parameters_of(name, parameters)?;
+            let supply = match (definition.supply, definition.power_factor) {
+                (MotorSupplyDefinition::Dc, None) if definition.connection.is_none() => {
+                    MotorSupply::Dc
+                }
+                (MotorSupplyDefinition::Ac, Some(power_factor)) => MotorSupply::Ac {
+                    connection: definition.connection.unwrap_or(PhaseConnection::ThreePhase),
+                    power_factor: power_factor.clamp(0.01, 1.0),
+                },
+                (MotorSupplyDefinition::Dc, _) => {
+                    return Err(NetworkLoadError::InvalidParameters {
+                        name: name.to_string(),
+                        message: "a DC motor has no connection or power_factor".to_string(),
+                    });
+                }
+                (MotorSupplyDefinition::Ac, None) => {
+                    return Err(NetworkLoadError::InvalidParameters {
+                        name: name.to_string(),
+                        message: "an AC motor needs a power_factor".to_string(),
+                    });
+                }
+            };
+            let mut motor = Motor::new(
+                name,
+                supply,
+                definition.rated_voltage,
+                definition.rated_power,
+                definition.rated_speed,
+                definition.stall_current_ratio,
+                definition.spin_up_time,
+            );
+            motor.set_load_torque(Torque::new::<newton_meter>(definition.load_torque));
+            motor.set_power_state(definition.powered);
+            add(system, name, motor)?
+        }
+        _ => {
+            return Err(NetworkLoadError::UnknownComponentType {
+                name: name.to_string(),
+                component_type: component_type.to_string(),
+            });
+        }
+    };
+    Ok(node)
+}
+
+fn add<C: ElectricalComponent>(
+    system: &mut ElectricalSystem,
+    name: &str,
+    component: C,
+) -> Result<NodeIndex, NetworkLoadError> {
+    system
+        .add_component(name, component)
+        .map(|handle| handle.node())
+        .map_err(|_| NetworkLoadError::DuplicateName {
+            name: name.to_string(),
+        })
+}
+
+fn parameters_of<T: Definition>(
+    name: &str,
+    parameters: toml::Table,
+) -> Result<T, NetworkLoadError> {
+    let invalid = |message: String| NetworkLoadError::InvalidParameters {
+        name: name.to_string(),
+        message,
+    };
+    let definition: T = parameters
+        .try_into()
+        .map_err(|error: toml::de::Error| invalid(error.message().to_string()))?;
+    definition.check().map_err(invalid)?;
+    Ok(definition)
+}
+
+fn wire_of(definition: &WireDefinition) -> Result<Option<Wire>, String> {
+    match (definition.gauge, definition.length, definition.resistance) {
+        (Some(gauge), Some(length), None) => {
+            if !(-3..=30).contains(&gauge) {
+                return Err(format!("gauge {gauge} is outside 4/0 (-3) to 30 AWG"));
+            }
+            positive("length", length)?;
+            if definition.bundle_size == 0 {
+                return Err("bundle_size must be at least 1".to_string());
+            }
+            Ok(Some(Wire::new(
+                gauge,
+                length,
+                definition.material,
+                definition.bundle_size,
+            )))
+        }
+        (None, None, Some(resistance)) => {
+            not_negative("resistance", resistance)?;
+            Ok(Some(Wire::fixed(resistance)))
+        }
+        (None, None, None) => Ok(None),
+        (_, _, Some(_)) => Err("use either a gauge and length or a resistance".to_string()),
+        _ => Err("a gauge needs a length and a length needs a gauge".to_string()),
+    }
+}
+
+fn generator(definition: &GeneratorDefinition) -> Generator {
+    Generator::new(
+        definition.num_poles,
+        definition.rated_power,
+        definition.rated_voltage,
+        definition.rated_frequency,
+        definition.efficiency,
+        definition.internal_resistance,
+        definition.spin_up_time,
+        definition.phase_count,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::ELECTRICAL_NETWORK;
+
+    const BATTERY_BUS: &str = r#"
+[[component]]
+name = "Battery"
+type = "battery"
+nominal_voltage = 24.0
+capacity = 44.0
+internal_resistance = 0.02
+state_of_charge = 0.9
+
+[[component]]
+name = "Bus"
+type = "bus"
+
+[[wire]]
+from = "Battery"
+to = "Bus"
+gauge = 2
+length = 2.0
+"#;
+
+    fn errors(source: &str) -> Vec<NetworkLoadError> {
+        match parse_network(source) {
+            Ok(_) => panic!("network loaded"),
+            Err(errors) => errors,
+        }
+    }
+
+    fn contactor(pull_in_voltage: f64, drop_out_voltage: f64) -> String {
+        format!(
+            r#"
+[[component]]
+name = "Contactor"
+type = "contactor"
+pull_in_voltage = {pull_in_voltage}
+drop_out_voltage = {drop_out_voltage}
+transfer_time = 0.05
+"#
+        )
+    }
+
+    #[test]
+    fn valid_network_loads() {
+        let system = parse_network(BATTERY_BUS).unwrap();
+        let battery = system.node("Battery").unwrap();
+        let bus = system.node("Bus").unwrap();
+        assert!(system.handle::<Battery>("Battery").is_some());
+        assert!(system.wire(battery, bus).is_some());
+    }
+
+    #[test]
+    fn e170_network_loads() {
+        assert!(parse_network(ELECTRICAL_NETWORK).is_ok());
+    }
+
+    #[test]
+    fn every_error_in_the_file_is_reported() {
+        let source = format!(
+            r#"{BATTERY_BUS}
+[[component]]
+name = "Bus"
+type = "bus"
+
+[[component]]
+name = "Flux Capacitor"
+type = "flux_capacitor"
+
+[[wire]]
+from = "Bus"
+to = "Galley"
+"#
+        );
+        let errors = errors(&source);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(matches!(&errors[0], NetworkLoadError::DuplicateName { name } if name == "Bus"));
+        assert!(matches!(
+            &errors[1],
+            NetworkLoadError::UnknownComponentType { name, component_type }
+                if name == "Flux Capacitor" && component_type == "flux_capacitor"
+        ));
+        assert!(matches!(
+            &errors[2],
+            NetworkLoadError::DanglingWire { missing, .. } if missing == "Galley"
+        ));
+    }
+
+    #[test]
+    fn duplicate_wires_and_wires_to_themselves_are_reported() {
+        let errors = errors(&format!(
+            r#"{BATTERY_BUS}
+[[wire]]
+from = "Bus"
+to = "Battery"
+resistance = 0.01
+
+[[wire]]
+from = "Bus"
+to = "Bus"
+"#
+        ));
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            NetworkLoadError::DuplicateWire { from, to } if from == "Bus" && to == "Battery"
+        ));
+        assert!(matches!(&errors[1], NetworkLoadError::WireToItself { name } if name == "Bus"));
+    }
+
+    #[test]
+    fn missing_and_unknown_parameters_are_reported() {
+        let errors = errors(
+            r#"
+[[component]]
+name = "Bus"
+type = "bus"
+voltage = 28.0
+
+[[component]]
+type = "bus"
+
+[[component]]
+name = "Orphan"
+"#,
+        );
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(
+            matches!(&errors[0], NetworkLoadError::InvalidParameters { name, .. } if name == "Bus")
+        );
+        assert!(matches!(
+            errors[1],
+            NetworkLoadError::MissingName { index: 1 }
+        ));
+        assert!(matches!(&errors[2], NetworkLoadError::MissingType { name } if name == "Orphan"));
+    }
+
+    #[test]
+    fn negative_resistances_and_ratings_are_refused() {
+        let refused = errors(&format!(
+            r#"{BATTERY_BUS}
+[[component]]
+name = "Shunt"
+type = "bus"
+
+[[wire]]
+from = "Bus"
+to = "Shunt"
+resistance = -0.01
+
+[[component]]
+name = "Breaker"
+type = "circuit_breaker"
+rating = -5.0
+"#
+        ));
+        assert_eq!(refused.len(), 2, "{refused:?}");
+        assert!(matches!(
+            &refused[0],
+            NetworkLoadError::InvalidParameters { name, message }
+                if name == "Breaker" && message.contains("rating")
+        ));
+        assert!(matches!(
+            &refused[1],
+            NetworkLoadError::InvalidWire { to, message, .. }
+                if to == "Shunt" && message.contains("resistance")
+        ));
+
+        let refused = errors(
+            &BATTERY_BUS.replace("internal_resistance = 0.02", "internal_resistance = -0.02"),
+        );
+        assert!(matches!(
+            &refused[0],
+            NetworkLoadError::InvalidParameters { name, message }
+                if name == "Battery" && message.contains("internal_resistance")
+        ));
+    }
+
+    #[test]
+    fn infinite_values_are_refused() {
+        let refused = errors(&format!(
+            r#"{BATTERY_BUS}
+[[component]]
+name = "Breaker"
+type = "circuit_breaker"
+rating = inf
+
+[[component]]
+name = "Shunt"
+type = "bus"
+
+[[wire]]
+from = "Bus"
+to = "Shunt"
+resistance = inf
+"#
+        ));
+        assert_eq!(refused.len(), 2, "{refused:?}");
+        assert!(matches!(
+            &refused[0],
+            NetworkLoadError::InvalidParameters { name, message }
+                if name == "Breaker" && message.contains("rating")
+        ));
+        assert!(matches!(
+            &refused[1],
+            NetworkLoadError::InvalidWire { to, message, .. }
+                if to == "Shunt" && message.contains("resistance")
+        ));
+    }
+
+    #[test]
+    fn wires_to_a_refused_component_are_not_also_dangling() {
+        let errors = errors(&format!(
+            r#"{BATTERY_BUS}
+[[component]]
+name = "Breaker"
+type = "circuit_breaker"
+rating = -5.0
+
+[[component]]
+name = "Flux Capacitor"
+type = "flux_capacitor"
+
+[[component]]
+name = "Orphan"
+
+[[wire]]
+from = "Bus"
+to = "Breaker"
+
+[[wire]]
+from = "Flux Capacitor"
+to = "Orphan"
+
+[[wire]]
+from = "Breaker"
+to = "Galley"
+"#
+        ));
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert!(matches!(
+            &errors[3],
+            NetworkLoadError::DanglingWire { missing, .. } if missing == "Galley"
+        ));
+    }
+
+    #[test]
+    fn contactor_must_pull_in_above_its_drop_out() {
+        assert!(parse_network(&contactor(18.0, 12.0)).is_ok());
+        for (pull_in_voltage, drop_out_voltage) in [(12.0, 12.0), (12.0, 18.0)] {
+            let errors = errors(&contactor(pull_in_voltage, drop_out_voltage));
+            assert!(matches!(
+                &errors[..],
+                [NetworkLoadError::InvalidParameters { message, .. }] if message.contains("pull_in_voltage")
+            ));
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// Secondary Power Distribution Assembly and the MCDU electronic circuit breaker page.
+// The SPDA owns every SSPC of the network. Each frame it carries out the commands received on its data
+// bus and publishes the state of all its SSPCs, the MCDU page only ever talks to it over that bus: it
+// lists the published states and sends open, close and reset commands by SSPC name.
+
+use crate::communication_bus::{CommunicationBus, MessageID};
+use crate::systems::electrical::ElectricalSystem;
+use crate::systems::electrical::components::shared::solid_state_power_controller::{
+    SolidStatePowerController, SspcState,
+};
+
+use std::fmt;
+use std::sync::Arc;
+use uom::si::f64::*;
+
+#[derive(Clone, Debug)]
+pub struct SspcStatus {
+    pub name: String,
+    pub state: SspcState,
+    pub rating: ElectricCurrent,
+    pub current: ElectricCurrent,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SspcAction {
+    Open,
+    Close,
+    Reset,
+}
+
+#[derive(Clone, Debug)]
+pub struct SspcCommand {
+    pub name: String,
+    pub action: SspcAction,
+}
+
+pub struct SecondaryPowerDistribution {
+    name: String,
+    bus: Arc<CommunicationBus>,
+}
+
+impl SecondaryPowerDistribution {
+    pub fn new(name: &str, bus: Arc<CommunicationBus>) -> Self {
+        SecondaryPowerDistribution {
+            name: name.to_string(),
+            bus,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn update(&mut self, system: &mut ElectricalSystem) {
+        let bus = &self.bus;
+
+        for command in bus.take::<SspcCommand>(MessageID::SspcCommand) {
+            let Some(handle) = system.handle::<SolidStatePowerController>(&command.name) else {
+                eprintln!("⚠️ {}: no SSPC named {}", self.name, command.name);
+                continue;
+            };
+            if let Some(sspc) = system.get_mut(handle) {
+                match command.action {
+                    SspcAction::Open => sspc.command(false),
+                    SspcAction::Close => sspc.command(true),
+                    SspcAction::Reset => sspc.reset(),
+                }
+            }
+        }
+
+        let status: Vec<SspcStatus> = system
+            .component_nodes()
+            .filter_map(|node| system.handle_at::<SolidStatePowerController>(node))
+            .filter_map(|handle| system.get(handle))
+            .map(|sspc| SspcStatus {
+                name: sspc.name().to_string(),
+                state: sspc.state(),
+                rating: sspc.rating(),
+                current: sspc.current(),
+            })
+            .collect();
+        bus.clear(MessageID::SspcStatus);
+        bus.send(MessageID::SspcStatus, status);
+    }
+}
+
+#[derive(Debug)]
+pub enum EcbPageError {
+    UnknownBreaker { name: String },
+}
+
+impl fmt::Display for EcbPageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EcbPageError::UnknownBreaker { name } => {
+                write!(f, "no electronic breaker named {name}")
+            }
+        }
+    }
+}
+
+pub struct ElectronicCircuitBreakerPage {
+    bus: Arc<CommunicationBus>,
+}
+
+impl ElectronicCircuitBreakerPage {
+    pub fn new(bus: Arc<CommunicationBus>) -> Self {
+        ElectronicCircuitBreakerPage { bus }
+    }
+
+    // Latest state of every SSPC, sorted by name the way the page shows them
+    pub fn list(&self) -> Vec<SspcStatus> {
+        let mut status = self
+            .bus
+            .receive::<Vec<SspcStatus>>(MessageID::SspcStatus)
+            .pop()
+            .unwrap_or_default();
+        status.sort_by(|a, b| a.name.cmp(&b.name));
+        status
+    }
+
+    // Breakers that are not closed, the page's "open/tripped" filter
+    pub fn list_open(&self) -> Vec<SspcStatus> {
+        self.list()
+            .into_iter()
+            .filter(|status| status.state != SspcState::Closed)
+            .collect()
+    }
+
+    pub fn open(&self, name: &str) -> Result<(), EcbPageError> {
+        self.send(name, SspcAction::Open)
+    }
+
+    pub fn close(&self, name: &str) -> Result<(), EcbPageError> {
+        self.send(name, SspcAction::Close)
+    }
+
+    pub fn reset(&self, name: &str) -> Result<(), EcbPageError> {
+        self.send(name, SspcAction::Reset)
+    }
+
+    fn send(&self, name: &str, action: SspcAction) -> Result<(), EcbPageError> {
+        if !self.list().iter().any(|status| status.name == name) {
+            return Err(EcbPageError::UnknownBreaker {
+                name: name.to_string(),
+            });
+        }
+        self.bus.send(
+            MessageID::SspcCommand,
+            SspcCommand {
+                name: name.to_string(),
+                action,
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use crate::systems::electrical::fault::Fault;
+    use petgraph::graph::NodeIndex;
+    use uom::si::electric_potential::volt;
+    use uom::si::power::watt;
+
+    const SSPC: &str = "Cabin Lights SSPC";
+
+    struct Bench {
+        system: ElectricalSystem,
+        spda: SecondaryPowerDistribution,
+        page: ElectronicCircuitBreakerPage,
+        load_bus: NodeIndex,
+    }
+
+    impl Bench {
+        fn new() -> Self {
+            let mut system = ElectricalSystem::new();
+            let battery = Battery::new("BATT", 24.0, 44.0, 0.02, 0.9);
+            let battery = system.add_component("BATT", battery).unwrap();
+            let sspc = SolidStatePowerController::new(SSPC, 5.0, true);
+            let sspc = system.add_component(SSPC, sspc).unwrap();
+            let load_bus = Bus {
+                voltage: ElectricPotential::new::<volt>(0.0),
+                power: Power::new::<watt>(0.0),
+            };
+            let load_bus = system.add_component("Cabin Lights", load_bus).unwrap();
+            system.connect_no_resistance(battery, sspc);
+            system.connect_no_resistance(sspc, load_bus);
+
+            let bus = Arc::new(CommunicationBus::new());
+            Bench {
+                system,
+                spda: SecondaryPowerDistribution::new("SPDA", bus.clone()),
+                page: ElectronicCircuitBreakerPage::new(bus),
+                load_bus: load_bus.node(),
+            }
+        }
+
+        fn update(&mut self) {
+            self.spda.update(&mut self.system);
+            self.system.update_system(0.1);
+            // Status goes out after the network updated, the way the page sees it next frame
+            self.spda.update(&mut self.system);
+        }
+
+        fn state(&self) -> SspcState {
+            self.page.list()[0].state
+        }
+
+        fn load_voltage(&self) -> f64 {
+            self.system
+                .get_voltage(self.load_bus)
+                .unwrap()
+                .get::<volt>()
+        }
+    }
+
+    #[test]
+    fn page_lists_only_what_the_spda_published() {
+        let mut bench = Bench::new();
+        assert!(bench.page.list().is_empty());
+        assert!(matches!(
+            bench.page.open(SSPC),
+            Err(EcbPageError::UnknownBreaker { .. })
+        ));
+
+        bench.update();
+        let list = bench.page.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, SSPC);
+        assert_eq!(list[0].state, SspcState::Closed);
+        assert!(bench.page.list_open().is_empty());
+        assert!(matches!(
+            bench.page.open("Galley SSPC"),
+            Err(EcbPageError::UnknownBreaker { .. })
+        ));
+    }
+
+    #[test]
+    fn page_opens_and_closes_an_sspc_through_the_spda() {
+        let mut bench = Bench::new();
+        bench.update();
+        assert!(bench.load_voltage() > 20.0);
+
+        bench.page.open(SSPC).unwrap();
+        bench.update();
+        assert_eq!(bench.state(), SspcState::Open);
+        assert_eq!(bench.page.list_open().len(), 1);
+        assert!(bench.load_voltage() < 0.1);
+
+        bench.page.close(SSPC).unwrap();
+        bench.update();
+        assert_eq!(bench.state(), SspcState::Closed);
+        assert!(bench.load_voltage() > 20.0);
+    }
+
+    #[test]
+    fn tripped_sspc_is_reset_from_the_page() {
+        let mut bench = Bench::new();
+        bench.update();
+        let short = bench
+            .system
+            .inject_fault(Fault::short_to_ground(bench.load_bus, 0.01))
+            .unwrap();
+        bench.update();
+        bench.update();
+        assert_eq!(bench.state(), SspcState::Tripped);
+        assert!(bench.load_voltage() < 0.1);
+
+        bench.system.clear_fault(short);
+        bench.page.reset(SSPC).unwrap();
+        bench.update();
+        assert_eq!(bench.state(), SspcState::Closed);
+        assert!(bench.load_voltage() > 20.0);
+    }
+
+    #[test]
+    fn spdas_on_separate_buses_do_not_see_each_other() {
+        let mut first = Bench::new();
+        let second = Bench::new();
+        first.update();
+        assert_eq!(first.page.list().len(), 1);
+        assert!(second.page.list().is_empty());
+    }
+}
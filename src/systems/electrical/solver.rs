@@ -0,0 +1,603 @@
+// Nodal analysis of the electrical network.
+// Every component is reduced to a small linear model (see `CircuitModel`), the wires become
+// conductances between the component terminals and the resulting G * V = I system is solved
+// directly, so loops, bus ties and parallel sources are handled the same way as a plain tree.
+// Diodes make the network piecewise linear: it is solved with every diode conducting, diodes found
+// carrying reverse current are blocked (and blocked ones seeing forward voltage let through again) and
+// the network is solved again until no diode changes. A network that does not settle has its disagreeing
+// diodes blocked, so no diode is ever reported carrying reverse current.
+
+use petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
+use uom::si::electric_current::ampere;
+use uom::si::electric_potential::volt;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+
+// Floor for any resistance stamped into the matrix, keeps ideal elements from blowing up the solve.
+const MIN_RESISTANCE: f64 = 1e-6; // Ohm
+// Tiny leakage from every net to ground so floating islands (no source connected) solve to 0V.
+const GMIN: f64 = 1e-9; // Siemens
+
+pub enum CircuitModel {
+    // Ideal junction, every wire attached to the component shares a single net (buses)
+    Junction,
+    // Thevenin source between the component net and ground (generators, batteries)
+    Source {
+        voltage: ElectricPotential,
+        internal_resistance: ElectricalResistance,
+    },
+    // Impedance from the component net to ground (consumers)
+    Load {
+        resistance: ElectricalResistance,
+    },
+    // Two terminal element, incoming wires land on the input side and outgoing wires leave the output side
+    Series {
+        resistance: ElectricalResistance,
+    },
+    // Two terminal element that does not conduct (tripped breaker, open contactor, unpowered source)
+    Open,
+    // Two terminal element that only conducts from input to output (diode, reverse current relay), a
+    // forward voltage drop in series with a resistance while conducting
+    Diode {
+        forward_voltage: ElectricPotential,
+        forward_resistance: ElectricalResistance,
+    },
+    // Power conversion between two isolated networks (TRU, inverter): the input side is a load on the
+    // feeding network and the output side is a Thevenin source on the fed network
+    Converter {
+        input_resistance: ElectricalResistance,
+        output_voltage: ElectricPotential,
+        output_resistance: ElectricalResistance,
+    },
+}
+
+impl CircuitModel {
+    fn is_two_terminal(&self) -> bool {
+        matches!(
+            self,
+            CircuitModel::Series { .. }
+                | CircuitModel::Open
+                | CircuitModel::Converter { .. }
+                | CircuitModel::Diode { .. }
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct NetworkSolution {
+    pub input_voltage: HashMap<NodeIndex, ElectricPotential>,
+    pub output_voltage: HashMap<NodeIndex, ElectricPotential>,
+    // Current through the component: delivered by sources and converters, drawn by loads, passed by
+    // series elements
+    pub component_current: HashMap<NodeIndex, ElectricCurrent>,
+    // Current along each wire, positive in the from -> to direction
+    pub wire_current: HashMap<(NodeIndex, NodeIndex), ElectricCurrent>,
+    // Current into each shunt, in the order they were added
+    pub shunt_current: Vec<ElectricCurrent>,
+}
+
+#[derive(Default)]
+pub struct NodalNetwork {
+    // (input net, output net) for every component, both are the same net for one terminal models
+    terminals: HashMap<NodeIndex, (usize, usize)>,
+    models: Vec<(NodeIndex, CircuitModel)>,
+    wires: Vec<(NodeIndex, NodeIndex, f64)>,
+    // Extra paths to ground on a component input net (short circuits)
+    shunts: Vec<(NodeIndex, f64)>,
+    net_count: usize,
+}
+
+impl NodalNetwork {
+    pub fn add_component(&mut self, node: NodeIndex, model: CircuitModel) {
+        let input = self.net_count;
+        let output = if model.is_two_terminal() {
+            input + 1
+        } else {
+            input
+        };
+        self.net_count = output + 1;
+        self.terminals.insert(node, (input, output));
+        self.models.push((node, model));
+    }
+
+    pub fn add_wire(&mut self, from: NodeIndex, to: NodeIndex, resistance: ElectricalResistance) {
+        self.wires
+            .push((from, to, conductance(resistance.get::<ohm>())));
+    }
+
+    pub fn add_shunt(&mut self, node: NodeIndex, resistance: ElectricalResistance) {
+        self.shunts
+            .push((node, conductance(resistance.get::<ohm>())));
+    }
+
+    pub fn solve(&self) -> NetworkSolution {
+        let diodes = self
+            .models
+            .iter()
+            .filter(|(_, model)| matches!(model, CircuitModel::Diode { .. }))
+            .count();
+        let mut blocking = HashSet::new();
+        let mut v = self.net_voltages(&blocking);
+        // The diode states settle in a pass or two on an aircraft network, the pass count only bounds a
+        // network that keeps switching
+        for _ in 0..2 * diodes {
+            let disagreeing = self.disagreeing_diodes(&v, &blocking);
+            if disagreeing.is_empty() {
+                break;
+            }
+            for node in disagreeing {
+                if !blocking.remove(&node) {
+                    blocking.insert(node);
+                }
+            }
+            v = self.net_voltages(&blocking);
+        }
+
+        // Still switching once the passes ran out: block whatever disagrees with the last solve rather than
+        // report a diode carrying reverse current
+        let disagreeing = self.disagreeing_diodes(&v, &blocking);
+        if !disagreeing.is_empty() {
+            eprintln!(
+                "⚠️ NETWORK SOLVER: {} diode(s) did not settle, blocking them",
+                disagreeing.len()
+            );
+            blocking.extend(disagreeing);
+            v = self.net_voltages(&blocking);
+        }
+        self.solution(&v, &blocking)
+    }
+
+    // Diodes conducting in reverse and blocked diodes seeing forward voltage in the solve `v`
+    fn disagreeing_diodes(&self, v: &[f64], blocking: &HashSet<NodeIndex>) -> Vec<NodeIndex> {
+        self.models
+            .iter()
+            .filter_map(|(node, model)| {
+                let CircuitModel::Diode {
+                    forward_voltage, ..
+                } = model
+                else {
+                    return None;
+                };
+                let (input, output) = self.terminals[node];
+                let forward = v[input] - v[output] - forward_voltage.get::<volt>();
+                let disagrees = if blocking.contains(node) {
+                    forward > 0.0
+                } else {
+                    forward < 0.0
+                };
+                disagrees.then_some(*node)
+            })
+            .collect()
+    }
+
+    fn net_voltages(&self, blocking: &HashSet<NodeIndex>) -> Vec<f64> {
+        let n = self.net_count;
+        let mut g = vec![0.0; n * n];
+        let mut i = vec![0.0; n];
+
+        for (node, model) in &self.models {
+            let (input, output) = self.terminals[node];
+            match model {
+                CircuitModel::Source {
+                    voltage,
+                    internal_resistance,
+                } => {
+                    // Norton equivalent of the Thevenin source
+                    let conductance = conductance(internal_resistance.get::<ohm>());
+                    g[input * n + input] += conductance;
+                    i[input] += voltage.get::<volt>() * conductance;
+                }
+                CircuitModel::Load { resistance } => {
+                    g[input * n + input] += conductance(resistance.get::<ohm>());
+                }
+                CircuitModel::Series { resistance } => {
+                    stamp_between(
+                        &mut g,
+                        n,
+                        input,
+                        output,
+                        conductance(resistance.get::<ohm>()),
+                    );
+                }
+                CircuitModel::Converter {
+                    input_resistance,
+                    output_voltage,
+                    output_resistance,
+                } => {
+                    g[input * n + input] += conductance(input_resistance.get::<ohm>());
+                    let conductance = conductance(output_resistance.get::<ohm>());
+                    g[output * n + output] += conductance;
+                    i[output] += output_voltage.get::<volt>() * conductance;
+                }
+                CircuitModel::Diode {
+                    forward_voltage,
+                    forward_resistance,
+                } => {
+                    if !blocking.contains(node) {
+                        // Resistance with a current source standing for the forward drop
+                        let conductance = conductance(forward_resistance.get::<ohm>());
+                        stamp_between(&mut g, n, input, output, conductance);
+                        i[input] += forward_voltage.get::<volt>() * conductance;
+                        i[output] -= forward_voltage.get::<volt>() * conductance;
+                    }
+                }
+                CircuitModel::Junction | CircuitModel::Open => {}
+            }
+        }
+
+        for (from, to, conductance) in &self.wires {
+            if let (Some((_, a)), Some((b, _))) = (self.terminals.get(from), self.terminals.get(to))
+                && a != b
+            {
+                stamp_between(&mut g, n, *a, *b, *conductance);
+            }
+        }
+
+        for (node, conductance) in &self.shunts {
+            if let Some((input, _)) = self.terminals.get(node) {
+                g[input * n + input] += conductance;
+            }
+        }
+
+        for net in 0..n {
+            g[net * n + net] += GMIN;
+        }
+
+        solve_linear(g, i, n)
+    }
+
+    fn solution(&self, v: &[f64], blocking: &HashSet<NodeIndex>) -> NetworkSolution {
+        let mut solution = NetworkSolution::default();
+        let mut junctions = HashSet::new();
+        for (node, model) in &self.models {
+            let (input, output) = self.terminals[node];
+            let current = match model {
+                CircuitModel::Source {
+                    voltage,
+                    internal_resistance,
+                } => {
+                    (voltage.get::<volt>() - v[input])
+                        * conductance(internal_resistance.get::<ohm>())
+                }
+                CircuitModel::Load { resistance } => {
+                    v[input] * conductance(resistance.get::<ohm>())
+                }
+                CircuitModel::Series { resistance } => {
+                    (v[input] - v[output]) * conductance(resistance.get::<ohm>())
+                }
+                CircuitModel::Converter {
+                    output_voltage,
+                    output_resistance,
+                    ..
+                } => {
+                    (output_voltage.get::<volt>() - v[output])
+                        * conductance(output_resistance.get::<ohm>())
+                }
+                CircuitModel::Diode {
+                    forward_voltage,
+                    forward_resistance,
+                } => {
+                    if blocking.contains(node) {
+                        0.0
+                    } else {
+                        (v[input] - v[output] - forward_voltage.get::<volt>())
+                            * conductance(forward_resistance.get::<ohm>())
+                    }
+                }
+                CircuitModel::Junction => {
+                    junctions.insert(*node);
+                    0.0
+                }
+                CircuitModel::Open => 0.0,
+            };
+            solution
+                .input_voltage
+                .insert(*node, ElectricPotential::new::<volt>(v[input]));
+            solution
+                .output_voltage
+                .insert(*node, ElectricPotential::new::<volt>(v[output]));
+            solution
+                .component_current
+                .insert(*node, ElectricCurrent::new::<ampere>(current));
+        }
+
+        for (from, to, conductance) in &self.wires {
+            if let (Some((_, a)), Some((b, _))) = (self.terminals.get(from), self.terminals.get(to))
+            {
+                let current = (v[*a] - v[*b]) * conductance;
+                solution
+                    .wire_current
+                    .insert((*from, *to), ElectricCurrent::new::<ampere>(current));
+
+                // Junctions have no current of their own, report what flows into them instead
+                if junctions.contains(to)
+                    && let Some(total) = solution.component_current.get_mut(to)
+                {
+                    *total += ElectricCurrent::new::<ampere>(current.max(0.0));
+                }
+            }
+        }
+
+        for (node, conductance) in &self.shunts {
+            let current = self
+                .terminals
+                .get(node)
+                .map_or(0.0, |(input, _)| v[*input] * conductance);
+            solution
+                .shunt_current
+                .push(ElectricCurrent::new::<ampere>(current));
+        }
+
+        solution
+    }
+}
+
+fn stamp_between(g: &mut [f64], n: usize, a: usize, b: usize, conductance: f64) {
+    g[a * n + a] += conductance;
+    g[b * n + b] += conductance;
+    g[a * n + b] -= conductance;
+    g[b * n + a] -= conductance;
+}
+
+pub(crate) fn conductance(resistance: f64) -> f64 {
+    if resistance.is_finite() {
+        1.0 / resistance.max(MIN_RESISTANCE)
+    } else {
+        0.0
+    }
+}
+
+// Gaussian elimination with partial pivoting, `g` is a row major n * n matrix
+fn solve_linear(mut g: Vec<f64>, mut i: Vec<f64>, n: usize) -> Vec<f64> {
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| g[a * n + col].abs().total_cmp(&g[b * n + col].abs()))
+            .unwrap_or(col);
+        if g[pivot * n + col].abs() < f64::EPSILON {
+            continue;
+        }
+        if pivot != col {
+            for k in 0..n {
+                g.swap(col * n + k, pivot * n + k);
+            }
+            i.swap(col, pivot);
+        }
+
+        for row in (col + 1)..n {
+            let factor = g[row * n + col] / g[col * n + col];
+            if factor == 0.0 {
+                continue;
+            }
+            for k in col..n {
+                g[row * n + k] -= factor * g[col * n + k];
+            }
+            i[row] -= factor * i[col];
+        }
+    }
+
+    let mut v = vec![0.0; n];
+    for row in (0..n).rev() {
+        let mut sum = i[row];
+        for k in (row + 1)..n {
+            sum -= g[row * n + k] * v[k];
+        }
+        v[row] = if g[row * n + row].abs() < f64::EPSILON {
+            0.0
+        } else {
+            sum / g[row * n + row]
+        };
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Of the expected value once it is over 1, the wires stamp at least the minimum resistance
+    const TOLERANCE: f64 = 1e-3;
+
+    fn node(index: usize) -> NodeIndex {
+        NodeIndex::new(index)
+    }
+
+    fn resistance(ohms: f64) -> ElectricalResistance {
+        ElectricalResistance::new::<ohm>(ohms)
+    }
+
+    fn source(voltage: f64, internal_resistance: f64) -> CircuitModel {
+        CircuitModel::Source {
+            voltage: ElectricPotential::new::<volt>(voltage),
+            internal_resistance: resistance(internal_resistance),
+        }
+    }
+
+    fn load(ohms: f64) -> CircuitModel {
+        CircuitModel::Load {
+            resistance: resistance(ohms),
+        }
+    }
+
+    fn voltage(solution: &NetworkSolution, index: usize) -> f64 {
+        solution.input_voltage[&node(index)].get::<volt>()
+    }
+
+    fn current(solution: &NetworkSolution, index: usize) -> f64 {
+        solution.component_current[&node(index)].get::<ampere>()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < TOLERANCE * expected.abs().max(1.0),
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn voltage_divider() {
+        // 10 V across 1 Ohm of wire and a 1 Ohm load
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(10.0, 0.0));
+        network.add_component(node(1), load(1.0));
+        network.add_wire(node(0), node(1), resistance(1.0));
+        let solution = network.solve();
+
+        assert_close(voltage(&solution, 0), 10.0);
+        assert_close(voltage(&solution, 1), 5.0);
+        assert_close(current(&solution, 0), 5.0);
+        assert_close(current(&solution, 1), 5.0);
+        assert_close(
+            solution.wire_current[&(node(0), node(1))].get::<ampere>(),
+            5.0,
+        );
+    }
+
+    #[test]
+    fn parallel_sources_share_by_internal_resistance() {
+        // 28 V and 26 V behind 0.1 Ohm each on one bus with a 1 Ohm load, by Millman
+        // V = (28 / 0.1 + 26 / 0.1) / (1 / 0.1 + 1 / 0.1 + 1 / 1) = 540 / 21
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(28.0, 0.1));
+        network.add_component(node(1), source(26.0, 0.1));
+        network.add_component(node(2), CircuitModel::Junction);
+        network.add_component(node(3), load(1.0));
+        network.add_wire(node(0), node(2), resistance(0.0));
+        network.add_wire(node(1), node(2), resistance(0.0));
+        network.add_wire(node(2), node(3), resistance(0.0));
+        let solution = network.solve();
+
+        let bus = 540.0 / 21.0;
+        assert_close(voltage(&solution, 2), bus);
+        assert_close(current(&solution, 0), (28.0 - bus) / 0.1);
+        assert_close(current(&solution, 1), (26.0 - bus) / 0.1);
+        assert_close(current(&solution, 3), bus);
+        // The bus reports what flows into it
+        assert_close(current(&solution, 2), (54.0 - 2.0 * bus) / 0.1);
+    }
+
+    #[test]
+    fn bus_tie_loop_between_two_sources() {
+        // 30 V and 20 V behind 1 Ohm each, their buses tied by two 2 Ohm ties in parallel: 10 V over 3 Ohm
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(30.0, 1.0));
+        network.add_component(node(1), CircuitModel::Junction);
+        network.add_component(node(2), source(20.0, 1.0));
+        network.add_component(node(3), CircuitModel::Junction);
+        for tie in [4, 5] {
+            network.add_component(
+                node(tie),
+                CircuitModel::Series {
+                    resistance: resistance(2.0),
+                },
+            );
+            network.add_wire(node(1), node(tie), resistance(0.0));
+            network.add_wire(node(tie), node(3), resistance(0.0));
+        }
+        network.add_wire(node(0), node(1), resistance(0.0));
+        network.add_wire(node(2), node(3), resistance(0.0));
+        let solution = network.solve();
+
+        let loop_current = 10.0 / 3.0;
+        assert_close(voltage(&solution, 1), 30.0 - loop_current);
+        assert_close(voltage(&solution, 3), 20.0 + loop_current);
+        assert_close(current(&solution, 0), loop_current);
+        assert_close(current(&solution, 2), -loop_current);
+        assert_close(current(&solution, 4), loop_current / 2.0);
+        assert_close(current(&solution, 5), loop_current / 2.0);
+    }
+
+    #[test]
+    fn open_branch_only_leaks_gmin() {
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(28.0, 0.01));
+        network.add_component(node(1), CircuitModel::Open);
+        network.add_component(node(2), load(1.0));
+        network.add_wire(node(0), node(1), resistance(0.0));
+        network.add_wire(node(1), node(2), resistance(0.0));
+        let solution = network.solve();
+
+        // The open element holds off the full source voltage, the load floats to ground
+        assert_close(voltage(&solution, 1), 28.0);
+        assert_close(solution.output_voltage[&node(1)].get::<volt>(), 0.0);
+        assert_close(voltage(&solution, 2), 0.0);
+        assert_eq!(current(&solution, 1), 0.0);
+        // All the source delivers is the GMIN leakage of the nets it reaches
+        assert!(current(&solution, 0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn converter_isolates_its_two_networks() {
+        // 115 V behind 0.01 Ohm into a 100 Ohm converter input, 28 V behind 0.01 Ohm out into 2.8 Ohm
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(115.0, 0.01));
+        network.add_component(
+            node(1),
+            CircuitModel::Converter {
+                input_resistance: resistance(100.0),
+                output_voltage: ElectricPotential::new::<volt>(28.0),
+                output_resistance: resistance(0.01),
+            },
+        );
+        network.add_component(node(2), load(2.8));
+        network.add_wire(node(0), node(1), resistance(0.0));
+        network.add_wire(node(1), node(2), resistance(0.0));
+        let solution = network.solve();
+
+        assert_close(voltage(&solution, 1), 115.0 * 100.0 / 100.01);
+        assert_close(current(&solution, 0), 115.0 / 100.01);
+        let output = 28.0 * 2.8 / 2.81;
+        assert_close(solution.output_voltage[&node(1)].get::<volt>(), output);
+        assert_close(current(&solution, 1), 28.0 / 2.81);
+        assert_close(voltage(&solution, 2), output);
+    }
+
+    fn diode(forward_voltage: f64, forward_resistance: f64) -> CircuitModel {
+        CircuitModel::Diode {
+            forward_voltage: ElectricPotential::new::<volt>(forward_voltage),
+            forward_resistance: resistance(forward_resistance),
+        }
+    }
+
+    #[test]
+    fn diode_blocks_battery_backfeed() {
+        // 24 V battery behind a diode facing a 28 V bus, the diode is reverse biased
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(24.0, 0.05));
+        network.add_component(node(1), diode(0.7, 0.002));
+        network.add_component(node(2), CircuitModel::Junction);
+        network.add_component(node(3), source(28.0, 0.01));
+        network.add_component(node(4), load(2.8));
+        network.add_wire(node(0), node(1), resistance(0.0));
+        network.add_wire(node(1), node(2), resistance(0.0));
+        network.add_wire(node(3), node(2), resistance(0.0));
+        network.add_wire(node(2), node(4), resistance(0.0));
+        let solution = network.solve();
+
+        assert_eq!(current(&solution, 1), 0.0);
+        assert!(current(&solution, 0).abs() < 1e-6);
+        assert_close(voltage(&solution, 0), 24.0);
+        // The bus only sees its own source
+        assert_close(voltage(&solution, 2), 28.0 * 2.8 / 2.81);
+    }
+
+    #[test]
+    fn diode_conducts_forward_with_its_drop() {
+        // 28 V bus charging a 24 V battery through the diode: 28 - 0.7 - 24 over 0.01 + 0.002 + 0.05 Ohm
+        let mut network = NodalNetwork::default();
+        network.add_component(node(0), source(28.0, 0.01));
+        network.add_component(node(1), diode(0.7, 0.002));
+        network.add_component(node(2), source(24.0, 0.05));
+        network.add_wire(node(0), node(1), resistance(0.0));
+        network.add_wire(node(1), node(2), resistance(0.0));
+        let solution = network.solve();
+
+        let charge = 3.3 / 0.062;
+        assert_close(current(&solution, 1), charge);
+        assert_close(current(&solution, 2), -charge);
+        let input = voltage(&solution, 1);
+        let output = solution.output_voltage[&node(1)].get::<volt>();
+        assert_close(input, 28.0 - charge * 0.01);
+        assert_close(input - output, 0.7 + charge * 0.002);
+    }
+}
@@ -0,0 +1,299 @@
+// Aircraft wiring.
+// A wire is described the way it is on the wiring diagram, gauge, length, conductor material and the
+// number of wires sharing its bundle. Resistance follows from the conductor section and changes with the
+// conductor temperature, which rises with the I²R losses and cools towards the ambient temperature of the
+// zone the wire runs through. The ampacity is the free air rating of the gauge derated for the bundle.
+// Bus bars and short links that only need a fixed resistance use `Wire::fixed`.
+
+use serde::Deserialize;
+use uom::si::electric_current::ampere;
+use uom::si::electrical_resistance::ohm;
+use uom::si::f64::*;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+const REFERENCE_TEMPERATURE: f64 = 20.0; // °C, resistivity is given at this temperature
+const RATED_TEMPERATURE: f64 = 150.0; // °C, conductor rating of the aircraft wire
+// Fit of the single wire free air ratings, 11 A at 20 AWG up to ~400 A at 4/0
+const AMPACITY_PER_MM2: f64 = 17.2; // Ampere
+const AMPACITY_EXPONENT: f64 = 0.68;
+// Aluminium carries less than copper for the same gauge
+const ALUMINIUM_AMPACITY_RATIO: f64 = 0.8;
+// Fit of the bundle derating curve, 5 wires carry ~73% and 20 wires ~55% of their free air rating
+const BUNDLE_DERATING_EXPONENT: f64 = -0.2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireMaterial {
+    Copper,
+    Aluminium,
+}
+
+impl WireMaterial {
+    fn resistivity(&self) -> f64 {
+        // Ohm meter at 20°C
+        match self {
+            WireMaterial::Copper => 1.724e-8,
+            WireMaterial::Aluminium => 2.826e-8,
+        }
+    }
+
+    fn temperature_coefficient(&self) -> f64 {
+        // Per °C
+        match self {
+            WireMaterial::Copper => 0.00393,
+            WireMaterial::Aluminium => 0.00403,
+        }
+    }
+
+    fn heat_capacity(&self) -> f64 {
+        // Density (kg/m³) times specific heat (J/kg/K)
+        match self {
+            WireMaterial::Copper => 8960.0 * 385.0,
+            WireMaterial::Aluminium => 2700.0 * 897.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Wire {
+    reference_resistance: ElectricalResistance,
+    temperature_coefficient: f64,
+    ampacity: Option<ElectricCurrent>,
+    // J/K of the whole conductor and W/K to the surrounding air, zero for fixed links
+    heat_capacity: f64,
+    thermal_conductance: f64,
+
+    ambient_temperature: ThermodynamicTemperature,
+    temperature: ThermodynamicTemperature,
+    current: ElectricCurrent,
+}
+
+impl Wire {
+    // `gauge` is the AWG number, 1/0 is 0 and 4/0 is -3. `length` in meter.
+    pub fn new(gauge: i8, length: f64, material: WireMaterial, bundle_size: u32) -> Self {
+        // AWG diameter in mm
+        let diameter = 0.127 * 92.0_f64.powf((36.0 - gauge as f64) / 39.0);
+        let section_mm2 = std::f64::consts::PI * diameter * diameter / 4.0;
+        let section = section_mm2 * 1e-6;
+
+        let mut ampacity = AMPACITY_PER_MM2 * section_mm2.powf(AMPACITY_EXPONENT);
+        if material == WireMaterial::Aluminium {
+            ampacity *= ALUMINIUM_AMPACITY_RATIO;
+        }
+        ampacity *= (bundle_size.max(1) as f64).powf(BUNDLE_DERATING_EXPONENT);
+
+        let reference_resistance = material.resistivity() * length / section;
+        let temperature_coefficient = material.temperature_coefficient();
+
+        // Sized so a wire carrying its ampacity settles at the rated conductor temperature
+        let rated_rise = RATED_TEMPERATURE - REFERENCE_TEMPERATURE;
+        let rated_resistance = reference_resistance * (1.0 + temperature_coefficient * rated_rise);
+        let thermal_conductance = ampacity * ampacity * rated_resistance / rated_rise;
+
+        Wire {
+            reference_resistance: ElectricalResistance::new::<ohm>(reference_resistance),
+            temperature_coefficient,
+            ampacity: Some(ElectricCurrent::new::<ampere>(ampacity)),
+            heat_capacity: material.heat_capacity() * section * length,
+            thermal_conductance,
+
+            ambient_temperature: ThermodynamicTemperature::new::<degree_celsius>(
+                REFERENCE_TEMPERATURE,
+            ),
+            temperature: ThermodynamicTemperature::new::<degree_celsius>(REFERENCE_TEMPERATURE),
+            current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    // Bus bars and links, constant resistance and no current limit
+    pub fn fixed(resistance: f64) -> Self {
+        Wire {
+            reference_resistance: ElectricalResistance::new::<ohm>(resistance),
+            temperature_coefficient: 0.0,
+            ampacity: None,
+            heat_capacity: 0.0,
+            thermal_conductance: 0.0,
+
+            ambient_temperature: ThermodynamicTemperature::new::<degree_celsius>(
+                REFERENCE_TEMPERATURE,
+            ),
+            temperature: ThermodynamicTemperature::new::<degree_celsius>(REFERENCE_TEMPERATURE),
+            current: ElectricCurrent::new::<ampere>(0.0),
+        }
+    }
+
+    pub fn resistance(&self) -> ElectricalResistance {
+        let rise = self.temperature.get::<degree_celsius>() - REFERENCE_TEMPERATURE;
+        self.reference_resistance * (1.0 + self.temperature_coefficient * rise).max(0.1)
+    }
+
+    pub fn ampacity(&self) -> Option<ElectricCurrent> {
+        self.ampacity
+    }
+
+    pub fn current(&self) -> ElectricCurrent {
+        self.current
+    }
+
+    pub fn temperature(&self) -> ThermodynamicTemperature {
+        self.temperature
+    }
+
+    // Judged on the conductor temperature rather than the instantaneous current, so a motor inrush or a
+    // short peak the wire rides through is not reported while a sustained overload is
+    pub fn is_overcurrent(&self) -> bool {
+        self.ampacity.is_some()
+            && self.temperature.get::<degree_celsius>() > RATED_TEMPERATURE
+    }
+
+    // Temperature of the zone the wire runs through
+    pub fn set_ambient_temperature(&mut self, temperature: ThermodynamicTemperature) {
+        self.ambient_temperature = temperature;
+        if self.heat_capacity <= 0.0 {
+            self.temperature = temperature;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, current: ElectricCurrent) {
+        self.current = current;
+        if self.heat_capacity <= 0.0 {
+            return;
+        }
+
+        let temperature = self.temperature.get::<degree_celsius>();
+        let ambient = self.ambient_temperature.get::<degree_celsius>();
+        let amps = current.get::<ampere>();
+        let heating = amps * amps * self.resistance().get::<ohm>();
+
+        // Exponential step towards the equilibrium so large frames stay stable
+        let equilibrium = ambient + heating / self.thermal_conductance;
+        let alpha = 1.0 - (-(dt as f64) * self.thermal_conductance / self.heat_capacity).exp();
+        self.temperature = ThermodynamicTemperature::new::<degree_celsius>(
+            temperature + (equilibrium - temperature) * alpha,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::electrical::ElectricalSystem;
+    use crate::systems::electrical::components::dc::battery::Battery;
+    use crate::systems::electrical::components::dc::generic_dc_component::{
+        GenericDcComponent, VoltageResponse,
+    };
+    use crate::systems::electrical::components::shared::bus::Bus;
+    use uom::si::electric_potential::volt;
+    use uom::si::power::watt;
+
+    fn ampacity(wire: &Wire) -> f64 {
+        wire.ampacity().unwrap().get::<ampere>()
+    }
+
+    fn celsius(temperature: f64) -> ThermodynamicTemperature {
+        ThermodynamicTemperature::new::<degree_celsius>(temperature)
+    }
+
+    #[test]
+    fn ampacity_follows_the_gauge() {
+        let awg_20 = Wire::new(20, 1.0, WireMaterial::Copper, 1);
+        let awg_4_0 = Wire::new(-3, 1.0, WireMaterial::Copper, 1);
+        assert!((ampacity(&awg_20) - 11.0).abs() < 0.5);
+        assert!((ampacity(&awg_4_0) - 413.0).abs() < 5.0);
+
+        let aluminium = Wire::new(-3, 1.0, WireMaterial::Aluminium, 1);
+        assert!(
+            (ampacity(&aluminium) / ampacity(&awg_4_0) - ALUMINIUM_AMPACITY_RATIO).abs() < 1e-9
+        );
+        assert!(Wire::fixed(0.01).ampacity().is_none());
+    }
+
+    #[test]
+    fn bundled_wires_are_derated() {
+        let single = ampacity(&Wire::new(12, 1.0, WireMaterial::Copper, 1));
+        let five = ampacity(&Wire::new(12, 1.0, WireMaterial::Copper, 5));
+        let twenty = ampacity(&Wire::new(12, 1.0, WireMaterial::Copper, 20));
+        assert!((five / single - 0.73).abs() < 0.01);
+        assert!((twenty / single - 0.55).abs() < 0.01);
+    }
+
+    #[test]
+    fn resistance_rises_with_the_conductor_temperature() {
+        let mut wire = Wire::new(12, 10.0, WireMaterial::Copper, 1);
+        let cold = wire.resistance().get::<ohm>();
+        // 10 m of 12 AWG copper is about 52 milliohm
+        assert!((cold - 0.052).abs() < 0.002);
+
+        wire.set_ambient_temperature(celsius(120.0));
+        for _ in 0..3000 {
+            wire.update(1.0, ElectricCurrent::new::<ampere>(0.0));
+        }
+        assert!((wire.temperature().get::<degree_celsius>() - 120.0).abs() < 0.01);
+        let hot = wire.resistance().get::<ohm>();
+        assert!((hot / cold - (1.0 + 0.00393 * 100.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wire_at_its_ampacity_settles_at_the_rated_temperature() {
+        let mut wire = Wire::new(20, 1.0, WireMaterial::Copper, 3);
+        let current = wire.ampacity().unwrap();
+        for _ in 0..1000 {
+            wire.update(1.0, current);
+        }
+        assert!((wire.temperature().get::<degree_celsius>() - RATED_TEMPERATURE).abs() < 0.1);
+        assert!(!wire.is_overcurrent());
+
+        // A sustained overload takes the conductor over its rating
+        for _ in 0..60 {
+            wire.update(1.0, current * 1.1);
+        }
+        assert!(wire.is_overcurrent());
+    }
+
+    #[test]
+    fn short_peak_is_not_an_overcurrent() {
+        // Three times the ampacity for a second, like a motor start, then back to the rated current
+        let mut wire = Wire::new(12, 5.0, WireMaterial::Copper, 1);
+        let current = wire.ampacity().unwrap();
+        for _ in 0..10 {
+            wire.update(0.1, current * 3.0);
+            assert!(!wire.is_overcurrent());
+        }
+        for _ in 0..1000 {
+            wire.update(1.0, current);
+            assert!(!wire.is_overcurrent());
+        }
+    }
+
+    #[test]
+    fn overcurrent_is_checked_against_each_wire_of_its_own() {
+        // 400 W at 24 V is ~12 A through a 20 AWG feeder (11 A) and a 12 AWG branch (~39 A)
+        let mut system = ElectricalSystem::new();
+        let battery = Battery::new("Battery", 24.0, 44.0, 0.02, 1.0);
+        let battery = system.add_component("Battery", battery).unwrap();
+        let bus = Bus {
+            voltage: ElectricPotential::new::<volt>(0.0),
+            power: Power::new::<watt>(0.0),
+        };
+        let bus = system.add_component("DC Bus", bus).unwrap();
+        let mut load =
+            GenericDcComponent::new("Load", 28.0, 400.0, 0.0, 32.0, VoltageResponse::Linear, 1.0);
+        load.set_power_state(true);
+        let load = system.add_component("Load", load).unwrap();
+        system.connect_with_wire(battery, bus, Wire::new(20, 1.0, WireMaterial::Copper, 1));
+        system.connect_with_wire(bus, load, Wire::new(12, 1.0, WireMaterial::Copper, 1));
+        for _ in 0..300 {
+            system.update_system(1.0);
+        }
+
+        let overcurrents = system.check_overcurrent();
+        assert_eq!(overcurrents.len(), 1);
+        let (from, to, current, ampacity) = overcurrents[0];
+        assert_eq!((from, to), (battery.node(), bus.node()));
+        assert!(current > ampacity);
+        assert_eq!(
+            Some(ampacity),
+            system.wire(battery, bus).unwrap().ampacity()
+        );
+    }
+}
@@ -1,13 +1,38 @@
+use crate::communication_bus::CommunicationBus;
+use crate::systems::electrical::bus_power_control_unit::{
+    BusPowerControlUnit, LoadShedLevel, ShedLoad, SourceKind,
+};
+use crate::systems::electrical::circuit_breaker_database::{
+    AircraftVariant, BreakerDatabaseError, CircuitBreakerDatabase, PanelLocation,
+};
+use crate::systems::electrical::components::ac::generic_ac_component::GenericAcComponent;
+use crate::systems::electrical::components::dc::generic_dc_component::GenericDcComponent;
+use crate::systems::electrical::components::dc::transformer_rectifier_unit::TransformerRectifierUnit;
+use crate::systems::electrical::components::shared::bus::Bus;
+use crate::systems::electrical::components::shared::circuit_breaker::{
+    BreakerState, CircuitBreaker,
+};
+use crate::systems::electrical::components::shared::contactor::Contactor;
+use crate::systems::electrical::components::shared::motor::Motor;
 use crate::systems::electrical::components::{
-    ac::generator::Generator,
-    dc::generic_dc_component::{GenericDcComponent, VoltageResponse},
-    shared::bus::Bus,
-    shared::circuit_breaker::{CircuitBreaker, TripCurve},
+    ac::air_driven_generator::AirDrivenGenerator, ac::generator::Generator,
+    ac::ground_power::GroundPower, ac::integrated_drive_generator::IntegratedDriveGenerator,
 };
-use crate::systems::electrical::{ElectricalComponent, ElectricalComponentExt, ElectricalSystem};
-use uom::si::electric_potential::volt;
+use crate::systems::electrical::generator_control_unit::GeneratorControlUnit;
+use crate::systems::electrical::network_loader::{NetworkLoadError, parse_network};
+use crate::systems::electrical::secondary_power_distribution::{
+    ElectronicCircuitBreakerPage, SecondaryPowerDistribution,
+};
+use crate::systems::electrical::{ComponentHandle, ElectricalSystem};
+use petgraph::graph::NodeIndex;
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use uom::si::angular_velocity::revolution_per_minute;
 use uom::si::f64::*;
 use uom::si::power::watt;
+use uom::si::ratio::percent;
+use uom::si::velocity::knot;
 pub mod air_conditioning;
 pub mod airborne_auxiliary_power;
 pub mod auto_flight;
@@ -29,115 +54,437 @@ pub mod oxygen;
 pub mod pneumatic;
 pub mod water_waste;
 
+// The aircraft data is built into the binary, nothing has to be found on disk wherever it runs
+pub const ELECTRICAL_NETWORK: &str = include_str!("../../assets/electrical/e170.toml");
+pub const CIRCUIT_BREAKERS: &str = include_str!("../../assets/electrical/circuit_breakers.toml");
+const AIRCRAFT_VARIANT: AircraftVariant = AircraftVariant::E170;
+
+#[derive(Debug)]
+pub enum SystemsError {
+    ElectricalNetwork(Vec<NetworkLoadError>),
+    CircuitBreakers(Vec<BreakerDatabaseError>),
+}
+
+impl fmt::Display for SystemsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemsError::ElectricalNetwork(errors) => {
+                write!(f, "invalid electrical network")?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+            SystemsError::CircuitBreakers(errors) => {
+                write!(f, "invalid circuit breaker catalogue")?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 // We will construct the entire aircraft from here.
 // I constructed this outside of the main loop so any value inside this struct will be preserved between frames.
 pub struct E170Systems {
     electrical_system: ElectricalSystem,
     elapsed_time: f32,
-    generator_node: petgraph::graph::NodeIndex,
+    idg_1: ComponentHandle<IntegratedDriveGenerator>,
+    idg_2: ComponentHandle<IntegratedDriveGenerator>,
+    adg: ComponentHandle<AirDrivenGenerator>,
+    ground_power: ComponentHandle<GroundPower>,
+    apu_generator: ComponentHandle<Generator>,
     generator_on: bool,
+    apu_running: bool,
+    airspeed: Velocity,
+    on_ground: bool,
+    gcu_1: GeneratorControlUnit,
+    gcu_2: GeneratorControlUnit,
+    gcu_apu: GeneratorControlUnit,
+    bpcu: BusPowerControlUnit,
+    spda: SecondaryPowerDistribution,
+    ecb_page: ElectronicCircuitBreakerPage,
+    circuit_breakers: CircuitBreakerDatabase,
+    // Wires over their ampacity at the last update
+    overcurrents: Vec<(NodeIndex, NodeIndex)>,
 }
 
 impl E170Systems {
-    pub fn new() -> E170Systems {
-        let mut electrical_system = ElectricalSystem::new();
+    pub fn new() -> Result<E170Systems, SystemsError> {
+        let mut electrical_system =
+            parse_network(ELECTRICAL_NETWORK).map_err(SystemsError::ElectricalNetwork)?;
+        let circuit_breakers = CircuitBreakerDatabase::parse(CIRCUIT_BREAKERS)
+            .and_then(|database| {
+                database.instantiate(&mut electrical_system, AIRCRAFT_VARIANT)?;
+                Ok(database)
+            })
+            .map_err(SystemsError::CircuitBreakers)?;
 
-        // Test electrical system construction
+        // The controllers are wired to their components by name
+        let system = &electrical_system;
+        let idg_1 = component::<IntegratedDriveGenerator>(system, "IDG 1");
+        let idg_2 = component::<IntegratedDriveGenerator>(system, "IDG 2");
+        let apu_generator = component::<Generator>(system, "APU GEN");
+        let ground_power = component::<GroundPower>(system, "GPU");
+        let adg = component::<AirDrivenGenerator>(system, "ADG");
 
-        let main_generator = Generator::new(2.0, 90000.0, 115.0, 400.0, 0.95, 0.05, 0.0, 3);
-        let generator_node = electrical_system.add_component("Main Generator", main_generator);
+        let gcu_1 = GeneratorControlUnit::new(
+            "GCU 1",
+            idg_1,
+            component::<Contactor>(system, "GLC 1"),
+            261.0,
+        );
+        let gcu_2 = GeneratorControlUnit::new(
+            "GCU 2",
+            idg_2,
+            component::<Contactor>(system, "GLC 2"),
+            261.0,
+        );
+        let gcu_apu = GeneratorControlUnit::new(
+            "GCU APU",
+            apu_generator,
+            component::<Contactor>(system, "APU GLC"),
+            116.0,
+        );
 
-        let main_bus = Bus {
-            voltage: ElectricPotential::new::<volt>(28.0),
-            power: Power::new::<watt>(0.0),
-        };
+        let mut bpcu = BusPowerControlUnit::new("BPCU");
+        bpcu.add_source("GEN 1", SourceKind::Main);
+        bpcu.add_source("GEN 2", SourceKind::Main);
+        bpcu.add_source("GPU", SourceKind::External);
+        bpcu.add_source("APU GEN", SourceKind::Main);
+        bpcu.add_source_priority(&["GEN 1", "GPU", "APU GEN"]);
+        bpcu.add_source_priority(&["GEN 2", "GPU", "APU GEN"]);
+        bpcu.add_source("ADG", SourceKind::Emergency);
+        bpcu.add_bus_tie(component::<Contactor>(system, "BTC 1"), "GEN 1");
+        bpcu.add_bus_tie(component::<Contactor>(system, "BTC 2"), "GEN 2");
+        bpcu.add_dc_bus_tie(
+            component::<Contactor>(system, "DC BTC"),
+            &[
+                component::<TransformerRectifierUnit>(system, "TRU 1").into(),
+                component::<TransformerRectifierUnit>(system, "TRU 2").into(),
+            ],
+        );
+        bpcu.add_bus_transfer(
+            "AC ESS Bus",
+            &[
+                (
+                    component::<Contactor>(system, "AC ESS XFR 1"),
+                    component::<Bus>(system, "AC Bus 1").into(),
+                ),
+                (
+                    component::<Contactor>(system, "AC ESS XFR 2"),
+                    component::<Bus>(system, "AC Bus 2").into(),
+                ),
+                (component::<Contactor>(system, "AC ESS XFR ADG"), adg.into()),
+            ],
+            100.0,
+        );
+        bpcu.add_bus_transfer(
+            "DC ESS Bus",
+            &[
+                (
+                    component::<Contactor>(system, "DC ESS XFR 1"),
+                    component::<Bus>(system, "DC Bus 1").into(),
+                ),
+                (
+                    component::<Contactor>(system, "DC ESS XFR 2"),
+                    component::<Bus>(system, "DC Bus 2").into(),
+                ),
+                (
+                    component::<Contactor>(system, "DC ESS XFR BATT"),
+                    component::<Bus>(system, "Hot Battery Bus 1").into(),
+                ),
+            ],
+            18.0,
+        );
+        bpcu.add_coil_supply(component::<Bus>(system, "DC ESS Bus"));
+        bpcu.add_coil_supply(component::<Bus>(system, "Hot Battery Bus 1"));
+        bpcu.add_shed_load(shed_load(system, "Galley"), LoadShedLevel::SingleSource);
+        bpcu.add_shed_load(shed_load(system, "Test Light"), LoadShedLevel::Emergency);
+        bpcu.add_shed_load(shed_load(system, "BATT 1 CHG"), LoadShedLevel::Emergency);
 
-        let main_bus_node = electrical_system.add_component("Main Bus", main_bus);
+        // The SPDA and the MCDU electronic breaker page share a data bus of their own
+        let spda_bus = Arc::new(CommunicationBus::new());
 
-        electrical_system.connect_no_resistance(generator_node, main_bus_node);
+        Ok(E170Systems {
+            electrical_system,
+            elapsed_time: 0.0,
+            idg_1,
+            idg_2,
+            adg,
+            ground_power,
+            apu_generator,
+            generator_on: false,
+            apu_running: false,
+            airspeed: Velocity::new::<knot>(0.0),
+            on_ground: true,
+            gcu_1,
+            gcu_2,
+            gcu_apu,
+            bpcu,
+            spda: SecondaryPowerDistribution::new("SPDA", spda_bus.clone()),
+            ecb_page: ElectronicCircuitBreakerPage::new(spda_bus),
+            circuit_breakers,
+            overcurrents: Vec::new(),
+        })
+    }
+
+    pub fn set_airspeed(&mut self, airspeed: f64) {
+        self.airspeed = Velocity::new::<knot>(airspeed);
+    }
 
-        let avionics_cb =
-            CircuitBreaker::new("Avionics CB", 15.0, TripCurve::ShortDelay(0.2), false, 0.0);
-        let avionics_cb_node = electrical_system.add_component("Avionics CB", avionics_cb);
+    // Weight on wheels
+    pub fn set_on_ground(&mut self, on_ground: bool) {
+        self.on_ground = on_ground;
+    }
 
-        let lights_cb =
-            CircuitBreaker::new("Lights CB", 10.0, TripCurve::ShortDelay(0.1), true, 5.0);
+    pub fn electrical_system(&self) -> &ElectricalSystem {
+        &self.electrical_system
+    }
 
-        let lights_cb_node = electrical_system.add_component("Lights CB", lights_cb);
+    // Live network for scenarios, fault injection and maintenance actions
+    pub fn electrical_system_mut(&mut self) -> &mut ElectricalSystem {
+        &mut self.electrical_system
+    }
 
-        electrical_system.connect_no_resistance(main_bus_node, avionics_cb_node);
-        electrical_system.connect_no_resistance(main_bus_node, lights_cb_node);
+    // Cockpit breaker panels, breakers are found by their panel coordinate
+    pub fn pull_circuit_breaker(&mut self, location: &PanelLocation) -> bool {
+        let Some(breaker) = self.circuit_breaker_at(location) else {
+            return false;
+        };
+        breaker.pull();
+        true
+    }
 
-        let test_display = GenericDcComponent::new(
-            "Test Display",
-            28.0,
-            120.0,
-            21.0,
-            32.0,
-            VoltageResponse::Regulated,
-            0.85,
-        );
+    // Push a breaker back in, false if there is none at `location` or it refuses to latch
+    pub fn push_circuit_breaker(&mut self, location: &PanelLocation) -> bool {
+        let Some(handle) =
+            self.circuit_breakers
+                .breaker_at(&self.electrical_system, AIRCRAFT_VARIANT, location)
+        else {
+            return false;
+        };
+        let Some(breaker) = self.electrical_system.get(handle) else {
+            return false;
+        };
+        let current = self
+            .electrical_system
+            .prospective_current(handle, breaker.closed_circuit_model());
+        let Some(breaker) = self.electrical_system.get_mut(handle) else {
+            return false;
+        };
+        if breaker.reset(current) {
+            return true;
+        }
+        let reason = if breaker.state() == BreakerState::Collared {
+            "collared".to_string()
+        } else if breaker.would_trip(current) {
+            format!("{:.1} A fault still present", current.value.abs())
+        } else {
+            "still hot".to_string()
+        };
+        eprintln!("⚠️ {} REFUSED TO LATCH: {reason}", breaker.name());
+        false
+    }
 
-        let test_display_node = electrical_system.add_component("Test Display", test_display);
+    pub fn circuit_breaker_state(&self, location: &PanelLocation) -> Option<BreakerState> {
+        let handle = self.circuit_breakers.breaker_at(
+            &self.electrical_system,
+            AIRCRAFT_VARIANT,
+            location,
+        )?;
+        self.electrical_system
+            .get(handle)
+            .map(|breaker| breaker.state())
+    }
 
-        let test_light = GenericDcComponent::new(
-            "Test Light",
-            28.0,
-            200.0,
-            20.0,
-            32.0,
-            VoltageResponse::Binary,
-            0.9,
-        );
+    // MCDU electronic circuit breaker page, works the SSPCs through the SPDA
+    pub fn ecb_page(&self) -> &ElectronicCircuitBreakerPage {
+        &self.ecb_page
+    }
 
-        let test_light_node = electrical_system.add_component("Test Light", test_light);
+    fn circuit_breaker_at(&mut self, location: &PanelLocation) -> Option<&mut CircuitBreaker> {
+        let handle = self.circuit_breakers.breaker_at(
+            &self.electrical_system,
+            AIRCRAFT_VARIANT,
+            location,
+        )?;
+        self.electrical_system.get_mut(handle)
+    }
 
-        electrical_system.connect_with_wire(avionics_cb_node, test_display_node, 0.01);
-        electrical_system.connect_with_wire(lights_cb_node, test_light_node, 0.02);
+    // Engine `engine` (1 or 2) N2, commanding the engines takes over from the automatic start. False and
+    // nothing changed for any other engine number.
+    pub fn set_engine_n2(&mut self, engine: usize, n2: f64) -> bool {
+        let idg = match engine {
+            1 => self.idg_1,
+            2 => self.idg_2,
+            _ => return false,
+        };
+        self.generator_on = true;
+        if let Some(idg) = self.electrical_system.get_mut(idg) {
+            idg.set_engine_n2(Ratio::new::<percent>(n2));
+        }
+        true
+    }
 
-        E170Systems {
-            electrical_system,
-            elapsed_time: 0.0,
-            generator_node,
-            generator_on: false,
+    // False and nothing changed for an engine number other than 1 or 2
+    pub fn set_generator_switch(&mut self, engine: usize, on: bool) -> bool {
+        let gcu = match engine {
+            1 => &mut self.gcu_1,
+            2 => &mut self.gcu_2,
+            _ => return false,
+        };
+        gcu.set_generator_switch(on);
+        self.generator_on = true;
+        true
+    }
+
+    // Plugs in the ground power cart and presses the GPU button
+    pub fn set_ground_power_connected(&mut self, connected: bool) {
+        if let Some(ground_power) = self.electrical_system.get_mut(self.ground_power) {
+            if connected {
+                ground_power.connect();
+            } else {
+                ground_power.disconnect();
+            }
+            ground_power.set_selected(connected);
         }
     }
 
+    // Switches a consumer on or off by name, false if there is no such load
+    pub fn set_load_power(&mut self, name: &str, on: bool) -> bool {
+        let system = &mut self.electrical_system;
+        if let Some(load) = system.handle::<GenericDcComponent>(name) {
+            return system
+                .get_mut(load)
+                .map(|load| load.set_power_state(on))
+                .is_some();
+        }
+        if let Some(load) = system.handle::<GenericAcComponent>(name) {
+            return system
+                .get_mut(load)
+                .map(|load| load.set_power_state(on))
+                .is_some();
+        }
+        let Some(motor) = system.handle::<Motor>(name) else {
+            return false;
+        };
+        system
+            .get_mut(motor)
+            .map(|motor| motor.set_power_state(on))
+            .is_some()
+    }
+
+    pub fn set_apu_running(&mut self, running: bool) {
+        self.apu_running = running;
+        self.gcu_apu.set_generator_switch(running);
+    }
+
     pub fn update(&mut self, dt: f32) {
         self.elapsed_time += dt;
         if !self.generator_on && self.elapsed_time > 3.0 {
-            if let Some(component) = self
-                .electrical_system
-                .components
-                .get_mut(&self.generator_node)
-            {
-                if let Some(generator) = component.downcast_mut::<Generator>() {
-                    generator.turn_on();
-                    generator.set_mechanical_input(80000.0, 6000.0); // Set some power and RPM
-                    println!(
-                        "🔌 Generator turned ON after {} seconds",
-                        self.elapsed_time / 1000.0
-                    );
-                    self.generator_on = true;
+            for idg in [self.idg_1, self.idg_2] {
+                if let Some(idg) = self.electrical_system.get_mut(idg) {
+                    idg.set_engine_n2(Ratio::new::<percent>(80.0));
                 }
             }
+            self.gcu_1.set_generator_switch(true);
+            self.gcu_2.set_generator_switch(true);
+            eprintln!("🔌 Generator turned ON after {} seconds", self.elapsed_time);
+            self.generator_on = true;
+        }
+        for idg in [self.idg_1, self.idg_2] {
+            if let Some(idg) = self.electrical_system.get_mut(idg) {
+                idg.set_on_ground(self.on_ground);
+            }
+        }
+        if let Some(apu_generator) = self.electrical_system.get_mut(self.apu_generator) {
+            // The APU runs at a governed speed
+            let (power, rpm) = if self.apu_running {
+                (
+                    apu_generator.rated_power().get::<watt>() * 1.5,
+                    apu_generator.rated_rpm().get::<revolution_per_minute>(),
+                )
+            } else {
+                (0.0, 0.0)
+            };
+            apu_generator.set_mechanical_input(power, rpm);
+        }
+
+        self.gcu_1
+            .set_bus_permission(self.bpcu.is_source_permitted("GEN 1"));
+        self.gcu_2
+            .set_bus_permission(self.bpcu.is_source_permitted("GEN 2"));
+        self.gcu_apu
+            .set_bus_permission(self.bpcu.is_source_permitted("APU GEN"));
+        self.gcu_1.update(dt, &mut self.electrical_system);
+        self.gcu_2.update(dt, &mut self.electrical_system);
+        self.gcu_apu.update(dt, &mut self.electrical_system);
+
+        if let Some(ground_power) = self.electrical_system.get_mut(self.ground_power) {
+            ground_power.set_permitted(self.bpcu.is_source_permitted("GPU"));
+            self.bpcu.set_source_available(
+                "GPU",
+                ground_power.is_available() && ground_power.is_selected(),
+            );
+        }
+
+        self.bpcu
+            .set_source_available("GEN 1", self.gcu_1.is_power_ready());
+        self.bpcu
+            .set_source_available("GEN 2", self.gcu_2.is_power_ready());
+        self.bpcu
+            .set_source_available("APU GEN", self.gcu_apu.is_power_ready());
+        let all_ac_lost = self.bpcu.shed_level() == LoadShedLevel::Emergency;
+        if let Some(adg) = self.electrical_system.get_mut(self.adg) {
+            adg.set_airspeed(self.airspeed);
+            adg.set_all_ac_lost(all_ac_lost);
+            self.bpcu.set_source_available("ADG", adg.is_available());
         }
+        self.bpcu.update(dt, &mut self.electrical_system);
+        self.spda.update(&mut self.electrical_system);
         self.electrical_system.update_system(dt);
 
-        let overcurrents = self.electrical_system.check_overcurrent(20.0);
-        if !overcurrents.is_empty() {
-            println!(
+        // Reported when a wire heats over its rating, not on every tick it stays there
+        let overcurrents = self.electrical_system.check_overcurrent();
+        let new_overcurrents: Vec<_> = overcurrents
+            .iter()
+            .filter(|(from, to, ..)| !self.overcurrents.contains(&(*from, *to)))
+            .collect();
+        if !new_overcurrents.is_empty() {
+            eprintln!(
                 "\n⚠️ OVERCURRENT DETECTED in {} connections:",
-                overcurrents.len()
+                new_overcurrents.len()
             );
-            for (from, to, current) in &overcurrents {
-                let from_name = self.electrical_system.graph.node_weight(*from).unwrap();
-                let to_name = self.electrical_system.graph.node_weight(*to).unwrap();
-                println!(
-                    "  - {from_name} → {to_name}: {:.2} A (EXCESSIVE!)",
-                    current.value
+            for (from, to, current, ampacity) in new_overcurrents {
+                let from_name = self.electrical_system.component_name(*from).unwrap();
+                let to_name = self.electrical_system.component_name(*to).unwrap();
+                eprintln!(
+                    "  - {from_name} → {to_name}: {:.2} A on {:.0} A ampacity, over its rated temperature",
+                    current.value.abs(),
+                    ampacity.value
                 );
             }
         }
+        self.overcurrents = overcurrents
+            .into_iter()
+            .map(|(from, to, ..)| (from, to))
+            .collect();
     }
 }
+
+fn component<T: Any>(system: &ElectricalSystem, name: &str) -> ComponentHandle<T> {
+    system.handle::<T>(name).unwrap_or_else(|| {
+        panic!(
+            "the electrical network has no {} named \"{name}\"",
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn shed_load(system: &ElectricalSystem, name: &str) -> ShedLoad {
+    ShedLoad::find(system, name)
+        .unwrap_or_else(|| panic!("the electrical network has no sheddable load \"{name}\""))
+}
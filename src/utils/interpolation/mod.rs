@@ -0,0 +1,17 @@
+// Piecewise linear lookup in a (x, y) table sorted by x, clamped to the first and last points.
+pub fn interpolate(table: &[(f64, f64)], x: f64) -> f64 {
+    let (first, last) = (table[0], table[table.len() - 1]);
+    if x <= first.0 {
+        return first.1;
+    }
+    if x >= last.0 {
+        return last.1;
+    }
+    for window in table.windows(2) {
+        let ((x0, y0), (x1, y1)) = (window[0], window[1]);
+        if x <= x1 {
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    last.1
+}
@@ -1 +1,2 @@
-pub mod delta_time;
\ No newline at end of file
+pub mod delta_time;
+pub mod interpolation;